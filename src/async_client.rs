@@ -0,0 +1,163 @@
+use crate::client::{next_link, parse_body};
+use crate::error::{CanvasError, Result};
+use crate::models::*;
+use crate::retry::{RateLimiter, RetryConfig};
+use futures_util::future::try_join_all;
+use reqwest::{Client, Response};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Non-blocking Canvas client for fetching many resources at once.
+///
+/// At most `max_concurrency` requests are in flight at a time, and every
+/// request first waits out the same rate limit pause as `CanvasClient`, so
+/// concurrent requests slow down together as Canvas's bucket drains.
+pub struct AsyncCanvasClient {
+    base_url: String,
+    access_token: String,
+    client: Client,
+    per_page: Option<u32>,
+    retry: RetryConfig,
+    rate_limiter: RateLimiter,
+    permits: Arc<Semaphore>,
+}
+
+impl AsyncCanvasClient {
+    pub fn new(base_url: &str, access_token: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            access_token: access_token.to_string(),
+            client: Client::new(),
+            per_page: None,
+            retry: RetryConfig::default(),
+            rate_limiter: RateLimiter::default(),
+            permits: Arc::new(Semaphore::new(1)),
+        }
+    }
+
+    /// Request `per_page` items per page from list endpoints
+    pub fn with_per_page(mut self, per_page: Option<u32>) -> Self {
+        self.per_page = per_page;
+        self
+    }
+
+    /// Override the throttling and retry limits
+    pub fn with_retry_config(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Allow up to `max_concurrency` requests in flight at once
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.permits = Arc::new(Semaphore::new(max_concurrency.max(1)));
+        self
+    }
+
+    /// Send a GET request, retrying throttled, transient and server errors with backoff
+    async fn send_get(&self, url: &str) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            match self.send_once(url).await {
+                Err(e) if attempt < self.retry.max_retries && RetryConfig::is_retryable(&e) => {
+                    tokio::time::sleep(self.retry.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send_once(&self, url: &str) -> Result<Response> {
+        let _permit = self.permits.acquire().await.expect("semaphore is never closed");
+        let delay = self.rate_limiter.delay(&self.retry);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        let resp = self.client.get(url).bearer_auth(&self.access_token).send().await?;
+        self.rate_limiter.update(resp.headers());
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let url = resp.url().to_string();
+        let body = resp.text().await.unwrap_or_default();
+        Err(CanvasError::from_response(status, &url, &body))
+    }
+
+    /// Fetch pages of a list endpoint for as long as `keep` accepts their items.
+    ///
+    /// Returns the accepted items and whether the whole list was read.
+    pub async fn get_while<T: DeserializeOwned>(&self, url: &str, mut keep: impl FnMut(&T) -> bool) -> Result<(Vec<T>, bool)> {
+        let mut next_url = Some(match self.per_page {
+            Some(per_page) => {
+                let sep = if url.contains('?') { '&' } else { '?' };
+                format!("{}{}per_page={}", url, sep, per_page)
+            }
+            None => url.to_string(),
+        });
+        let mut items = Vec::new();
+        while let Some(url) = next_url.take() {
+            let resp = self.send_get(&url).await?;
+            next_url = next_link(resp.headers());
+            let page: Vec<T> = parse_body(&resp.text().await?)?;
+            for item in page {
+                if !keep(&item) {
+                    return Ok((items, false));
+                }
+                items.push(item);
+            }
+        }
+        Ok((items, true))
+    }
+
+    /// Fetch every page of a list endpoint
+    async fn get_all<T: DeserializeOwned>(&self, url: &str) -> Result<Vec<T>> {
+        self.get_while(url, |_| true).await.map(|(items, _)| items)
+    }
+
+    pub async fn get_courses(&self) -> Result<Vec<Course>> {
+        self.get_all(&format!("{}/api/v1/courses", self.base_url)).await
+    }
+
+    pub async fn get_assignments(&self, course_id: u64) -> Result<Vec<Assignment>> {
+        self.get_all(&format!("{}/api/v1/courses/{}/assignments", self.base_url, course_id)).await
+    }
+
+    /// Every announcement of a course, listed as discussion topics (see `CanvasClient::get_announcements`)
+    pub async fn get_announcements(&self, course_id: u64) -> Result<Vec<Announcement>> {
+        let url = format!("{}/api/v1/courses/{}/discussion_topics?only_announcements=true", self.base_url, course_id);
+        self.get_all(&url).await
+    }
+
+    /// Wiki pages of a course, bodies included, most recently updated first,
+    /// for as long as `keep` accepts them
+    pub async fn pages_by_update(&self, course_id: u64, keep: impl FnMut(&Page) -> bool) -> Result<(Vec<Page>, bool)> {
+        let url = format!("{}/api/v1/courses/{}/pages?include[]=body&sort=updated_at&order=desc", self.base_url, course_id);
+        self.get_while(&url, keep).await
+    }
+
+    /// Discussion topics, most recently active first, for as long as `keep` accepts them
+    pub async fn discussions_by_activity(&self, course_id: u64, keep: impl FnMut(&Discussion) -> bool) -> Result<(Vec<Discussion>, bool)> {
+        let url = format!("{}/api/v1/courses/{}/discussion_topics?order_by=recent_activity", self.base_url, course_id);
+        self.get_while(&url, keep).await
+    }
+
+    /// Fetch modules with their items inline, saving a request per module.
+    ///
+    /// Canvas leaves `items` out for large modules; those are fetched separately, concurrently.
+    pub async fn get_modules_with_items(&self, course_id: u64) -> Result<Vec<Module>> {
+        let url = format!("{}/api/v1/courses/{}/modules?include[]=items", self.base_url, course_id);
+        let mut modules: Vec<Module> = self.get_all(&url).await?;
+        let missing = modules.iter_mut().filter(|m| m.items.is_none());
+        try_join_all(missing.map(|module| async move {
+            module.items = Some(self.get_module_items(course_id, module.id).await?);
+            Ok::<_, CanvasError>(())
+        })).await?;
+        Ok(modules)
+    }
+
+    pub async fn get_module_items(&self, course_id: u64, module_id: u64) -> Result<Vec<ModuleItem>> {
+        self.get_all(&format!("{}/api/v1/courses/{}/modules/{}/items", self.base_url, course_id, module_id)).await
+    }
+}
@@ -0,0 +1,188 @@
+use crate::models::{Announcement, Assignment, Discussion, Module};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Something worth telling the user about, noticed while syncing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    NewAssignment,
+    DueDateChanged,
+    PointsChanged,
+    NewAnnouncement,
+    NewDiscussion,
+    NewReply,
+    ModuleUnlocked,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::NewAssignment => "new_assignment",
+            ChangeKind::DueDateChanged => "due_date_changed",
+            ChangeKind::PointsChanged => "points_changed",
+            ChangeKind::NewAnnouncement => "new_announcement",
+            ChangeKind::NewDiscussion => "new_discussion",
+            ChangeKind::NewReply => "new_reply",
+            ChangeKind::ModuleUnlocked => "module_unlocked",
+        }
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ChangeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "new_assignment" => Ok(ChangeKind::NewAssignment),
+            "due_date_changed" => Ok(ChangeKind::DueDateChanged),
+            "points_changed" => Ok(ChangeKind::PointsChanged),
+            "new_announcement" => Ok(ChangeKind::NewAnnouncement),
+            "new_discussion" => Ok(ChangeKind::NewDiscussion),
+            "new_reply" => Ok(ChangeKind::NewReply),
+            "module_unlocked" => Ok(ChangeKind::ModuleUnlocked),
+            _ => Err(format!("unknown change kind '{}'", s)),
+        }
+    }
+}
+
+/// One entry in the change log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub recorded_at: DateTime<Utc>,
+    pub course_id: u64,
+    /// Filled in when reading the log, from the cached course
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub course_code: Option<String>,
+    pub kind: ChangeKind,
+    /// ID of the assignment, announcement, discussion or module
+    pub resource_id: u64,
+    pub title: String,
+    pub detail: Option<String>,
+}
+
+impl Change {
+    pub fn new(recorded_at: DateTime<Utc>, course_id: u64, kind: ChangeKind, resource_id: u64, title: &str) -> Self {
+        Change { recorded_at, course_id, course_code: None, kind, resource_id, title: title.to_string(), detail: None }
+    }
+
+    fn with_detail(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+}
+
+/// Compare an incoming assignment with the stored one, if any
+pub fn assignment_changes(at: DateTime<Utc>, course_id: u64, old: Option<&Assignment>, new: &Assignment) -> Vec<Change> {
+    let change = |kind| Change::new(at, course_id, kind, new.id, &new.name);
+    let Some(old) = old else {
+        return vec![change(ChangeKind::NewAssignment).with_detail(format!("due {}", format_due(new.due_at)))];
+    };
+
+    let mut changes = Vec::new();
+    if old.due_at != new.due_at {
+        changes.push(change(ChangeKind::DueDateChanged)
+            .with_detail(format!("{} -> {}", format_due(old.due_at), format_due(new.due_at))));
+    }
+    if old.points_possible != new.points_possible {
+        changes.push(change(ChangeKind::PointsChanged)
+            .with_detail(format!("{} -> {}", format_points(old.points_possible), format_points(new.points_possible))));
+    }
+    changes
+}
+
+/// Compare an incoming discussion topic with the stored one, if any
+pub fn discussion_changes(at: DateTime<Utc>, course_id: u64, old: Option<&Discussion>, new: &Discussion) -> Vec<Change> {
+    let change = |kind| Change::new(at, course_id, kind, new.id, &new.title);
+    let Some(old) = old else {
+        return vec![change(ChangeKind::NewDiscussion)];
+    };
+
+    let replied = match (old.last_reply_at, new.last_reply_at) {
+        (Some(old), Some(new)) => new > old,
+        (None, Some(_)) => true,
+        _ => false,
+    };
+    if !replied {
+        return Vec::new();
+    }
+    let count = new.discussion_subentry_count - old.discussion_subentry_count;
+    let detail = match count {
+        1 => "1 new reply".to_string(),
+        n if n > 1 => format!("{} new replies", n),
+        _ => "new replies".to_string(),
+    };
+    vec![change(ChangeKind::NewReply).with_detail(detail)]
+}
+
+/// Compare an incoming module with the stored one, if any
+pub fn module_changes(at: DateTime<Utc>, course_id: u64, old: Option<&Module>, new: &Module) -> Vec<Change> {
+    match old {
+        Some(old) if old.state == "locked" && new.state != "locked" => {
+            vec![Change::new(at, course_id, ChangeKind::ModuleUnlocked, new.id, &new.name)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// An announcement posted after the last one seen
+pub fn announcement_change(at: DateTime<Utc>, course_id: u64, announcement: &Announcement) -> Change {
+    Change::new(at, course_id, ChangeKind::NewAnnouncement, announcement.id, &announcement.title)
+        .with_detail(format!("posted {}", announcement.posted_at.format("%Y-%m-%d %H:%M UTC")))
+}
+
+fn format_due(due_at: Option<DateTime<Utc>>) -> String {
+    match due_at {
+        Some(due_at) => due_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        None => "no due date".to_string(),
+    }
+}
+
+fn format_points(points: Option<f64>) -> String {
+    match points {
+        Some(points) => format!("{} pts", points),
+        None => "ungraded".to_string(),
+    }
+}
+
+/// Parse a `--since` value: a relative age such as `12h`, `3d` or `2w`, a date
+/// (`2024-03-01`, midnight UTC) or an RFC 3339 timestamp
+pub fn parse_since(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).expect("midnight exists").and_utc());
+    }
+
+    parse_age(value)
+        .ok()
+        .and_then(|age| now.checked_sub_signed(age))
+        .ok_or_else(|| format!("invalid time '{}' (expected e.g. 12h, 3d, 2w, 2024-03-01 or an RFC 3339 timestamp)", value))
+}
+
+/// Parse a length of time such as `30m`, `12h`, `3d` or `2w`
+pub fn parse_age(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let invalid = || format!("invalid length of time '{}' (expected e.g. 30m, 12h, 3d or 2w)", value);
+    let split = value.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (count, unit) = value.split_at(split);
+    let count: i64 = count.parse().map_err(|_| invalid())?;
+    let age = match unit {
+        "m" => Duration::try_minutes(count),
+        "h" => Duration::try_hours(count),
+        "d" => Duration::try_days(count),
+        "w" => Duration::try_weeks(count),
+        _ => None,
+    };
+    age.ok_or_else(invalid)
+}
@@ -1,14 +1,40 @@
 use clap::{Parser, Subcommand, Args};
+use crate::compose::TextSource;
+use crate::models::SubmissionType;
+use crate::output::Format;
+use crate::sync;
 
 #[derive(Parser)]
 #[command(name = "canvas-cli", about = "CLI tool for Canvas API", version = "0.1.0")]
 pub struct Cli {
-    #[arg(short, long)]
+    /// Canvas instance URL (overrides BASE_URL and the config file)
+    #[arg(short, long, global = true)]
     pub base_url: Option<String>,
 
-    #[arg(short, long)]
+    /// Canvas API access token (overrides ACCESS_KEY and the config file)
+    #[arg(short, long, global = true)]
     pub access_token: Option<String>,
 
+    /// Named profile from the config file (overrides CANVAS_PROFILE)
+    #[arg(short, long, global = true)]
+    pub profile: Option<String>,
+
+    /// Output format
+    #[arg(short, long, global = true, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+
+    /// Read from the local database instead of Canvas (run `sync` first)
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    /// Also show cached rows that have since been deleted in Canvas
+    #[arg(long, global = true)]
+    pub include_deleted: bool,
+
+    /// Local database file (default: $XDG_DATA_HOME/canvas-cli/<profile>.db)
+    #[arg(long = "db", global = true, value_name = "PATH")]
+    pub database: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -23,63 +49,390 @@ pub enum Commands {
     Assignments(CourseArgs),
     /// Show assignment details and submit work
     Assignment(AssignmentArgs),
+    /// Show your submission for an assignment, with grades and feedback
+    Submission(SubmissionArgs),
+    /// Comment on your submission for an assignment
+    Comment(CommentArgs),
     /// List course announcements
     Announcements(CourseArgs),
+    /// Show an announcement as text
+    Announcement(AnnouncementArgs),
     /// Show grades for a course
     Grades(CourseArgs),
     /// List upcoming todos
     Todos,
+    /// Show upcoming deadlines across all active courses
+    Due(DueArgs),
     /// List course modules
     Modules(CourseArgs),
     /// Show module items
     ModuleItems(ModuleArgs),
     /// List course discussions
     Discussions(CourseArgs),
+    /// Show a discussion topic and its entries as text
+    Discussion(DiscussionArgs),
     /// Show discussion entries
     DiscussionEntries(DiscussionArgs),
     /// Post to discussion
     PostDiscussion(PostDiscussionArgs),
+    /// List a course's wiki pages
+    Pages(CourseArgs),
+    /// Show a wiki page as text
+    Page(PageArgs),
+    /// List a course's folders
+    Folders(CourseArgs),
+    /// List a course's files
+    Files(FilesArgs),
+    /// Download course files or whole folders, resuming interrupted downloads
+    Download(DownloadArgs),
+    /// Keep a local directory in step with a course's files
+    Mirror(MirrorArgs),
     /// Sync data with local database
-    Sync,
+    Sync(SyncArgs),
+    /// Show what changed in Canvas, as noticed by `sync`
+    Whatsnew(WhatsnewArgs),
+    /// Search synced assignments, discussions, announcements and module items
+    Search(SearchArgs),
+    /// Export course data for other applications
+    Export(ExportArgs),
+    /// Inspect the effective configuration
+    Config(ConfigArgs),
+    /// Manage named profiles for different Canvas instances
+    Profile(ProfileArgs),
+    /// Manage short aliases for courses
+    Alias(AliasArgs),
+    /// Inspect and upgrade the local database
+    Db(DbArgs),
+}
+
+#[derive(Args)]
+pub struct SyncArgs {
+    /// Refetch everything instead of only what changed since the last sync
+    #[arg(long)]
+    pub full: bool,
+    /// Number of courses to fetch at once
+    #[arg(short, long, default_value_t = sync::DEFAULT_JOBS, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=32))]
+    pub jobs: usize,
+}
+
+#[derive(Args)]
+pub struct WhatsnewArgs {
+    /// Show changes since this time (e.g. 12h, 3d, 2w, 2024-03-01) instead of since you last looked
+    #[arg(long)]
+    pub since: Option<String>,
+    /// Only show changes in this course (ID, course code, name fragment or alias)
+    #[arg(short, long)]
+    pub course: Option<String>,
+}
+
+#[derive(Args)]
+pub struct DueArgs {
+    /// Show assignments due within this many days, and unsubmitted ones overdue by up to as many
+    #[arg(long, default_value_t = 14)]
+    pub days: u32,
+    /// Only show this course (ID, course code, name fragment or alias)
+    #[arg(short, long)]
+    pub course: Option<String>,
+}
+
+#[derive(Args)]
+pub struct SearchArgs {
+    /// Words to look for; results contain all of them
+    #[arg(required = true)]
+    pub query: Vec<String>,
+    /// Only search this course (ID, course code, name fragment or alias)
+    #[arg(short, long)]
+    pub course: Option<String>,
+    /// Show at most this many results
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub limit: usize,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[command(subcommand)]
+    pub command: ExportCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ExportCommand {
+    /// Write assignment due dates as an iCalendar (.ics) file
+    Ical(IcalArgs),
+}
+
+#[derive(Args)]
+pub struct IcalArgs {
+    /// File to write instead of standard output
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// Only export this course (ID, course code, name fragment or alias)
+    #[arg(short, long)]
+    pub course: Option<String>,
+    /// Keep running and rewrite the file this often (e.g. 30m, 6h)
+    #[arg(long, requires = "output")]
+    pub every: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Show each effective setting and where it came from
+    Show,
+}
+
+#[derive(Args)]
+pub struct DbArgs {
+    #[command(subcommand)]
+    pub command: DbCommand,
+}
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// Apply pending schema migrations, backing up the database first
+    Migrate,
+    /// Show the schema version and which migrations are applied
+    Status,
+}
+
+#[derive(Args)]
+pub struct ProfileArgs {
+    #[command(subcommand)]
+    pub command: ProfileCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ProfileCommand {
+    /// List configured profiles
+    List,
+    /// Add a profile, or update an existing one
+    Add(ProfileAddArgs),
+    /// Remove a profile
+    Remove(ProfileNameArgs),
+    /// Make a profile the default
+    Use(ProfileNameArgs),
+}
+
+#[derive(Args)]
+pub struct ProfileAddArgs {
+    /// Profile name
+    pub name: String,
+    /// Canvas instance URL
+    #[arg(long)]
+    pub base_url: Option<String>,
+    /// Canvas API access token
+    #[arg(long)]
+    pub access_token: Option<String>,
+    /// Also make this the default profile
+    #[arg(long = "use")]
+    pub make_default: bool,
+}
+
+#[derive(Args)]
+pub struct ProfileNameArgs {
+    /// Profile name
+    pub name: String,
+}
+
+#[derive(Args)]
+pub struct AliasArgs {
+    #[command(subcommand)]
+    pub command: AliasCommand,
+}
+
+#[derive(Subcommand)]
+pub enum AliasCommand {
+    /// List course aliases of the active profile
+    List,
+    /// Add an alias, or point an existing one at another course
+    Add(AliasAddArgs),
+    /// Remove an alias
+    Remove(AliasNameArgs),
+}
+
+#[derive(Args)]
+pub struct AliasAddArgs {
+    /// Alias name
+    pub name: String,
+    /// Course ID, course code or name fragment
+    pub course: String,
+}
+
+#[derive(Args)]
+pub struct AliasNameArgs {
+    /// Alias name
+    pub name: String,
 }
 
 #[derive(Args)]
 pub struct CourseArgs {
-    /// Course ID
-    pub course_id: u64,
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
+}
+
+#[derive(Args)]
+pub struct PageArgs {
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
+    /// Page URL slug (as in .../pages/<slug>), page ID or link to the page
+    pub page: String,
+}
+
+#[derive(Args)]
+pub struct FilesArgs {
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
+    /// Only list files in this folder and below (folder ID or path such as "Week 1/Slides")
+    #[arg(long)]
+    pub folder: Option<String>,
+}
+
+#[derive(Args)]
+pub struct DownloadArgs {
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
+    /// IDs of files to download
+    #[arg(required_unless_present = "folder")]
+    pub file_ids: Vec<u64>,
+    /// Download this folder and everything below it (folder ID or path, "/" for all files)
+    #[arg(long)]
+    pub folder: Option<String>,
+    /// Directory to download into
+    #[arg(short, long, default_value = ".")]
+    pub output: String,
+}
+
+#[derive(Args)]
+pub struct MirrorArgs {
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
+    /// Local directory to keep in step with the course files
+    pub dir: String,
+    /// Delete local files that are no longer on Canvas
+    #[arg(long)]
+    pub prune: bool,
 }
 
 #[derive(Args)]
 pub struct AssignmentArgs {
-    /// Course ID
-    pub course_id: u64,
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
     /// Assignment ID
     pub assignment_id: u64,
-    /// File to submit (optional)
-    #[arg(long)]
-    pub file_path: Option<String>,
+    /// File to submit; repeat to submit several files
+    #[arg(long = "file-path")]
+    pub file_paths: Vec<String>,
+    /// Submit text from a file (Markdown, or HTML if it ends in .html); use - for stdin
+    #[arg(long, conflicts_with_all = ["file_paths", "url"])]
+    pub text: Option<String>,
+    /// Write a text submission in $EDITOR
+    #[arg(long, conflicts_with_all = ["file_paths", "url", "text"])]
+    pub editor: bool,
+    /// Submit a URL
+    #[arg(long, conflicts_with = "file_paths")]
+    pub url: Option<String>,
+    /// Submission type (online_upload, online_text_entry, online_url); inferred when omitted
+    #[arg(long = "type")]
+    pub submission_type: Option<SubmissionType>,
+}
+
+impl AssignmentArgs {
+    /// The type of submission requested, inferred from the given options.
+    ///
+    /// Returns `None` when nothing should be submitted.
+    pub fn requested_submission_type(&self) -> Result<Option<SubmissionType>, String> {
+        let inferred = if !self.file_paths.is_empty() {
+            Some(SubmissionType::Upload)
+        } else if self.text.is_some() || self.editor {
+            Some(SubmissionType::TextEntry)
+        } else if self.url.is_some() {
+            Some(SubmissionType::Url)
+        } else {
+            None
+        };
+        match (self.submission_type, inferred) {
+            (Some(requested), Some(inferred)) if requested != inferred => Err(format!(
+                "--type {} does not match the options given, which make a {} submission",
+                requested, inferred
+            )),
+            (Some(SubmissionType::Upload), None) => Err("--type online_upload needs at least one --file-path".to_string()),
+            (Some(SubmissionType::Url), None) => Err("--type online_url needs --url".to_string()),
+            (requested, inferred) => Ok(requested.or(inferred)),
+        }
+    }
+
+    /// Where to read a text submission from; the editor is used when no file is given
+    pub fn text_source(&self) -> TextSource {
+        match &self.text {
+            Some(arg) => TextSource::from_path_arg(arg),
+            None => TextSource::Editor,
+        }
+    }
 }
 
 #[derive(Args)]
 pub struct ModuleArgs {
-    /// Course ID
-    pub course_id: u64,
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
     /// Module ID
     pub module_id: u64,
 }
 
+#[derive(Args)]
+pub struct SubmissionArgs {
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
+    /// Assignment ID
+    pub assignment_id: u64,
+}
+
+#[derive(Args)]
+pub struct CommentArgs {
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
+    /// Assignment ID
+    pub assignment_id: u64,
+    /// Comment text; use - for stdin, or leave out to write it in $EDITOR
+    pub message: Option<String>,
+    /// File to attach; repeat to attach several files
+    #[arg(long = "attach", value_name = "PATH")]
+    pub attachments: Vec<String>,
+}
+
+impl CommentArgs {
+    pub fn text_source(&self) -> TextSource {
+        match self.message.as_deref() {
+            Some("-") => TextSource::Stdin,
+            Some(text) => TextSource::Inline(text.to_string()),
+            None => TextSource::Editor,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct AnnouncementArgs {
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
+    /// Announcement ID
+    pub announcement_id: u64,
+}
+
 #[derive(Args)]
 pub struct DiscussionArgs {
-    /// Course ID
-    pub course_id: u64,
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
     /// Discussion Topic ID
     pub topic_id: u64,
 }
 
 #[derive(Args)]
 pub struct PostDiscussionArgs {
-    /// Course ID
-    pub course_id: u64,
+    /// Course ID, course code, name fragment or alias
+    pub course: String,
     /// Discussion Topic ID
     pub topic_id: u64,
     /// Message to post
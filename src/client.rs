@@ -1,13 +1,23 @@
+use crate::async_client::AsyncCanvasClient;
+use crate::error::{CanvasError, Result};
 use crate::models::*;
-use reqwest::blocking::{Client, multipart};
-use std::error::Error;
-use std::path::Path;
-use serde_json;
+use crate::retry::{RateLimitState, RateLimiter, RetryConfig};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, LINK};
+use reqwest::redirect::Policy;
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+use std::thread;
 
 pub struct CanvasClient {
     base_url: String,
     access_token: String,
     client: Client,
+    /// Unauthenticated client for third-party upload URLs; redirects are followed manually
+    upload_client: Client,
+    per_page: Option<u32>,
+    retry: RetryConfig,
+    rate_limiter: RateLimiter,
 }
 
 impl CanvasClient {
@@ -16,101 +26,310 @@ impl CanvasClient {
             base_url: base_url.trim_end_matches('/').to_string(),
             access_token: access_token.to_string(),
             client: Client::new(),
+            upload_client: Client::builder()
+                .redirect(Policy::none())
+                .build()
+                .expect("failed to build HTTP client"),
+            per_page: None,
+            retry: RetryConfig::default(),
+            rate_limiter: RateLimiter::default(),
         }
     }
 
-    fn get<T: for<'de> serde::Deserialize<'de>>(&self, url: &str) -> Result<T, Box<dyn Error>> {
-        let resp = self.client
-            .get(url)
-            .bearer_auth(&self.access_token)
-            .send()?
-            .error_for_status()?;
-        Ok(resp.json()?)
+    /// Request `per_page` items per page from list endpoints instead of Canvas's default of 10
+    pub fn with_per_page(mut self, per_page: u32) -> Self {
+        self.per_page = Some(per_page);
+        self
     }
 
-    pub fn get_courses(&self) -> Result<Vec<Course>, Box<dyn Error>> {
+    /// Override the throttling and retry limits
+    #[allow(dead_code)]
+    pub fn with_retry_config(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// The rate limit bucket as last reported by Canvas
+    #[allow(dead_code)]
+    pub fn rate_limit_state(&self) -> RateLimitState {
+        self.rate_limiter.state()
+    }
+
+    /// A non-blocking client for the same instance, with the same settings
+    pub fn to_async(&self) -> AsyncCanvasClient {
+        AsyncCanvasClient::new(&self.base_url, &self.access_token)
+            .with_per_page(self.per_page)
+            .with_retry_config(self.retry.clone())
+    }
+
+    /// Absolute URL of an API path relative to `/api/v1/`
+    pub(crate) fn api_url(&self, path: &str) -> String {
+        format!("{}/api/v1/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    pub(crate) fn http(&self) -> &Client {
+        &self.client
+    }
+
+    pub(crate) fn upload_http(&self) -> &Client {
+        &self.upload_client
+    }
+
+    /// Send an authenticated request, turning non-success statuses into a `CanvasError`.
+    ///
+    /// Pauses first if the rate limit bucket is running low.
+    pub(crate) fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let delay = self.rate_limiter.delay(&self.retry);
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+        let resp = request.bearer_auth(&self.access_token).send()?;
+        self.rate_limiter.update(resp.headers());
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let url = resp.url().to_string();
+        let body = resp.text().unwrap_or_default();
+        Err(CanvasError::from_response(status, &url, &body))
+    }
+
+    /// Send a GET request, retrying throttled, transient and server errors with backoff
+    pub(crate) fn send_get(&self, url: &str) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            match self.send(self.client.get(url)) {
+                Err(e) if attempt < self.retry.max_retries && RetryConfig::is_retryable(&e) => {
+                    thread::sleep(self.retry.backoff(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub(crate) fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let resp = self.send_get(url)?;
+        parse_json(resp)
+    }
+
+    /// Fetch a single page, returning its items and the URL of the next page if there is one
+    fn fetch_page<T: DeserializeOwned>(&self, url: &str) -> Result<(Vec<T>, Option<String>)> {
+        let resp = self.send_get(url)?;
+        let next = next_link(resp.headers());
+        Ok((parse_json(resp)?, next))
+    }
+
+    /// Fetch every page of a list endpoint and collect the results
+    pub(crate) fn get_all<T: DeserializeOwned>(&self, url: &str) -> Result<Vec<T>> {
+        self.paginate_url(url).collect()
+    }
+
+    fn paginate_url<T: DeserializeOwned>(&self, url: &str) -> Paginator<'_, T> {
+        let url = match self.per_page {
+            Some(per_page) => {
+                let sep = if url.contains('?') { '&' } else { '?' };
+                format!("{}{}per_page={}", url, sep, per_page)
+            }
+            None => url.to_string(),
+        };
+        Paginator {
+            client: self,
+            next_url: Some(url),
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Lazily iterate over a list endpoint, fetching pages as they are consumed.
+    ///
+    /// `path` is relative to `/api/v1/`, e.g. `courses/1234/assignments`.
+    #[allow(dead_code)]
+    pub fn paginate<T: DeserializeOwned>(&self, path: &str) -> Paginator<'_, T> {
+        self.paginate_url(&self.api_url(path))
+    }
+
+    pub fn get_courses(&self) -> Result<Vec<Course>> {
         let url = format!("{}/api/v1/courses", self.base_url);
-        self.get(&url)
+        self.get_all(&url)
     }
 
-    pub fn get_course(&self, course_id: u64) -> Result<Course, Box<dyn Error>> {
+    /// Courses the user is currently enrolled in
+    pub fn get_active_courses(&self) -> Result<Vec<Course>> {
+        let url = format!("{}/api/v1/courses?enrollment_state=active", self.base_url);
+        self.get_all(&url)
+    }
+
+    pub fn get_course(&self, course_id: u64) -> Result<Course> {
         let url = format!("{}/api/v1/courses/{}", self.base_url, course_id);
         self.get(&url)
     }
 
-    pub fn get_assignments(&self, course_id: u64) -> Result<Vec<Assignment>, Box<dyn Error>> {
+    pub fn get_assignments(&self, course_id: u64) -> Result<Vec<Assignment>> {
         let url = format!("{}/api/v1/courses/{}/assignments", self.base_url, course_id);
-        self.get(&url)
+        self.get_all(&url)
+    }
+
+    /// Assignments along with the user's own submission for each
+    pub fn get_assignments_with_submission(&self, course_id: u64) -> Result<Vec<Assignment>> {
+        let url = format!("{}/api/v1/courses/{}/assignments?include[]=submission", self.base_url, course_id);
+        self.get_all(&url)
     }
 
-    pub fn get_assignment(&self, course_id: u64, assignment_id: u64) -> Result<Assignment, Box<dyn Error>> {
+    pub fn get_assignment(&self, course_id: u64, assignment_id: u64) -> Result<Assignment> {
         let url = format!("{}/api/v1/courses/{}/assignments/{}", self.base_url, course_id, assignment_id);
         self.get(&url)
     }
 
-    pub fn submit_assignment(&self, course_id: u64, assignment_id: u64, file_path: &Path) -> Result<Submission, Box<dyn Error>> {
+    /// Create a submission from the fields of a `submission` object
+    pub(crate) fn create_submission(&self, course_id: u64, assignment_id: u64, submission: serde_json::Value) -> Result<Submission> {
         let url = format!("{}/api/v1/courses/{}/assignments/{}/submissions", self.base_url, course_id, assignment_id);
-        
-        let form = multipart::Form::new()
-            .file("file", file_path)?;
-
-        let resp = self.client
-            .post(&url)
-            .bearer_auth(&self.access_token)
-            .multipart(form)
-            .send()?
-            .error_for_status()?;
-        
-        Ok(resp.json()?)
+        let resp = self.send(self.client.post(&url).json(&serde_json::json!({ "submission": submission })))?;
+        parse_json(resp)
+    }
+
+    /// Submit an HTML body as an `online_text_entry` submission, if the assignment accepts one
+    pub fn submit_text_entry(&self, course_id: u64, assignment: &Assignment, body: &str) -> Result<Submission> {
+        assignment.check_accepts(SubmissionType::TextEntry)?;
+        self.create_submission(course_id, assignment.id, serde_json::json!({
+            "submission_type": SubmissionType::TextEntry.as_str(),
+            "body": body,
+        }))
+    }
+
+    /// Submit a link as an `online_url` submission, if the assignment accepts one
+    pub fn submit_url(&self, course_id: u64, assignment: &Assignment, submission_url: &str) -> Result<Submission> {
+        assignment.check_accepts(SubmissionType::Url)?;
+        self.create_submission(course_id, assignment.id, serde_json::json!({
+            "submission_type": SubmissionType::Url.as_str(),
+            "url": submission_url,
+        }))
+    }
+
+    /// Every announcement of a course, newest first. They are listed as
+    /// discussion topics because the announcements endpoint only covers a
+    /// date window, two weeks back by default.
+    pub fn get_announcements(&self, course_id: u64) -> Result<Vec<Announcement>> {
+        let url = format!("{}/api/v1/courses/{}/discussion_topics?only_announcements=true", self.base_url, course_id);
+        self.get_all(&url)
+    }
+
+    /// Announcements are discussion topics, and are fetched as one
+    pub fn get_announcement(&self, course_id: u64, announcement_id: u64) -> Result<Announcement> {
+        let url = format!("{}/api/v1/courses/{}/discussion_topics/{}", self.base_url, course_id, announcement_id);
+        self.get(&url)
     }
 
-    pub fn get_announcements(&self, course_id: u64) -> Result<Vec<Announcement>, Box<dyn Error>> {
-        let url = format!("{}/api/v1/courses/{}/announcements", self.base_url, course_id);
+    /// Wiki pages of a course, without their bodies
+    pub fn get_pages(&self, course_id: u64) -> Result<Vec<Page>> {
+        let url = format!("{}/api/v1/courses/{}/pages?sort=title", self.base_url, course_id);
+        self.get_all(&url)
+    }
+
+    /// A wiki page with its body, by URL slug, page ID or link to the page
+    pub fn get_page(&self, course_id: u64, page: &str) -> Result<Page> {
+        let url = format!("{}/api/v1/courses/{}/pages/{}", self.base_url, course_id, page_key(page));
         self.get(&url)
     }
 
-    pub fn get_grades(&self, course_id: u64) -> Result<Grade, Box<dyn Error>> {
+    pub fn get_grades(&self, course_id: u64) -> Result<Grade> {
         let url = format!("{}/api/v1/courses/{}/grades", self.base_url, course_id);
         self.get(&url)
     }
 
-    pub fn get_todos(&self) -> Result<Vec<Todo>, Box<dyn Error>> {
+    pub fn get_todos(&self) -> Result<Vec<Todo>> {
         let url = format!("{}/api/v1/users/self/todo", self.base_url);
-        self.get(&url)
+        self.get_all(&url)
     }
 
-    pub fn get_modules(&self, course_id: u64) -> Result<Vec<Module>, Box<dyn Error>> {
+    pub fn get_modules(&self, course_id: u64) -> Result<Vec<Module>> {
         let url = format!("{}/api/v1/courses/{}/modules", self.base_url, course_id);
-        self.get(&url)
+        self.get_all(&url)
     }
 
-    pub fn get_module_items(&self, course_id: u64, module_id: u64) -> Result<Vec<ModuleItem>, Box<dyn Error>> {
+    pub fn get_module_items(&self, course_id: u64, module_id: u64) -> Result<Vec<ModuleItem>> {
         let url = format!("{}/api/v1/courses/{}/modules/{}/items", self.base_url, course_id, module_id);
-        self.get(&url)
+        self.get_all(&url)
     }
 
-    pub fn get_discussions(&self, course_id: u64) -> Result<Vec<Discussion>, Box<dyn Error>> {
+    pub fn get_discussions(&self, course_id: u64) -> Result<Vec<Discussion>> {
         let url = format!("{}/api/v1/courses/{}/discussion_topics", self.base_url, course_id);
+        self.get_all(&url)
+    }
+
+    pub fn get_discussion(&self, course_id: u64, topic_id: u64) -> Result<Discussion> {
+        let url = format!("{}/api/v1/courses/{}/discussion_topics/{}", self.base_url, course_id, topic_id);
         self.get(&url)
     }
 
-    pub fn get_discussion_entries(&self, course_id: u64, topic_id: u64) -> Result<Vec<DiscussionEntry>, Box<dyn Error>> {
+    pub fn get_discussion_entries(&self, course_id: u64, topic_id: u64) -> Result<Vec<DiscussionEntry>> {
         let url = format!("{}/api/v1/courses/{}/discussion_topics/{}/entries", 
             self.base_url, course_id, topic_id);
-        self.get(&url)
+        self.get_all(&url)
     }
 
-    pub fn post_discussion_entry(&self, course_id: u64, topic_id: u64, message: &str) -> Result<DiscussionEntry, Box<dyn Error>> {
+    pub fn post_discussion_entry(&self, course_id: u64, topic_id: u64, message: &str) -> Result<DiscussionEntry> {
         let url = format!("{}/api/v1/courses/{}/discussion_topics/{}/entries", 
             self.base_url, course_id, topic_id);
         
-        let resp = self.client
-            .post(&url)
-            .bearer_auth(&self.access_token)
-            .json(&serde_json::json!({ "message": message }))
-            .send()?
-            .error_for_status()?;
-        
-        Ok(resp.json()?)
+        let resp = self.send(self.client.post(&url).json(&serde_json::json!({ "message": message })))?;
+        parse_json(resp)
+    }
+}
+
+/// Iterator over the items of a paginated Canvas list endpoint.
+///
+/// Pages are requested on demand by following the `Link: rel="next"` header.
+/// An error ends the iteration after it has been yielded.
+pub struct Paginator<'a, T> {
+    client: &'a CanvasClient,
+    next_url: Option<String>,
+    buffer: VecDeque<T>,
+}
+
+impl<T: DeserializeOwned> Iterator for Paginator<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.is_empty() {
+            let url = self.next_url.take()?;
+            match self.client.fetch_page(&url) {
+                Ok((items, next)) => {
+                    self.buffer.extend(items);
+                    self.next_url = next;
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        self.buffer.pop_front().map(Ok)
     }
 }
+
+/// Deserialize a response body, reporting the JSON path of any mismatch
+pub(crate) fn parse_json<T: DeserializeOwned>(resp: Response) -> Result<T> {
+    parse_body(&resp.text()?)
+}
+
+pub(crate) fn parse_body<T: DeserializeOwned>(body: &str) -> Result<T> {
+    let de = &mut serde_json::Deserializer::from_str(body);
+    Ok(serde_path_to_error::deserialize(de)?)
+}
+
+/// The URL slug or ID in a page argument, which may also be a link to the page
+pub fn page_key(page: &str) -> &str {
+    page.trim_end_matches('/').rsplit_once("/pages/").map_or(page, |(_, slug)| slug)
+}
+
+/// Extract the `rel="next"` URL from an RFC 5988 `Link` header
+pub fn next_link(headers: &HeaderMap) -> Option<String> {
+    headers.get_all(LINK).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|link| {
+            let mut parts = link.split(';');
+            let url = parts.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+            parts.any(|param| {
+                let param = param.trim();
+                param == "rel=\"next\"" || param == "rel=next"
+            }).then(|| url.to_string())
+        })
+}
@@ -0,0 +1,100 @@
+use pulldown_cmark::{html, Options, Parser};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where the body of a text submission or comment comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextSource {
+    /// Text given directly on the command line
+    Inline(String),
+    /// A file on disk
+    File(PathBuf),
+    /// Standard input
+    Stdin,
+    /// A temporary file opened in `$VISUAL`/`$EDITOR`
+    Editor,
+}
+
+impl TextSource {
+    /// Build a source from a `--text` style argument, where `-` means stdin
+    pub fn from_path_arg(arg: &str) -> Self {
+        if arg == "-" {
+            TextSource::Stdin
+        } else {
+            TextSource::File(PathBuf::from(arg))
+        }
+    }
+
+    /// Read the text, launching the editor if needed
+    pub fn read(&self) -> io::Result<String> {
+        match self {
+            TextSource::Inline(text) => Ok(text.clone()),
+            TextSource::File(path) => fs::read_to_string(path),
+            TextSource::Stdin => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                Ok(text)
+            }
+            TextSource::Editor => edit_text(""),
+        }
+    }
+
+    /// Whether the text is already HTML and should be sent as-is
+    pub fn is_html(&self) -> bool {
+        match self {
+            TextSource::File(path) => has_html_extension(path),
+            _ => false,
+        }
+    }
+
+    /// Read the text and convert it to the HTML Canvas expects
+    pub fn read_html(&self) -> io::Result<String> {
+        let text = self.read()?;
+        if text.trim().is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "text is empty, nothing to send"));
+        }
+        Ok(if self.is_html() { text } else { markdown_to_html(&text) })
+    }
+}
+
+fn has_html_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"))
+}
+
+/// Convert Markdown (plain text is valid Markdown) to HTML
+pub fn markdown_to_html(markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    let parser = Parser::new_ext(markdown, options);
+    let mut out = String::new();
+    html::push_html(&mut out, parser);
+    out
+}
+
+/// Open `initial` in the user's editor and return the saved text
+pub fn edit_text(initial: &str) -> io::Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // Created with a random name, readable only by the user, and removed when dropped
+    let mut draft = tempfile::Builder::new().prefix("canvas-cli-").suffix(".md").tempfile()?;
+    draft.write_all(initial.as_bytes())?;
+    draft.flush()?;
+
+    // The editor setting may include arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = Command::new(program).args(parts).arg(draft.path()).status();
+
+    let text = fs::read_to_string(draft.path());
+    if !status?.success() {
+        return Err(io::Error::other(format!("editor '{}' exited with an error", editor)));
+    }
+    text
+}
@@ -0,0 +1,468 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const BASE_URL_VAR: &str = "BASE_URL";
+pub const ACCESS_TOKEN_VAR: &str = "ACCESS_KEY";
+pub const PROFILE_VAR: &str = "CANVAS_PROFILE";
+
+/// Profile used when none is selected; backed by the top-level config file keys
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Where an effective configuration value came from, in order of precedence
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// A command line flag
+    Flag(&'static str),
+    /// A variable set in the process environment
+    Env(&'static str),
+    /// A variable read from a `.env` file
+    DotEnv(PathBuf),
+    /// The user config file
+    ConfigFile(PathBuf),
+    /// Nothing selected a value, so the built-in default applies
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Flag(flag) => write!(f, "{} flag", flag),
+            Source::Env(var) => write!(f, "environment variable {}", var),
+            Source::DotEnv(path) => write!(f, ".env file {}", path.display()),
+            Source::ConfigFile(path) => write!(f, "config file {}", path.display()),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// A configuration value together with its origin
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting {
+    pub value: String,
+    pub source: Source,
+}
+
+/// Connection settings for one Canvas instance
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    /// Short names for courses, mapped to course IDs
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, u64>,
+    /// Local database file; relative paths are relative to the config file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+}
+
+/// Contents of `config.toml`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ConfigFile {
+    /// Profile used when `--profile` and `CANVAS_PROFILE` are not given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ConfigFile {
+    /// Read a config file; a missing file is treated as empty
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| ConfigError::Parse(path.to_path_buf(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(ConfigError::Io(path.to_path_buf(), e)),
+        }
+    }
+
+    /// Write the config file, readable only by the user since it holds tokens
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let text = toml::to_string_pretty(self).expect("config file is always serializable");
+        let write = || -> io::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create(true).truncate(true);
+            // Created private rather than narrowed afterwards, so the token is
+            // never readable by others; an older file is narrowed as well.
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            let mut file = options.open(path)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                file.set_permissions(fs::Permissions::from_mode(0o600))?;
+            }
+            file.write_all(text.as_bytes())
+        };
+        write().map_err(|e| ConfigError::Io(path.to_path_buf(), e))
+    }
+
+    /// Settings of a named profile.
+    ///
+    /// The `default` profile falls back to the top-level `base_url`,
+    /// `access_token`, `aliases` and `database` keys when there is no
+    /// `[profiles.default]` table.
+    pub fn profile(&self, name: &str) -> Option<Profile> {
+        match self.profiles.get(name) {
+            Some(profile) => Some(profile.clone()),
+            None if name == DEFAULT_PROFILE => Some(Profile {
+                base_url: self.base_url.clone(),
+                access_token: self.access_token.clone(),
+                aliases: self.aliases.clone(),
+                database: self.database.clone(),
+            }),
+            None => None,
+        }
+    }
+
+    /// The course aliases of a profile, for editing.
+    ///
+    /// Like [`ConfigFile::profile`], the `default` profile uses the top-level
+    /// table unless `[profiles.default]` exists.
+    pub fn aliases_mut(&mut self, profile: &str) -> Result<&mut BTreeMap<String, u64>, ConfigError> {
+        if profile == DEFAULT_PROFILE && !self.profiles.contains_key(DEFAULT_PROFILE) {
+            return Ok(&mut self.aliases);
+        }
+        self.profiles.get_mut(profile)
+            .map(|p| &mut p.aliases)
+            .ok_or_else(|| ConfigError::UnknownProfile(profile.to_string()))
+    }
+
+    /// Every profile name, including the implicit default profile if it has settings
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.profiles.keys().cloned().collect();
+        let implicit_default = self.base_url.is_some() || self.access_token.is_some();
+        if implicit_default && !self.profiles.contains_key(DEFAULT_PROFILE) {
+            names.insert(0, DEFAULT_PROFILE.to_string());
+        }
+        names
+    }
+}
+
+/// Check that a profile name is usable as a table key and file name
+pub fn validate_profile_name(name: &str) -> Result<(), ConfigError> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ConfigError::InvalidProfileName(name.to_string()))
+    }
+}
+
+/// Check that a course alias can't be mistaken for a course ID
+pub fn validate_alias(name: &str) -> Result<(), ConfigError> {
+    let valid = !name.is_empty()
+        && !name.chars().all(|c| c.is_ascii_digit())
+        && !name.chars().any(char::is_whitespace);
+    if valid {
+        Ok(())
+    } else {
+        Err(ConfigError::InvalidAlias(name.to_string()))
+    }
+}
+
+/// Values given on the command line
+#[derive(Debug, Default, Clone)]
+pub struct Flags {
+    pub base_url: Option<String>,
+    pub access_token: Option<String>,
+    pub profile: Option<String>,
+    pub database: Option<String>,
+}
+
+/// The raw inputs to configuration resolution, one per layer
+#[derive(Debug, Default)]
+pub struct Layers {
+    pub flags: Flags,
+    pub env: HashMap<String, String>,
+    pub dotenv: Option<(PathBuf, HashMap<String, String>)>,
+    pub file: Option<(PathBuf, ConfigFile)>,
+    /// Base directory for application data, see [`data_dir`]
+    pub data_dir: Option<PathBuf>,
+}
+
+impl Layers {
+    /// Gather every layer from the real environment, `.env` and config file
+    pub fn gather(flags: Flags) -> Result<Self, ConfigError> {
+        let env = [BASE_URL_VAR, ACCESS_TOKEN_VAR, PROFILE_VAR].iter()
+            .filter_map(|var| env::var(var).ok().map(|value| (var.to_string(), value)))
+            .collect();
+
+        // Read `.env` without exporting it, so its values stay distinguishable from the real environment
+        #[allow(deprecated)]
+        let dotenv = find_dotenv().map(|path| {
+            let vars = dotenv::from_path_iter(&path)
+                .map(|iter| iter.filter_map(|item| item.ok()).collect())
+                .unwrap_or_default();
+            (path, vars)
+        });
+
+        let file = match config_path() {
+            Some(path) => {
+                let file = ConfigFile::load(&path)?;
+                Some((path, file))
+            }
+            None => None,
+        };
+
+        Ok(Self { flags, env, dotenv, file, data_dir: data_dir() })
+    }
+
+    fn env_value(&self, var: &'static str) -> Option<(String, Source)> {
+        self.env.get(var).cloned().map(|v| (v, Source::Env(var)))
+    }
+
+    fn dotenv_value(&self, var: &str) -> Option<(String, Source)> {
+        self.dotenv.as_ref()
+            .and_then(|(path, vars)| vars.get(var).map(|v| (v.clone(), Source::DotEnv(path.clone()))))
+    }
+}
+
+/// The effective configuration after merging every layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Name of the active profile
+    pub profile: Setting,
+    pub base_url: Option<Setting>,
+    pub access_token: Option<Setting>,
+    /// Location of the user config file, whether or not it exists
+    pub config_path: Option<PathBuf>,
+    /// Course aliases of the active profile
+    pub aliases: BTreeMap<String, u64>,
+    /// Local database file of the active profile
+    pub database: Setting,
+}
+
+impl Config {
+    /// Load the configuration for this invocation.
+    ///
+    /// Precedence, highest first: command line flags, environment variables,
+    /// the nearest `.env` file, then the active profile in
+    /// `$XDG_CONFIG_HOME/canvas-cli/config.toml`.
+    pub fn load(flags: Flags) -> Result<Self, ConfigError> {
+        Self::resolve(Layers::gather(flags)?)
+    }
+
+    /// Merge already gathered layers; empty values are treated as unset.
+    ///
+    /// The environment and `.env` describe the default profile, so they are
+    /// ignored for connection settings once another profile is selected.
+    pub fn resolve(layers: Layers) -> Result<Self, ConfigError> {
+        let profile = first_set([
+            layers.flags.profile.clone().map(|v| (v, Source::Flag("--profile"))),
+            layers.env_value(PROFILE_VAR),
+            layers.dotenv_value(PROFILE_VAR),
+            layers.file.as_ref().and_then(|(path, file)| {
+                file.default_profile.clone().map(|v| (v, Source::ConfigFile(path.clone())))
+            }),
+        ]).unwrap_or(Setting { value: DEFAULT_PROFILE.to_string(), source: Source::Default });
+        validate_profile_name(&profile.value)?;
+
+        let is_default = profile.value == DEFAULT_PROFILE;
+        let file_profile = match &layers.file {
+            Some((path, file)) => match file.profile(&profile.value) {
+                Some(settings) => Some((path.clone(), settings)),
+                None => return Err(ConfigError::UnknownProfile(profile.value)),
+            },
+            None if is_default => None,
+            None => return Err(ConfigError::UnknownProfile(profile.value)),
+        };
+
+        let pick = |flag_value: &Option<String>, flag: &'static str, var: &'static str, file_value: fn(&Profile) -> &Option<String>| {
+            first_set([
+                flag_value.clone().map(|v| (v, Source::Flag(flag))),
+                layers.env_value(var).filter(|_| is_default),
+                layers.dotenv_value(var).filter(|_| is_default),
+                file_profile.as_ref()
+                    .and_then(|(path, settings)| file_value(settings).clone().map(|v| (v, Source::ConfigFile(path.clone())))),
+            ])
+        };
+
+        let database = first_set([
+            layers.flags.database.clone().map(|v| (v, Source::Flag("--db"))),
+            file_profile.as_ref().and_then(|(path, settings)| {
+                settings.database.as_deref()
+                    .map(|v| (relative_to(path, v).display().to_string(), Source::ConfigFile(path.clone())))
+            }),
+        ]).unwrap_or_else(|| Setting {
+            value: default_database_path(layers.data_dir.as_deref(), &profile.value).display().to_string(),
+            source: Source::Default,
+        });
+
+        Ok(Self {
+            base_url: pick(&layers.flags.base_url, "--base-url", BASE_URL_VAR, |p| &p.base_url),
+            access_token: pick(&layers.flags.access_token, "--access-token", ACCESS_TOKEN_VAR, |p| &p.access_token),
+            config_path: layers.file.as_ref().map(|(path, _)| path.clone()),
+            aliases: file_profile.as_ref().map(|(_, settings)| settings.aliases.clone()).unwrap_or_default(),
+            database,
+            profile,
+        })
+    }
+
+    /// The base URL and access token, or an error naming what is missing
+    pub fn credentials(&self) -> Result<(&str, &str), ConfigError> {
+        match (&self.base_url, &self.access_token) {
+            (Some(base_url), Some(token)) => Ok((&base_url.value, &token.value)),
+            (base_url, token) => {
+                let mut missing = Vec::new();
+                if base_url.is_none() {
+                    missing.push("base_url");
+                }
+                if token.is_none() {
+                    missing.push("access_token");
+                }
+                Err(ConfigError::Missing(self.profile.value.clone(), missing))
+            }
+        }
+    }
+
+    /// Local database for the active profile
+    pub fn database_path(&self) -> PathBuf {
+        PathBuf::from(&self.database.value)
+    }
+}
+
+fn first_set<const N: usize>(candidates: [Option<(String, Source)>; N]) -> Option<Setting> {
+    candidates.into_iter()
+        .flatten()
+        .find(|(value, _)| !value.trim().is_empty())
+        .map(|(value, source)| Setting { value, source })
+}
+
+/// Location of the user config file: `$XDG_CONFIG_HOME/canvas-cli/config.toml`,
+/// falling back to `~/.config/canvas-cli/config.toml`
+pub fn config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("canvas-cli").join("config.toml"))
+}
+
+/// Base directory for application data: `$XDG_DATA_HOME`, falling back to `~/.local/share`
+pub fn data_dir() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+}
+
+/// `<data dir>/canvas-cli/<profile>.db`, one per profile so different instances
+/// never share a database; without a data directory it goes in the current one
+pub fn default_database_path(data_dir: Option<&Path>, profile: &str) -> PathBuf {
+    let file = format!("{}.db", profile);
+    match data_dir {
+        Some(dir) => dir.join("canvas-cli").join(file),
+        None => PathBuf::from(file),
+    }
+}
+
+/// Resolve a path from the config file: `~/` is the home directory and other
+/// relative paths are relative to the file's own directory
+fn relative_to(config_path: &Path, value: &str) -> PathBuf {
+    if let (Some(rest), Some(home)) = (value.strip_prefix("~/"), env::var_os("HOME")) {
+        return PathBuf::from(home).join(rest);
+    }
+    match config_path.parent() {
+        Some(dir) => dir.join(value),
+        None => PathBuf::from(value),
+    }
+}
+
+/// The nearest `.env` file in the current directory or one of its parents
+fn find_dotenv() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(".env"))
+        .find(|path| path.is_file())
+}
+
+/// Hide all but the last four characters of a secret
+pub fn redact(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return "*".repeat(chars.len());
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}{}", "*".repeat(8), tail)
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    /// Required settings that no layer provided for the named profile
+    Missing(String, Vec<&'static str>),
+    /// The selected profile is not defined in the config file
+    UnknownProfile(String),
+    InvalidProfileName(String),
+    InvalidAlias(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "could not access {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "invalid config file {}: {}", path.display(), e),
+            ConfigError::Missing(profile, keys) if profile == DEFAULT_PROFILE => write!(
+                f,
+                "missing {}: set --base-url/--access-token, {}/{} in the environment or .env, \
+                 or base_url/access_token in the config file (see `canvas-cli config show`)",
+                keys.join(" and "),
+                BASE_URL_VAR,
+                ACCESS_TOKEN_VAR
+            ),
+            ConfigError::Missing(profile, keys) => write!(
+                f,
+                "profile '{}' is missing {} (see `canvas-cli profile add`)",
+                profile,
+                keys.join(" and ")
+            ),
+            ConfigError::UnknownProfile(name) => {
+                write!(f, "unknown profile '{}' (see `canvas-cli profile list`)", name)
+            }
+            ConfigError::InvalidProfileName(name) => write!(
+                f,
+                "invalid profile name '{}': use letters, digits, '-' and '_' only",
+                name
+            ),
+            ConfigError::InvalidAlias(name) => write!(
+                f,
+                "invalid alias '{}': aliases can't contain spaces or be all digits",
+                name
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(_, e) => Some(e),
+            ConfigError::Parse(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
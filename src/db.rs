@@ -1,109 +1,153 @@
-use rusqlite::{Connection, Result, params};
-use std::path::PathBuf;
+use rusqlite::{Connection, OptionalExtension, Result, Transaction, params};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use chrono::{DateTime, Utc};
+use crate::changes::Change;
+use crate::migrations::{self, Migration, MigrationError, MigrationReport};
 use crate::models::*;
+use crate::search::{self, DocumentKind, SearchHit};
+use crate::render;
 
 pub struct Database {
     conn: Connection,
+    path: PathBuf,
+}
+
+/// What syncing a single row did to the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncOutcome {
+    Inserted,
+    Updated,
+    /// The content hash matched, so only `last_sync` was touched
+    Unchanged,
+}
+
+/// A recorded run of `sync`
+#[derive(Debug, Serialize)]
+pub struct SyncRun {
+    pub id: u64,
+    pub started_at: DateTime<Utc>,
+    /// Missing while the run is going on, or if it was interrupted
+    pub finished_at: Option<DateTime<Utc>>,
+    pub full: bool,
+    pub courses: Option<u64>,
+    /// Row counts per table, as in the sync report
+    pub counts: serde_json::Value,
+    pub errors: Vec<String>,
+}
+
+/// A set of cached rows, for asking how fresh they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheScope {
+    Courses,
+    /// Assignments of a course
+    Assignments(u64),
+    /// Discussions of a course
+    Discussions(u64),
+    /// Modules of a course
+    Modules(u64),
+    /// Items of a module
+    ModuleItems(u64),
+    /// Wiki pages of a course
+    Pages(u64),
+}
+
+impl CacheScope {
+    /// The table holding the rows, and the parent column and ID that select them
+    fn table(&self) -> (&'static str, Option<(&'static str, u64)>) {
+        match *self {
+            CacheScope::Courses => ("courses", None),
+            CacheScope::Assignments(id) => ("assignments", Some(("course_id", id))),
+            CacheScope::Discussions(id) => ("discussions", Some(("course_id", id))),
+            CacheScope::Modules(id) => ("modules", Some(("course_id", id))),
+            CacheScope::ModuleItems(id) => ("module_items", Some(("module_id", id))),
+            CacheScope::Pages(id) => ("pages", Some(("course_id", id))),
+        }
+    }
 }
 
 impl Database {
-    pub fn new(path: &PathBuf) -> Result<Self> {
+    /// Open a database, creating the file if needed. Nothing is migrated until
+    /// [`Database::migrate`] is called, so the schema can be inspected first.
+    pub fn open_unmigrated(path: &Path) -> Result<Self> {
         let conn = Connection::open(path)?;
-        let db = Database { conn };
-        db.initialize()?;
-        Ok(db)
+        // Readers may overlap with a sync's writes; wait for them rather than failing
+        conn.busy_timeout(Duration::from_secs(5))?;
+        Ok(Database { conn, path: path.to_path_buf() })
     }
 
-    fn initialize(&self) -> Result<()> {
-        // Create courses table
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS courses (
-                id INTEGER PRIMARY KEY,
-                name TEXT,
-                course_code TEXT,
-                enrollment_term_id INTEGER,
-                total_students INTEGER,
-                start_at TEXT,
-                end_at TEXT,
-                last_sync TEXT NOT NULL
-            )",
-            [],
-        )?;
+    /// Apply pending migrations, backing up the file first if it holds data
+    pub fn migrate(&self) -> std::result::Result<MigrationReport, MigrationError> {
+        migrations::migrate(&self.conn, Some(&self.path))
+    }
 
-        // Create assignments table
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS assignments (
-                id INTEGER PRIMARY KEY,
-                course_id INTEGER,
-                name TEXT NOT NULL,
-                description TEXT,
-                due_at TEXT,
-                points_possible REAL,
-                html_url TEXT,
-                last_sync TEXT NOT NULL,
-                FOREIGN KEY(course_id) REFERENCES courses(id)
-            )",
-            [],
-        )?;
+    /// Schema version recorded in the database
+    pub fn schema_version(&self) -> Result<u32> {
+        migrations::current_version(&self.conn)
+    }
 
-        // Create discussions table
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS discussions (
-                id INTEGER PRIMARY KEY,
-                course_id INTEGER,
-                title TEXT NOT NULL,
-                message TEXT,
-                posted_at TEXT,
-                discussion_type TEXT,
-                pinned BOOLEAN,
-                last_sync TEXT NOT NULL,
-                FOREIGN KEY(course_id) REFERENCES courses(id)
-            )",
-            [],
-        )?;
+    /// Migrations not yet applied to this database
+    pub fn pending_migrations(&self) -> std::result::Result<Vec<&'static Migration>, MigrationError> {
+        migrations::pending(&self.conn)
+    }
+
+    /// Start a transaction; everything written through this database joins it
+    /// until it is committed, and is rolled back if it is dropped instead
+    pub fn transaction(&self) -> Result<Transaction<'_>> {
+        self.conn.unchecked_transaction()
+    }
 
-        // Create modules table
+    /// Record the start of a sync run, returning its ID
+    pub fn start_sync_run(&self, started_at: DateTime<Utc>, full: bool) -> Result<u64> {
         self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS modules (
-                id INTEGER PRIMARY KEY,
-                course_id INTEGER,
-                name TEXT NOT NULL,
-                position INTEGER,
-                unlock_at TEXT,
-                state TEXT,
-                completed_at TEXT,
-                last_sync TEXT NOT NULL,
-                FOREIGN KEY(course_id) REFERENCES courses(id)
-            )",
-            [],
+            "INSERT INTO sync_runs (started_at, full) VALUES (?1, ?2)",
+            params![started_at.to_rfc3339(), full],
         )?;
+        Ok(self.conn.last_insert_rowid() as u64)
+    }
 
-        // Create module items table
+    /// Record how a sync run ended
+    pub fn finish_sync_run<C: Serialize>(
+        &self,
+        id: u64,
+        finished_at: DateTime<Utc>,
+        courses: usize,
+        counts: &C,
+        errors: &[String],
+    ) -> Result<()> {
+        let counts = serde_json::to_string(counts).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+        let errors = serde_json::to_string(errors).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
         self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS module_items (
-                id INTEGER PRIMARY KEY,
-                module_id INTEGER,
-                position INTEGER,
-                title TEXT NOT NULL,
-                item_type TEXT,
-                content_id INTEGER,
-                last_sync TEXT NOT NULL,
-                FOREIGN KEY(module_id) REFERENCES modules(id)
-            )",
-            [],
+            "UPDATE sync_runs SET finished_at = ?1, courses = ?2, counts = ?3, errors = ?4 WHERE id = ?5",
+            params![finished_at.to_rfc3339(), courses, counts, errors, id],
         )?;
+        Ok(())
+    }
 
-        // Create sync_status table
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS sync_status (
-                type_name TEXT PRIMARY KEY,
-                last_sync TEXT NOT NULL
-            )",
-            [],
+    /// The most recent sync runs, newest first
+    pub fn sync_runs(&self, limit: usize) -> Result<Vec<SyncRun>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, started_at, finished_at, full, courses, counts, errors
+            FROM sync_runs ORDER BY id DESC LIMIT ?1"
         )?;
 
-        Ok(())
+        let runs = stmt.query_map([limit], |row| {
+            let counts: Option<String> = row.get(5)?;
+            let errors: Option<String> = row.get(6)?;
+            Ok(SyncRun {
+                id: row.get(0)?,
+                started_at: parse_datetime(row.get(1)?).unwrap_or_default(),
+                finished_at: parse_datetime(row.get(2)?),
+                full: row.get(3)?,
+                courses: row.get(4)?,
+                counts: counts.and_then(|c| serde_json::from_str(&c).ok()).unwrap_or_default(),
+                errors: errors.and_then(|e| serde_json::from_str(&e).ok()).unwrap_or_default(),
+            })
+        })?;
+
+        runs.collect()
     }
 
     /// Get a reference to the underlying database connection
@@ -112,44 +156,33 @@ impl Database {
         &self.conn
     }
 
-    #[allow(dead_code)]
-    pub fn update_sync_status(&self, type_name: &str) -> Result<()> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO sync_status (type_name, last_sync)
-            VALUES (?1, ?2)",
-            params![
-                type_name,
-                Utc::now().to_rfc3339()
-            ],
-        )?;
-        Ok(())
-    }
-
-    pub fn sync_course(&self, course: &Course) -> Result<()> {
-        self.conn.execute(
+    pub fn sync_course(&self, course: &Course) -> Result<SyncOutcome> {
+        let hash = content_hash(course);
+        self.upsert("courses", course.id, &hash, || self.conn.prepare_cached(
             "INSERT OR REPLACE INTO courses (id, name, course_code, enrollment_term_id, 
-                total_students, start_at, end_at, last_sync)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![
-                course.id,
-                course.name,
-                course.course_code,
-                course.enrollment_term_id,
-                course.total_students,
-                course.start_at.map(|dt| dt.to_rfc3339()),
-                course.end_at.map(|dt| dt.to_rfc3339()),
-                Utc::now().to_rfc3339()
-            ],
-        )?;
-        Ok(())
+                total_students, start_at, end_at, last_sync, content_hash)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+        )?.execute(params![
+            course.id,
+            course.name,
+            course.course_code,
+            course.enrollment_term_id,
+            course.total_students,
+            course.start_at.map(|dt| dt.to_rfc3339()),
+            course.end_at.map(|dt| dt.to_rfc3339()),
+            Utc::now().to_rfc3339(),
+            hash
+        ]))
     }
 
-    pub fn sync_assignment(&self, course_id: u64, assignment: &Assignment) -> Result<()> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO assignments (id, course_id, name, description, 
-                due_at, points_possible, html_url, last_sync)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![
+    pub fn sync_assignment(&self, course_id: u64, assignment: &Assignment) -> Result<SyncOutcome> {
+        let hash = content_hash(&(course_id, assignment));
+        self.upsert("assignments", assignment.id, &hash, || {
+            let written = self.conn.prepare_cached(
+                "INSERT OR REPLACE INTO assignments (id, course_id, name, description, 
+                    due_at, points_possible, html_url, last_sync, content_hash)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+            )?.execute(params![
                 assignment.id,
                 course_id,
                 assignment.name,
@@ -157,18 +190,23 @@ impl Database {
                 assignment.due_at.map(|dt| dt.to_rfc3339()),
                 assignment.points_possible,
                 assignment.html_url,
-                Utc::now().to_rfc3339()
-            ],
-        )?;
-        Ok(())
+                Utc::now().to_rfc3339(),
+                hash
+            ])?;
+            self.index_document(DocumentKind::Assignment, assignment.id, course_id, &assignment.name, assignment.description.as_deref())?;
+            Ok(written)
+        })
     }
 
-    pub fn sync_discussion(&self, course_id: u64, discussion: &Discussion) -> Result<()> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO discussions (id, course_id, title, message, 
-                posted_at, discussion_type, pinned, last_sync)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![
+    pub fn sync_discussion(&self, course_id: u64, discussion: &Discussion) -> Result<SyncOutcome> {
+        let hash = content_hash(&(course_id, discussion));
+        self.upsert("discussions", discussion.id, &hash, || {
+            let written = self.conn.prepare_cached(
+                "INSERT OR REPLACE INTO discussions (id, course_id, title, message, 
+                    posted_at, discussion_type, pinned, last_sync, content_hash,
+                    last_reply_at, discussion_subentry_count)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"
+            )?.execute(params![
                 discussion.id,
                 course_id,
                 discussion.title,
@@ -176,79 +214,560 @@ impl Database {
                 discussion.posted_at.map(|dt| dt.to_rfc3339()),
                 discussion.discussion_type,
                 discussion.pinned,
-                Utc::now().to_rfc3339()
-            ],
-        )?;
-        
-        // Update sync status after successful sync
-        self.update_sync_status("discussions")?;
-        Ok(())
+                Utc::now().to_rfc3339(),
+                hash,
+                discussion.last_reply_at.map(|dt| dt.to_rfc3339()),
+                discussion.discussion_subentry_count
+            ])?;
+            self.index_document(DocumentKind::Discussion, discussion.id, course_id, &discussion.title, Some(&discussion.message))?;
+            Ok(written)
+        })
     }
 
-    pub fn sync_module(&self, course_id: u64, module: &Module) -> Result<()> {
-        self.conn.execute(
+    pub fn sync_module(&self, course_id: u64, module: &Module) -> Result<SyncOutcome> {
+        // Items are stored separately, so they don't count towards the module's own content
+        let hash = content_hash(&(course_id, &module.id, &module.name, module.position, &module.unlock_at, &module.state, &module.completed_at));
+        self.upsert("modules", module.id, &hash, || self.conn.prepare_cached(
             "INSERT OR REPLACE INTO modules (id, course_id, name, position, 
-                unlock_at, state, completed_at, last_sync)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![
-                module.id,
-                course_id,
-                module.name,
-                module.position,
-                module.unlock_at.map(|dt| dt.to_rfc3339()),
-                module.state,
-                module.completed_at.map(|dt| dt.to_rfc3339()),
-                Utc::now().to_rfc3339()
-            ],
-        )?;
-        Ok(())
+                unlock_at, state, completed_at, last_sync, content_hash)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+        )?.execute(params![
+            module.id,
+            course_id,
+            module.name,
+            module.position,
+            module.unlock_at.map(|dt| dt.to_rfc3339()),
+            module.state,
+            module.completed_at.map(|dt| dt.to_rfc3339()),
+            Utc::now().to_rfc3339(),
+            hash
+        ]))
     }
 
-    pub fn sync_module_item(&self, module_id: u64, item: &ModuleItem) -> Result<()> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO module_items (id, module_id, position, 
-                title, item_type, content_id, last_sync)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![
+    pub fn sync_module_item(&self, module_id: u64, item: &ModuleItem) -> Result<SyncOutcome> {
+        let hash = content_hash(&(module_id, item));
+        self.upsert("module_items", item.id, &hash, || {
+            let written = self.conn.prepare_cached(
+                "INSERT OR REPLACE INTO module_items (id, module_id, position, 
+                    title, item_type, content_id, last_sync, content_hash)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"
+            )?.execute(params![
                 item.id,
                 module_id,
                 item.position,
                 item.title,
                 item.item_type,
                 item.content_id,
-                Utc::now().to_rfc3339()
-            ],
+                Utc::now().to_rfc3339(),
+                hash
+            ])?;
+            let course_id: u64 = self.conn
+                .prepare_cached("SELECT course_id FROM modules WHERE id = ?1")?
+                .query_row([module_id], |row| row.get(0))?;
+            self.index_document(DocumentKind::ModuleItem, item.id, course_id, &item.title, None)?;
+            Ok(written)
+        })
+    }
+
+    pub fn sync_page(&self, course_id: u64, page: &Page) -> Result<SyncOutcome> {
+        let hash = content_hash(&(course_id, page));
+        self.upsert("pages", page.page_id, &hash, || {
+            let written = self.conn.prepare_cached(
+                "INSERT OR REPLACE INTO pages (id, course_id, url, title, body, published,
+                    front_page, html_url, created_at, updated_at, last_sync, content_hash)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
+            )?.execute(params![
+                page.page_id,
+                course_id,
+                page.url,
+                page.title,
+                page.body,
+                page.published,
+                page.front_page,
+                page.html_url,
+                page.created_at.to_rfc3339(),
+                page.updated_at.to_rfc3339(),
+                Utc::now().to_rfc3339(),
+                hash
+            ])?;
+            self.index_document(DocumentKind::Page, page.page_id, course_id, &page.title, page.body.as_deref())?;
+            Ok(written)
+        })
+    }
+
+    /// Make an announcement searchable. Announcements aren't cached as rows,
+    /// so the search index is the only place they are kept.
+    pub fn index_announcement(&self, course_id: u64, announcement: &Announcement) -> Result<SyncOutcome> {
+        self.index_document(DocumentKind::Announcement, announcement.id, course_id, &announcement.title, Some(&announcement.message))
+    }
+
+    /// Tombstone the indexed announcements of a course that are missing from
+    /// `remote_ids`, the complete listing Canvas just returned. Returns the IDs
+    /// newly marked deleted.
+    pub fn mark_announcements_deleted(&self, course_id: u64, remote_ids: &[u64]) -> Result<Vec<u64>> {
+        let live: Vec<u64> = self.conn
+            .prepare_cached("SELECT resource_id FROM search_documents WHERE kind = ?1 AND course_id = ?2 AND deleted_at IS NULL")?
+            .query_map(params![DocumentKind::Announcement.as_str(), course_id], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+
+        let remote: HashSet<u64> = remote_ids.iter().copied().collect();
+        let gone: Vec<u64> = live.into_iter().filter(|id| !remote.contains(id)).collect();
+        let now = Utc::now().to_rfc3339();
+        let mut tombstone = self.conn
+            .prepare_cached("UPDATE search_documents SET deleted_at = ?1 WHERE kind = ?2 AND resource_id = ?3")?;
+        for id in &gone {
+            tombstone.execute(params![now, DocumentKind::Announcement.as_str(), id])?;
+        }
+        Ok(gone)
+    }
+
+    /// Add or replace a row's entry in the full-text index, reduced to plain text.
+    /// Entries whose text hasn't changed are left alone; a tombstoned entry is
+    /// restored and counts as updated.
+    fn index_document(&self, kind: DocumentKind, resource_id: u64, course_id: u64, title: &str, body: Option<&str>) -> Result<SyncOutcome> {
+        let body = body.map(render::plain_text).unwrap_or_default();
+        let hash = content_hash(&(course_id, title, &body));
+        let existing: Option<(u64, Option<String>, bool)> = self.conn
+            .prepare_cached("SELECT id, content_hash, deleted_at IS NOT NULL FROM search_documents WHERE kind = ?1 AND resource_id = ?2")?
+            .query_row(params![kind.as_str(), resource_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .optional()?;
+
+        let (id, outcome) = match existing {
+            Some((id, Some(existing), deleted)) if existing == hash => {
+                self.conn
+                    .prepare_cached("UPDATE search_documents SET deleted_at = NULL WHERE id = ?1")?
+                    .execute([id])?;
+                return Ok(if deleted { SyncOutcome::Updated } else { SyncOutcome::Unchanged });
+            }
+            Some((id, _, _)) => {
+                self.conn
+                    .prepare_cached("UPDATE search_documents SET course_id = ?1, content_hash = ?2, deleted_at = NULL WHERE id = ?3")?
+                    .execute(params![course_id, hash, id])?;
+                self.conn.prepare_cached("DELETE FROM search_index WHERE rowid = ?1")?.execute([id])?;
+                (id, SyncOutcome::Updated)
+            }
+            None => {
+                self.conn
+                    .prepare_cached("INSERT INTO search_documents (kind, resource_id, course_id, content_hash) VALUES (?1, ?2, ?3, ?4)")?
+                    .execute(params![kind.as_str(), resource_id, course_id, hash])?;
+                (self.conn.last_insert_rowid() as u64, SyncOutcome::Inserted)
+            }
+        };
+        self.conn
+            .prepare_cached("INSERT INTO search_index (rowid, title, body) VALUES (?1, ?2, ?3)")?
+            .execute(params![id, title, body])?;
+        Ok(outcome)
+    }
+
+    /// Tombstone or restore a row's search entry along with the row itself
+    fn set_document_deleted(&self, table: &str, id: u64, deleted_at: Option<&str>) -> Result<()> {
+        let kind = match table {
+            "assignments" => DocumentKind::Assignment,
+            "discussions" => DocumentKind::Discussion,
+            "module_items" => DocumentKind::ModuleItem,
+            "pages" => DocumentKind::Page,
+            _ => return Ok(()),
+        };
+        self.conn
+            .prepare_cached("UPDATE search_documents SET deleted_at = ?1 WHERE kind = ?2 AND resource_id = ?3")?
+            .execute(params![deleted_at, kind.as_str(), id])?;
+        Ok(())
+    }
+
+    /// Full-text search over synced content, best matches first.
+    ///
+    /// `query` is what the user typed (see [`search::match_expression`]);
+    /// `None` is returned when it has no words to look for. Tombstoned entries,
+    /// and everything in a tombstoned course, are left out unless `include_deleted`.
+    pub fn search(&self, query: &str, course_id: Option<u64>, include_deleted: bool, limit: usize) -> Result<Option<Vec<SearchHit>>> {
+        let Some(expression) = search::match_expression(query) else {
+            return Ok(None);
+        };
+        // Matches in titles weigh more than matches in bodies
+        let mut stmt = self.conn.prepare(
+            "SELECT d.kind, d.resource_id, d.course_id, courses.course_code, search_index.title,
+                snippet(search_index, -1, ?5, ?6, '...', 16), bm25(search_index, 5.0, 1.0) AS score,
+                d.deleted_at
+            FROM search_index
+            JOIN search_documents d ON d.id = search_index.rowid
+            LEFT JOIN courses ON courses.id = d.course_id
+            WHERE search_index MATCH ?1 AND (?2 IS NULL OR d.course_id = ?2) AND (?3 OR (d.deleted_at IS NULL AND courses.deleted_at IS NULL))
+            ORDER BY score
+            LIMIT ?4"
         )?;
+
+        let (open, close) = search::HIGHLIGHT;
+        let hits = stmt.query_map(params![expression, course_id, include_deleted, limit as i64, open, close], |row| {
+            let kind: String = row.get(0)?;
+            Ok(SearchHit {
+                kind: kind.parse().map_err(|e: String| {
+                    rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+                })?,
+                resource_id: row.get(1)?,
+                course_id: row.get(2)?,
+                course_code: row.get(3)?,
+                title: row.get(4)?,
+                snippet: row.get(5)?,
+                rank: row.get(6)?,
+                deleted_at: parse_datetime(row.get(7)?),
+            })
+        })?;
+
+        hits.collect::<Result<_>>().map(Some)
+    }
+
+    /// Write a row only if its content hash changed; otherwise just mark it as seen.
+    ///
+    /// A tombstoned row that shows up again is restored and counts as updated.
+    fn upsert(&self, table: &str, id: u64, hash: &str, write: impl FnOnce() -> Result<usize>) -> Result<SyncOutcome> {
+        let existing: Option<(Option<String>, bool)> = self.conn
+            .prepare_cached(&format!("SELECT content_hash, deleted_at IS NOT NULL FROM {} WHERE id = ?1", table))?
+            .query_row([id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()?;
+
+        match existing {
+            Some((Some(existing), deleted)) if existing == hash => {
+                self.conn
+                    .prepare_cached(&format!("UPDATE {} SET last_sync = ?1, deleted_at = NULL WHERE id = ?2", table))?
+                    .execute(params![Utc::now().to_rfc3339(), id])?;
+                if deleted {
+                    self.set_document_deleted(table, id, None)?;
+                }
+                Ok(if deleted { SyncOutcome::Updated } else { SyncOutcome::Unchanged })
+            }
+            Some(_) => write().map(|_| SyncOutcome::Updated),
+            None => write().map(|_| SyncOutcome::Inserted),
+        }
+    }
+
+    /// Tombstone cached rows in `scope` that are missing from `remote_ids`, the
+    /// complete set Canvas just returned. Returns the IDs newly marked deleted.
+    pub fn mark_deleted(&self, scope: CacheScope, remote_ids: &[u64]) -> Result<Vec<u64>> {
+        let (table, parent) = scope.table();
+        let live: Vec<u64> = match parent {
+            Some((column, parent_id)) => self.conn
+                .prepare_cached(&format!("SELECT id FROM {} WHERE {} = ?1 AND deleted_at IS NULL", table, column))?
+                .query_map([parent_id], |row| row.get(0))?
+                .collect::<Result<_>>()?,
+            None => self.conn
+                .prepare_cached(&format!("SELECT id FROM {} WHERE deleted_at IS NULL", table))?
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_>>()?,
+        };
+
+        let remote: HashSet<u64> = remote_ids.iter().copied().collect();
+        let gone: Vec<u64> = live.into_iter().filter(|id| !remote.contains(id)).collect();
+        let now = Utc::now().to_rfc3339();
+        let mut tombstone = self.conn.prepare_cached(&format!("UPDATE {} SET deleted_at = ?1 WHERE id = ?2", table))?;
+        for id in &gone {
+            tombstone.execute(params![now, id])?;
+            self.set_document_deleted(table, *id, Some(&now))?;
+        }
+        Ok(gone)
+    }
+
+    /// When `resource` was last synced completely for `scope_id` (a course ID, or 0 for global lists)
+    pub fn cursor(&self, resource: &str, scope_id: u64) -> Result<Option<DateTime<Utc>>> {
+        let synced_at: Option<String> = self.conn
+            .prepare_cached("SELECT synced_at FROM sync_cursors WHERE resource = ?1 AND scope_id = ?2")?
+            .query_row(params![resource, scope_id], |row| row.get(0))
+            .optional()?;
+        Ok(parse_datetime(synced_at))
+    }
+
+    /// Record a complete sync of `resource` for `scope_id` as of `synced_at`
+    pub fn set_cursor(&self, resource: &str, scope_id: u64, synced_at: DateTime<Utc>) -> Result<()> {
+        self.conn
+            .prepare_cached("INSERT OR REPLACE INTO sync_cursors (resource, scope_id, synced_at) VALUES (?1, ?2, ?3)")?
+            .execute(params![resource, scope_id, synced_at.to_rfc3339()])?;
+        Ok(())
+    }
+
+    /// Append an entry to the change log
+    pub fn record_change(&self, change: &Change) -> Result<()> {
+        self.conn.prepare_cached(
+            "INSERT INTO changes (recorded_at, course_id, kind, resource_id, title, detail)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+        )?.execute(params![
+            change.recorded_at.to_rfc3339(),
+            change.course_id,
+            change.kind.as_str(),
+            change.resource_id,
+            change.title,
+            change.detail
+        ])?;
         Ok(())
     }
 
-    pub fn get_stale_courses(&self, hours: i64) -> Result<Vec<u64>> {
+    /// Logged changes recorded after `since` (or all of them), oldest first,
+    /// optionally limited to one course
+    pub fn changes_since(&self, since: Option<DateTime<Utc>>, course_id: Option<u64>) -> Result<Vec<Change>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT changes.recorded_at, changes.course_id, courses.course_code, changes.kind,
+                changes.resource_id, changes.title, changes.detail
+            FROM changes LEFT JOIN courses ON courses.id = changes.course_id
+            WHERE (?1 IS NULL OR changes.recorded_at > ?1) AND (?2 IS NULL OR changes.course_id = ?2)
+            ORDER BY changes.recorded_at, changes.id"
+        )?;
+
+        let changes = stmt.query_map(params![since.map(|dt| dt.to_rfc3339()), course_id], |row| {
+            let kind: String = row.get(3)?;
+            Ok(Change {
+                recorded_at: parse_datetime(row.get(0)?).unwrap_or_default(),
+                course_id: row.get(1)?,
+                course_code: row.get(2)?,
+                kind: kind.parse().map_err(|e: String| {
+                    rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, e.into())
+                })?,
+                resource_id: row.get(4)?,
+                title: row.get(5)?,
+                detail: row.get(6)?,
+            })
+        })?;
+
+        changes.collect()
+    }
+
+    /// Every cached course, with tombstoned ones only if `include_deleted` is set
+    pub fn get_courses(&self, include_deleted: bool) -> Result<Vec<Course>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, course_code, enrollment_term_id, total_students, start_at, end_at, deleted_at
+            FROM courses WHERE deleted_at IS NULL OR ?1 ORDER BY id"
+        )?;
+
+        let courses = stmt.query_map([include_deleted], |row| {
+            Ok(Course {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                course_code: row.get(2)?,
+                enrollment_term_id: row.get(3)?,
+                total_students: row.get(4)?,
+                start_at: parse_datetime(row.get(5)?),
+                end_at: parse_datetime(row.get(6)?),
+                deleted_at: parse_datetime(row.get(7)?),
+            })
+        })?;
+
+        courses.collect()
+    }
+
+    /// Cached assignments of a course; fields the cache doesn't store are left empty
+    pub fn get_assignments(&self, course_id: u64, include_deleted: bool) -> Result<Vec<Assignment>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, description, due_at, points_possible, html_url, deleted_at
+            FROM assignments WHERE course_id = ?1 AND (deleted_at IS NULL OR ?2)
+            ORDER BY due_at IS NULL, due_at, id"
+        )?;
+
+        let assignments = stmt.query_map(params![course_id, include_deleted], |row| {
+            Ok(Assignment {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                due_at: parse_datetime(row.get(3)?),
+                points_possible: row.get(4)?,
+                html_url: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                submission_types: Vec::new(),
+                allowed_extensions: None,
+                submission: None,
+                rubric: None,
+                deleted_at: parse_datetime(row.get(6)?),
+            })
+        })?;
+
+        assignments.collect()
+    }
+
+    /// Cached discussions of a course
+    pub fn get_discussions(&self, course_id: u64, include_deleted: bool) -> Result<Vec<Discussion>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, title, message, posted_at, discussion_type, pinned, deleted_at,
+                last_reply_at, discussion_subentry_count
+            FROM discussions WHERE course_id = ?1 AND (deleted_at IS NULL OR ?2)
+            ORDER BY posted_at DESC, id"
+        )?;
+
+        let discussions = stmt.query_map(params![course_id, include_deleted], |row| {
+            Ok(Discussion {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                message: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                html_url: String::new(),
+                posted_at: parse_datetime(row.get(3)?),
+                last_reply_at: parse_datetime(row.get(7)?),
+                allow_rating: false,
+                user_can_see_posts: true,
+                discussion_type: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                discussion_subentry_count: row.get::<_, Option<i32>>(8)?.unwrap_or(0),
+                published: true,
+                locked: false,
+                pinned: row.get::<_, Option<bool>>(5)?.unwrap_or(false),
+                deleted_at: parse_datetime(row.get(6)?),
+            })
+        })?;
+
+        discussions.collect()
+    }
+
+    /// Cached modules of a course, with `items_count` counted from the cached live items
+    pub fn get_modules(&self, course_id: u64, include_deleted: bool) -> Result<Vec<Module>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id FROM courses 
-            WHERE datetime(last_sync) < datetime('now', ?1 || ' hours')"
+            "SELECT id, name, position, unlock_at, state, completed_at,
+                (SELECT COUNT(*) FROM module_items
+                    WHERE module_items.module_id = modules.id AND module_items.deleted_at IS NULL),
+                deleted_at
+            FROM modules WHERE course_id = ?1 AND (deleted_at IS NULL OR ?2)
+            ORDER BY position, id"
         )?;
-        
-        let course_ids: Result<Vec<u64>> = stmt
-            .query_map([format!("-{}", hours)], |row| row.get(0))?
-            .collect();
 
-        course_ids
+        let modules = stmt.query_map(params![course_id, include_deleted], |row| {
+            Ok(Module {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                position: row.get::<_, Option<i32>>(2)?.unwrap_or_default(),
+                unlock_at: parse_datetime(row.get(3)?),
+                require_sequential_progress: false,
+                publish_final_grade: false,
+                prerequisite_module_ids: None,
+                state: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                completed_at: parse_datetime(row.get(5)?),
+                items_count: row.get(6)?,
+                items_url: String::new(),
+                items: None,
+                deleted_at: parse_datetime(row.get(7)?),
+            })
+        })?;
+
+        modules.collect()
     }
 
-    pub fn get_sync_status(&self) -> Result<Vec<(String, DateTime<Utc>)>> {
+    /// Cached items of a module
+    pub fn get_module_items(&self, module_id: u64, include_deleted: bool) -> Result<Vec<ModuleItem>> {
         let mut stmt = self.conn.prepare(
-            "SELECT type_name, last_sync FROM sync_status"
+            "SELECT id, module_id, position, title, item_type, content_id, deleted_at
+            FROM module_items WHERE module_id = ?1 AND (deleted_at IS NULL OR ?2)
+            ORDER BY position, id"
         )?;
-        
-        let status = stmt.query_map([], |row| {
-            let last_sync_str: String = row.get(1)?;
-            let last_sync = DateTime::parse_from_rfc3339(&last_sync_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or_else(|_| Utc::now());
-            
-            Ok((row.get(0)?, last_sync))
+
+        let items = stmt.query_map(params![module_id, include_deleted], |row| {
+            Ok(ModuleItem {
+                id: row.get(0)?,
+                module_id: row.get(1)?,
+                position: row.get::<_, Option<i32>>(2)?.unwrap_or_default(),
+                title: row.get(3)?,
+                indent: 0,
+                item_type: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                html_url: String::new(),
+                content_id: row.get(5)?,
+                external_url: None,
+                completion_requirement: None,
+                deleted_at: parse_datetime(row.get(6)?),
+            })
         })?;
 
-        status.collect()
+        items.collect()
+    }
+
+    /// Cached wiki pages of a course, by title, with their bodies
+    pub fn get_pages(&self, course_id: u64, include_deleted: bool) -> Result<Vec<Page>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, url, title, body, published, front_page, html_url, created_at, updated_at, deleted_at
+            FROM pages WHERE course_id = ?1 AND (deleted_at IS NULL OR ?2)
+            ORDER BY title COLLATE NOCASE, id"
+        )?;
+
+        let pages = stmt.query_map(params![course_id, include_deleted], page_from_row)?;
+        pages.collect()
+    }
+
+    /// A cached wiki page, looked up by URL slug or page ID
+    pub fn get_page(&self, course_id: u64, page: &str, include_deleted: bool) -> Result<Option<Page>> {
+        self.conn.query_row(
+            "SELECT id, url, title, body, published, front_page, html_url, created_at, updated_at, deleted_at
+            FROM pages WHERE course_id = ?1 AND (url = ?2 OR CAST(id AS TEXT) = ?2) AND (deleted_at IS NULL OR ?3)",
+            params![course_id, page, include_deleted],
+            page_from_row,
+        ).optional()
+    }
+
+    /// When the rows in `scope` were last synced, or `None` if nothing is cached.
+    ///
+    /// Uses the sync cursor when there is one, since an incremental sync
+    /// doesn't revisit every row; otherwise the oldest row's `last_sync`.
+    pub fn synced_at(&self, scope: CacheScope) -> Result<Option<DateTime<Utc>>> {
+        let (cursor_sql, rows_sql, id) = match scope {
+            CacheScope::Courses => (
+                "SELECT synced_at FROM sync_cursors WHERE resource = 'courses' AND scope_id = ?1",
+                "SELECT MIN(last_sync) FROM courses WHERE ?1 = 0",
+                0,
+            ),
+            CacheScope::Assignments(id) => (
+                "SELECT synced_at FROM sync_cursors WHERE resource = 'assignments' AND scope_id = ?1",
+                "SELECT MIN(last_sync) FROM assignments WHERE course_id = ?1",
+                id,
+            ),
+            CacheScope::Discussions(id) => (
+                "SELECT synced_at FROM sync_cursors WHERE resource = 'discussions' AND scope_id = ?1",
+                "SELECT MIN(last_sync) FROM discussions WHERE course_id = ?1",
+                id,
+            ),
+            CacheScope::Modules(id) => (
+                "SELECT synced_at FROM sync_cursors WHERE resource = 'modules' AND scope_id = ?1",
+                "SELECT MIN(last_sync) FROM modules WHERE course_id = ?1",
+                id,
+            ),
+            CacheScope::Pages(id) => (
+                "SELECT synced_at FROM sync_cursors WHERE resource = 'pages' AND scope_id = ?1",
+                "SELECT MIN(last_sync) FROM pages WHERE course_id = ?1",
+                id,
+            ),
+            // Items are synced together with their course's modules
+            CacheScope::ModuleItems(id) => (
+                "SELECT synced_at FROM sync_cursors
+                WHERE resource = 'modules' AND scope_id = (SELECT course_id FROM modules WHERE id = ?1)",
+                "SELECT MIN(last_sync) FROM module_items WHERE module_id = ?1",
+                id,
+            ),
+        };
+        let cursor: Option<String> = self.conn.query_row(cursor_sql, [id], |row| row.get(0)).optional()?;
+        let synced_at = match cursor {
+            Some(cursor) => Some(cursor),
+            None => self.conn.query_row(rows_sql, [id], |row| row.get(0))?,
+        };
+        Ok(parse_datetime(synced_at))
     }
-}
\ No newline at end of file
+
+}
+
+/// Stable hash of a value's JSON form, used to skip rewriting unchanged rows.
+///
+/// FNV-1a rather than `DefaultHasher`, whose output may change between Rust
+/// releases and would then make every stored hash look stale.
+pub fn content_hash<T: Serialize + ?Sized>(value: &T) -> String {
+    let bytes = serde_json::to_vec(value).unwrap_or_default();
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+fn page_from_row(row: &rusqlite::Row) -> Result<Page> {
+    Ok(Page {
+        page_id: row.get(0)?,
+        url: row.get(1)?,
+        title: row.get(2)?,
+        body: row.get(3)?,
+        published: row.get::<_, Option<bool>>(4)?.unwrap_or(true),
+        front_page: row.get::<_, Option<bool>>(5)?.unwrap_or(false),
+        html_url: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+        created_at: parse_datetime(row.get(7)?).unwrap_or_default(),
+        updated_at: parse_datetime(row.get(8)?).unwrap_or_default(),
+        editing_roles: String::new(),
+        deleted_at: parse_datetime(row.get(9)?),
+    })
+}
+
+fn parse_datetime(value: Option<String>) -> Option<DateTime<Utc>> {
+    value.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+}
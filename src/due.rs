@@ -0,0 +1,154 @@
+use crate::models::{Assignment, Course, Submission};
+use crate::offline::format_age;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::Serialize;
+use std::fmt;
+
+/// Where a deadline falls relative to now, in the user's time zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DueBucket {
+    Overdue,
+    Today,
+    /// Within the next seven days, after today
+    ThisWeek,
+    Later,
+}
+
+impl DueBucket {
+    pub fn of<Tz: TimeZone>(due_at: DateTime<Utc>, now: &DateTime<Tz>) -> Self {
+        if due_at < now.with_timezone(&Utc) {
+            return DueBucket::Overdue;
+        }
+        let today = now.date_naive();
+        let day = due_at.with_timezone(&now.timezone()).date_naive();
+        if day == today {
+            DueBucket::Today
+        } else if day < today + Duration::days(7) {
+            DueBucket::ThisWeek
+        } else {
+            DueBucket::Later
+        }
+    }
+
+    /// Heading for the group in table output
+    pub fn heading(&self) -> &'static str {
+        match self {
+            DueBucket::Overdue => "Overdue",
+            DueBucket::Today => "Due today",
+            DueBucket::ThisWeek => "Due this week",
+            DueBucket::Later => "Later",
+        }
+    }
+}
+
+/// Where the user's own submission for an assignment stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionStatus {
+    Graded,
+    Submitted,
+    /// Submitted after the deadline
+    Late,
+    Missing,
+    NotSubmitted,
+    /// Cached assignments don't record submissions
+    Unknown,
+}
+
+impl SubmissionStatus {
+    pub fn of(submission: Option<&Submission>) -> Self {
+        let Some(submission) = submission else {
+            return SubmissionStatus::Unknown;
+        };
+        if submission.workflow_state.as_deref() == Some("graded") {
+            SubmissionStatus::Graded
+        } else if submission.submitted_at.is_some() {
+            if submission.late { SubmissionStatus::Late } else { SubmissionStatus::Submitted }
+        } else if submission.missing {
+            SubmissionStatus::Missing
+        } else {
+            SubmissionStatus::NotSubmitted
+        }
+    }
+
+    /// Whether there is nothing left for the user to hand in
+    pub fn is_done(&self) -> bool {
+        matches!(self, SubmissionStatus::Graded | SubmissionStatus::Submitted | SubmissionStatus::Late)
+    }
+}
+
+/// One row of the `due` dashboard
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DueItem {
+    pub bucket: DueBucket,
+    pub course_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub course_code: Option<String>,
+    pub assignment_id: u64,
+    pub name: String,
+    pub due_at: DateTime<Utc>,
+    /// `due_at` in local time, with how far off it is
+    pub due: String,
+    pub points_possible: Option<f64>,
+    pub status: SubmissionStatus,
+    pub html_url: String,
+}
+
+/// Assignments of several courses due within `days` of `now`, soonest first.
+///
+/// Overdue assignments from the last `days` days are included unless they
+/// have been handed in. Assignments without a due date are left out.
+pub fn due_items<Tz: TimeZone>(courses: &[(Course, Vec<Assignment>)], now: &DateTime<Tz>, days: i64) -> Vec<DueItem>
+where
+    Tz::Offset: fmt::Display,
+{
+    // Windows reaching past the dates chrono can represent are unbounded
+    let utc_now = now.with_timezone(&Utc);
+    let horizon = Duration::try_days(days);
+    let earliest = horizon.and_then(|h| utc_now.checked_sub_signed(h));
+    let latest = horizon.and_then(|h| utc_now.checked_add_signed(h));
+    let mut items: Vec<DueItem> = courses.iter()
+        .flat_map(|(course, assignments)| assignments.iter().map(move |a| (course, a)))
+        .filter(|(_, a)| a.deleted_at.is_none())
+        .filter_map(|(course, assignment)| {
+            let due_at = assignment.due_at?;
+            let status = SubmissionStatus::of(assignment.submission.as_ref());
+            let bucket = DueBucket::of(due_at, now);
+            let in_window = if bucket == DueBucket::Overdue {
+                earliest.is_none_or(|earliest| earliest <= due_at) && !status.is_done()
+            } else {
+                latest.is_none_or(|latest| due_at <= latest)
+            };
+            in_window.then(|| DueItem {
+                bucket,
+                course_id: course.id,
+                course_code: course.course_code.clone(),
+                assignment_id: assignment.id,
+                name: assignment.name.clone(),
+                due_at,
+                due: format_due(due_at, now),
+                points_possible: assignment.points_possible,
+                status,
+                html_url: assignment.html_url.clone(),
+            })
+        })
+        .collect();
+    items.sort_by(|a, b| a.due_at.cmp(&b.due_at).then(a.course_id.cmp(&b.course_id)));
+    items
+}
+
+/// A deadline in local time, e.g. "Fri Mar 8 23:59 (in 2 days)"
+pub fn format_due<Tz: TimeZone>(due_at: DateTime<Utc>, now: &DateTime<Tz>) -> String
+where
+    Tz::Offset: fmt::Display,
+{
+    let local = due_at.with_timezone(&now.timezone());
+    let now = now.with_timezone(&Utc);
+    let relative = if due_at >= now {
+        format!("in {}", format_age(due_at - now))
+    } else {
+        format!("{} ago", format_age(now - due_at))
+    };
+    format!("{} ({})", local.format("%a %b %-d %H:%M"), relative)
+}
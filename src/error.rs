@@ -0,0 +1,152 @@
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, CanvasError>;
+
+/// Errors returned by `CanvasClient`
+#[derive(Debug)]
+pub enum CanvasError {
+    /// The access token is missing, invalid or expired (401)
+    Unauthorized,
+    /// The token is valid but lacks permission for this resource (403)
+    Forbidden(String),
+    /// The requested resource does not exist (404)
+    NotFound(String),
+    /// Canvas throttled the request (429, or 403 "Rate Limit Exceeded")
+    RateLimited,
+    /// Canvas rejected the request, with the messages from its `errors` body
+    Validation(Vec<String>),
+    /// Any other non-success response
+    Api { status: StatusCode, message: String },
+    /// The response body did not match the expected model
+    Deserialize { path: String, source: serde_json::Error },
+    /// The request could not be sent or the response could not be read
+    Transport(reqwest::Error),
+    /// A local file could not be read
+    Io(std::io::Error),
+    /// A local file cannot be submitted, e.g. because of its extension
+    InvalidFile(String),
+    /// The assignment does not accept this kind of submission
+    UnsupportedSubmissionType { requested: String, allowed: Vec<String> },
+}
+
+impl CanvasError {
+    /// Build an error from a non-success response status and its body
+    pub fn from_response(status: StatusCode, url: &str, body: &str) -> Self {
+        let messages = error_messages(body);
+        match status {
+            StatusCode::UNAUTHORIZED => CanvasError::Unauthorized,
+            StatusCode::TOO_MANY_REQUESTS => CanvasError::RateLimited,
+            StatusCode::FORBIDDEN if body.contains("Rate Limit Exceeded") => CanvasError::RateLimited,
+            StatusCode::FORBIDDEN => CanvasError::Forbidden(url.to_string()),
+            StatusCode::NOT_FOUND => CanvasError::NotFound(url.to_string()),
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY if !messages.is_empty() => {
+                CanvasError::Validation(messages)
+            }
+            _ => CanvasError::Api {
+                status,
+                message: if messages.is_empty() { body.trim().to_string() } else { messages.join("; ") },
+            },
+        }
+    }
+}
+
+/// Collect the messages from a Canvas `{"errors": ...}` body.
+///
+/// Canvas uses several shapes: a list of strings, a list of `{"message": ...}`
+/// objects, or an object mapping field names to lists of such objects.
+fn error_messages(body: &str) -> Vec<String> {
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return Vec::new();
+    };
+    let mut messages = Vec::new();
+    if let Some(errors) = value.get("errors") {
+        collect_messages(None, errors, &mut messages);
+    } else if let Some(message) = value.get("message").and_then(Value::as_str) {
+        messages.push(message.to_string());
+    }
+    messages
+}
+
+fn collect_messages(field: Option<&str>, value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(message) => out.push(match field {
+            Some(field) => format!("{}: {}", field, message),
+            None => message.clone(),
+        }),
+        Value::Array(items) => {
+            for item in items {
+                collect_messages(field, item, out);
+            }
+        }
+        Value::Object(map) => {
+            if let Some(message) = map.get("message").filter(|m| m.is_string()) {
+                collect_messages(field, message, out);
+            } else {
+                for (key, item) in map {
+                    collect_messages(Some(key), item, out);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+impl fmt::Display for CanvasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CanvasError::Unauthorized => write!(f, "unauthorized: check that your access token is valid"),
+            CanvasError::Forbidden(url) => write!(f, "forbidden: you do not have access to {}", url),
+            CanvasError::NotFound(url) => write!(f, "not found: {}", url),
+            CanvasError::RateLimited => write!(f, "rate limited by Canvas, try again later"),
+            CanvasError::Validation(messages) => write!(f, "Canvas rejected the request: {}", messages.join("; ")),
+            CanvasError::Api { status, message } => write!(f, "Canvas returned {}: {}", status, message),
+            CanvasError::Deserialize { path, source } => {
+                write!(f, "unexpected response from Canvas at `{}`: {}", path, source)
+            }
+            CanvasError::Transport(e) => write!(f, "request failed: {}", e),
+            CanvasError::Io(e) => write!(f, "{}", e),
+            CanvasError::InvalidFile(message) => write!(f, "{}", message),
+            CanvasError::UnsupportedSubmissionType { requested, allowed } => write!(
+                f,
+                "this assignment does not accept {} submissions (allowed: {})",
+                requested,
+                if allowed.is_empty() { "none".to_string() } else { allowed.join(", ") }
+            ),
+        }
+    }
+}
+
+impl Error for CanvasError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CanvasError::Deserialize { source, .. } => Some(source),
+            CanvasError::Transport(e) => Some(e),
+            CanvasError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for CanvasError {
+    fn from(e: reqwest::Error) -> Self {
+        CanvasError::Transport(e)
+    }
+}
+
+impl From<std::io::Error> for CanvasError {
+    fn from(e: std::io::Error) -> Self {
+        CanvasError::Io(e)
+    }
+}
+
+impl From<serde_path_to_error::Error<serde_json::Error>> for CanvasError {
+    fn from(e: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        CanvasError::Deserialize {
+            path: e.path().to_string(),
+            source: e.into_inner(),
+        }
+    }
+}
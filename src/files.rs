@@ -0,0 +1,235 @@
+use crate::client::CanvasClient;
+use crate::error::{CanvasError, Result};
+use crate::models::*;
+use reqwest::header::RANGE;
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+impl CanvasClient {
+    /// Every folder of a course, as a flat list
+    pub fn get_folders(&self, course_id: u64) -> Result<Vec<Folder>> {
+        self.get_all(&self.api_url(&format!("courses/{}/folders", course_id)))
+    }
+
+    /// Every file of a course, in all of its folders
+    pub fn get_course_files(&self, course_id: u64) -> Result<Vec<CanvasFile>> {
+        self.get_all(&self.api_url(&format!("courses/{}/files", course_id)))
+    }
+
+    pub fn get_file(&self, file_id: u64) -> Result<CanvasFile> {
+        self.get(&self.api_url(&format!("files/{}", file_id)))
+    }
+
+    /// Download a file to `dest`.
+    ///
+    /// Data is written to a `.part` file next to `dest` first. If an earlier
+    /// download of the same version was interrupted, it picks up where that
+    /// one stopped. Once complete, the file is moved into place with its
+    /// modification time set to the file's `updated_at`, which is what
+    /// [`is_current`] checks.
+    pub fn download_file(&self, file: &CanvasFile, dest: &Path) -> Result<()> {
+        if file.url.is_empty() || file.locked_for_user {
+            return Err(CanvasError::Forbidden(file.display_name.clone()));
+        }
+        if let Some(dir) = dest.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let part = part_path(file, dest);
+        let mut offset = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+        if offset > file.size {
+            fs::remove_file(&part)?;
+            offset = 0;
+        }
+
+        if offset < file.size || file.size == 0 {
+            let mut request = self.http().get(&file.url);
+            if offset > 0 {
+                request = request.header(RANGE, format!("bytes={}-", offset));
+            }
+            let mut resp = self.send(request)?;
+            // Servers that ignore the range send the whole file again
+            let resumed = resp.status() == StatusCode::PARTIAL_CONTENT;
+            let mut out = OpenOptions::new().create(true).append(resumed).write(true).truncate(!resumed).open(&part)?;
+            resp.copy_to(&mut out)?;
+        }
+
+        let written = fs::metadata(&part)?.len();
+        if written != file.size {
+            return Err(CanvasError::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("download of {} stopped after {} of {} bytes; run it again to resume", file.display_name, written, file.size),
+            )));
+        }
+        fs::rename(&part, dest)?;
+        File::options().write(true).open(dest)?.set_modified(SystemTime::from(file.updated_at))?;
+        Ok(())
+    }
+}
+
+/// Where a download is kept until it completes. The name includes the
+/// version, so a partial download is never resumed with newer content.
+fn part_path(file: &CanvasFile, dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.part", file.updated_at.timestamp()));
+    dest.with_file_name(name)
+}
+
+/// Whether `path` already holds this version of the file: same size, and
+/// modified at the file's `updated_at`
+pub fn is_current(file: &CanvasFile, path: &Path) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    let modified = metadata.modified().ok().map(|m| chrono::DateTime::<chrono::Utc>::from(m).timestamp());
+    metadata.len() == file.size && modified == Some(file.updated_at.timestamp())
+}
+
+/// A file with its path relative to the folder it was listed from
+#[derive(Debug, Serialize)]
+pub struct CourseFile {
+    pub path: String,
+    #[serde(flatten)]
+    pub file: CanvasFile,
+}
+
+impl CourseFile {
+    /// A file on its own, named after its display name
+    pub fn named(file: CanvasFile) -> Self {
+        CourseFile { path: safe_name(&file.display_name), file }
+    }
+}
+
+/// A folder's path below the course root, e.g. `Week 1/Slides`, or `""` for the root
+pub fn folder_path(folder: &Folder) -> &str {
+    folder.full_name.split_once('/').map_or("", |(_, rest)| rest)
+}
+
+/// Find a folder by ID or by its path below the course root (`/` is the root)
+pub fn find_folder<'a>(folders: &'a [Folder], folder: &str) -> Option<&'a Folder> {
+    let path = folder.trim_matches('/');
+    folders.iter().find(|f| folder.parse() == Ok(f.id) || folder_path(f) == path)
+}
+
+/// Course files inside `within` (the whole course if `None`), with paths
+/// relative to it, sorted by path. Names that aren't safe as local paths are
+/// adjusted, and files sharing a path get their ID appended to the name,
+/// except for the oldest one.
+pub fn files_in(folders: &[Folder], files: Vec<CanvasFile>, within: Option<&Folder>) -> Vec<CourseFile> {
+    let paths: HashMap<u64, &str> = folders.iter().map(|f| (f.id, folder_path(f))).collect();
+    let base = within.map_or("", folder_path);
+    let mut located: Vec<CourseFile> = files.into_iter()
+        .filter_map(|file| {
+            let folder = paths.get(&file.folder_id?)?;
+            let relative = if base.is_empty() {
+                *folder
+            } else if *folder == base {
+                ""
+            } else {
+                folder.strip_prefix(base)?.strip_prefix('/')?
+            };
+            let mut path: Vec<String> = relative.split('/').filter(|c| !c.is_empty()).map(safe_name).collect();
+            path.push(safe_name(&file.display_name));
+            Some(CourseFile { path: path.join("/"), file })
+        })
+        .collect();
+    located.sort_by(|a, b| a.path.cmp(&b.path).then(a.file.id.cmp(&b.file.id)));
+    let mut previous = None;
+    for located in &mut located {
+        let path = located.path.clone();
+        if previous.as_ref() == Some(&path) {
+            located.path = with_id(&path, located.file.id);
+        }
+        previous = Some(path);
+    }
+    located.sort_by(|a, b| a.path.cmp(&b.path));
+    located
+}
+
+/// `path` with `-<id>` added to its file name, before the extension
+fn with_id(path: &str, id: u64) -> String {
+    let name_start = path.rfind('/').map_or(0, |i| i + 1);
+    match path[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let (stem, extension) = path.split_at(name_start + dot);
+            format!("{}-{}{}", stem, id, extension)
+        }
+        _ => format!("{}-{}", path, id),
+    }
+}
+
+/// A name usable as one local path component
+fn safe_name(name: &str) -> String {
+    match name {
+        "" | "." | ".." => "_".to_string(),
+        name => name.replace(['/', '\\'], "_"),
+    }
+}
+
+/// What a folder download or mirror did
+#[derive(Debug, Default)]
+pub struct MirrorReport {
+    pub downloaded: usize,
+    /// Files whose local copy was already current
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+/// Delete files below `dir` that aren't among `files`, and the directories
+/// left empty, returning the deleted files' paths relative to `dir`.
+///
+/// Partial downloads of listed files are kept so they can still resume.
+pub fn prune(files: &[CourseFile], dir: &Path) -> io::Result<Vec<String>> {
+    let mut keep = HashSet::new();
+    for CourseFile { path, file } in files {
+        let dest = dir.join(path);
+        keep.insert(part_path(file, &dest));
+        keep.insert(dest);
+    }
+    let mut removed = Vec::new();
+    prune_dir(dir, dir, &keep, &mut removed)?;
+    removed.sort();
+    Ok(removed)
+}
+
+fn prune_dir(root: &Path, dir: &Path, keep: &HashSet<PathBuf>, removed: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if fs::symlink_metadata(&path)?.is_dir() {
+            prune_dir(root, &path, keep, removed)?;
+            if fs::read_dir(&path)?.next().is_none() {
+                fs::remove_dir(&path)?;
+            }
+        } else if !keep.contains(&path) {
+            fs::remove_file(&path)?;
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            removed.push(relative.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
+
+/// Download `files` below `dir`, skipping those already current there.
+///
+/// A failed file is reported and the rest still downloaded; running again
+/// resumes it.
+pub fn mirror(client: &CanvasClient, files: &[CourseFile], dir: &Path, progress: impl Fn(&str)) -> MirrorReport {
+    let mut report = MirrorReport::default();
+    for CourseFile { path, file } in files {
+        let dest = dir.join(path);
+        if is_current(file, &dest) {
+            report.skipped += 1;
+            continue;
+        }
+        progress(path);
+        match client.download_file(file, &dest) {
+            Ok(()) => report.downloaded += 1,
+            Err(e) => report.errors.push(format!("could not download {}: {}", path, e)),
+        }
+    }
+    report
+}
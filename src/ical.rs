@@ -0,0 +1,118 @@
+use crate::models::{Assignment, Course};
+use crate::render::plain_text;
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Longest content line allowed before folding, in octets (RFC 5545 §3.1)
+const MAX_LINE: usize = 75;
+
+/// A calendar entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// Globally unique and stable across exports, so calendar apps update the
+    /// event on re-import instead of adding a copy
+    pub uid: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub summary: String,
+    pub description: Option<String>,
+    pub url: Option<String>,
+}
+
+impl Event {
+    /// The due date of an assignment, or `None` if it has none.
+    ///
+    /// `domain` is the Canvas host, which keeps UIDs from different
+    /// instances apart.
+    pub fn for_assignment(domain: &str, course: &Course, assignment: &Assignment) -> Option<Self> {
+        let due_at = assignment.due_at?;
+        let summary = match &course.course_code {
+            Some(code) => format!("[{}] {}", code, assignment.name),
+            None => assignment.name.clone(),
+        };
+        Some(Event {
+            uid: format!("assignment-{}@{}", assignment.id, domain),
+            start: due_at,
+            end: due_at,
+            summary,
+            description: assignment.description.as_deref().map(plain_text).filter(|d| !d.is_empty()),
+            url: Some(assignment.html_url.clone()),
+        })
+    }
+}
+
+/// Write an RFC 5545 calendar with the given events
+pub fn write_calendar(out: &mut impl Write, name: &str, events: &[Event], now: DateTime<Utc>) -> io::Result<()> {
+    let mut line = |content: String| write_line(out, &content);
+    line("BEGIN:VCALENDAR".to_string())?;
+    line("VERSION:2.0".to_string())?;
+    line(format!("PRODID:-//canvas-cli//canvas-cli {}//EN", env!("CARGO_PKG_VERSION")))?;
+    line("CALSCALE:GREGORIAN".to_string())?;
+    line("METHOD:PUBLISH".to_string())?;
+    line(format!("X-WR-CALNAME:{}", escape_text(name)))?;
+    for event in events {
+        line("BEGIN:VEVENT".to_string())?;
+        line(format!("UID:{}", event.uid))?;
+        line(format!("DTSTAMP:{}", format_time(now)))?;
+        line(format!("DTSTART:{}", format_time(event.start)))?;
+        line(format!("DTEND:{}", format_time(event.end)))?;
+        line(format!("SUMMARY:{}", escape_text(&event.summary)))?;
+        if let Some(description) = &event.description {
+            line(format!("DESCRIPTION:{}", escape_text(description)))?;
+        }
+        if let Some(url) = &event.url {
+            line(format!("URL:{}", url))?;
+        }
+        line("END:VEVENT".to_string())?;
+    }
+    line("END:VCALENDAR".to_string())
+}
+
+/// Replace `path` with `contents` in one step, so a calendar app reading it
+/// never sees a half-written file
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_os_string();
+    temp.push(".tmp");
+    fs::write(&temp, contents)?;
+    fs::rename(&temp, path)
+}
+
+/// A UTC date-time in the basic format, e.g. `20240301T235900Z`
+fn format_time(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a TEXT value (RFC 5545 §3.3.11)
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write a content line, folded so no line exceeds 75 octets, with CRLF endings
+fn write_line(out: &mut impl Write, content: &str) -> io::Result<()> {
+    let mut rest = content;
+    // Continuation lines start with a space, which counts towards their length
+    let mut limit = MAX_LINE;
+    while rest.len() > limit {
+        let mut split = limit;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        write!(out, "{}\r\n ", &rest[..split])?;
+        rest = &rest[split..];
+        limit = MAX_LINE - 1;
+    }
+    write!(out, "{}\r\n", rest)
+}
@@ -1,4 +1,23 @@
 pub mod models;
+pub mod async_client;
+pub mod changes;
 pub mod client;
 pub mod cli;
+pub mod compose;
+pub mod config;
 pub mod db;
+pub mod due;
+pub mod error;
+pub mod files;
+pub mod ical;
+pub mod lock;
+pub mod migrations;
+pub mod offline;
+pub mod output;
+pub mod render;
+pub mod resolve;
+pub mod retry;
+pub mod search;
+pub mod submissions;
+pub mod sync;
+pub mod upload;
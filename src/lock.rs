@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Exclusive hold on a local database for work that must not overlap, such as
+/// syncing or migrating. The lock is released when this is dropped, including
+/// when the process dies.
+#[derive(Debug)]
+pub struct DatabaseLock {
+    _file: File,
+}
+
+impl DatabaseLock {
+    /// Take the lock for the database at `db_path`, failing at once if another
+    /// process holds it
+    pub fn acquire(db_path: &Path) -> Result<Self, LockError> {
+        let path = lock_path(db_path);
+        let io_error = |e| LockError::Io(path.clone(), e);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let file = OpenOptions::new().create(true).truncate(false).write(true).open(&path).map_err(io_error)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Self { _file: file }),
+            Err(TryLockError::WouldBlock) => Err(LockError::Busy(db_path.to_path_buf())),
+            Err(TryLockError::Error(e)) => Err(io_error(e)),
+        }
+    }
+}
+
+/// Where the lock for a database lives, e.g. `canvas.db.lock`
+pub fn lock_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    db_path.with_file_name(name)
+}
+
+#[derive(Debug)]
+pub enum LockError {
+    /// Another canvas-cli is already working on this database
+    Busy(PathBuf),
+    Io(PathBuf, io::Error),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::Busy(path) => write!(
+                f,
+                "another canvas-cli is already syncing or upgrading {}; try again when it has finished",
+                path.display()
+            ),
+            LockError::Io(path, e) => write!(f, "could not lock {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl Error for LockError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LockError::Io(_, e) => Some(e),
+            LockError::Busy(_) => None,
+        }
+    }
+}
@@ -1,228 +1,913 @@
 mod models;
+mod async_client;
+mod changes;
 mod client;
 mod cli;
+mod compose;
+mod config;
 mod db;
+mod due;
+mod error;
+mod files;
+mod ical;
+mod lock;
+mod migrations;
+mod offline;
+mod output;
+mod render;
+mod resolve;
+mod retry;
+mod search;
+mod submissions;
+mod sync;
+mod upload;
 
+use chrono::{Local, Utc};
 use clap::Parser;
 use std::error::Error;
-use dotenv::dotenv;
-use std::env;
+use std::io::{self, Write};
 use std::path::Path;
-use std::path::PathBuf;
 
-use cli::{Cli, Commands};
+use cli::{AliasArgs, AliasCommand, Cli, Commands, ConfigCommand, DbCommand, ExportCommand, IcalArgs, ProfileArgs, ProfileCommand, SearchArgs, WhatsnewArgs};
 use client::CanvasClient;
-use db::Database;
+use config::{Config, ConfigFile, Flags, Profile};
+use db::{CacheScope, Database, SyncRun};
+use files::CourseFile;
+use ical::Event;
+use due::DueItem;
+use lock::DatabaseLock;
+use models::{Assignment, Course, Page, Submission, SubmissionType};
+use offline::{DataSource, Origin};
+use output::{Format, Output};
+use render::{FileRef, RenderOptions};
+use resolve::CourseResolver;
+use sync::Syncer;
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() {
     let cli = Cli::parse();
 
-    dotenv().ok();
-    let base_url = env::var("BASE_URL")?;
-    let access_token = env::var("ACCESS_KEY")?;
-
-    if (base_url.is_empty() || access_token.is_empty()) && (cli.base_url.is_none() || cli.access_token.is_none()) {
-        eprintln!("Error: BASE_URL and ACCESS_KEY must be set either in .env file or as command line arguments.");
+    if let Err(e) = run(cli) {
+        eprintln!("Error: {}", e);
         std::process::exit(1);
     }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let output = Output::new(cli.format);
+
+    // Profile management edits the config file directly, so it works even if the active profile is broken
+    if let Commands::Profile(args) = &cli.command {
+        return manage_profiles(args, &output);
+    }
+
+    let config = Config::load(Flags {
+        base_url: cli.base_url.clone(),
+        access_token: cli.access_token.clone(),
+        profile: cli.profile.clone(),
+        database: cli.database.clone(),
+    })?;
+
+    // Configuration commands work without credentials
+    if let Commands::Config(args) = &cli.command {
+        match args.command {
+            ConfigCommand::Show => show_config(&config, &output)?,
+        }
+        return Ok(());
+    }
 
-    let canvas = CanvasClient::new(&base_url, &access_token);
+    if let Commands::Db(args) = &cli.command {
+        return match args.command {
+            DbCommand::Migrate => migrate_database(&config, &output),
+            DbCommand::Status => database_status(&config, &output),
+        };
+    }
 
+    if let Commands::Alias(args) = &cli.command {
+        return manage_aliases(args, &config, &output);
+    }
+
+    // The change log and search index live in the local database
+    if let Commands::Whatsnew(args) = &cli.command {
+        return whats_new(args, &config, &output);
+    }
+    if let Commands::Search(args) = &cli.command {
+        return search(args, &config, &output, cli.include_deleted);
+    }
+
+    let canvas = if cli.offline { None } else { Some(connect(&config)?) };
+    let cache = open_cache(&config)?;
+    let courses = CourseResolver::new(&config.aliases, cache.as_ref(), canvas.as_ref());
+    let data = DataSource::new(canvas.as_ref(), cache.as_ref());
+    let deleted = cli.include_deleted;
+
+    // Listings that can be served from the local database
     match &cli.command {
         Commands::Courses => {
-            let courses = canvas.get_courses()?;
-            println!("Courses:");
-            for course in courses {
-                println!("  {} : {} : {}",
-                    course.id, 
-                    course.name.as_deref().unwrap_or("No name"),
-                    course.course_code.as_deref().unwrap_or("No course code"),
-                );
+            let (courses, origin) = data.load(CacheScope::Courses, |c| c.get_courses(), |db| db.get_courses(deleted))?;
+            show_origin(origin);
+            output.list(&courses, &with_deleted(&["id", "name", "course_code"], deleted))?;
+            return Ok(());
+        }
+        Commands::Assignments(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let (assignments, origin) = data.load(
+                CacheScope::Assignments(course_id),
+                |c| c.get_assignments(course_id),
+                |db| db.get_assignments(course_id, deleted),
+            )?;
+            show_origin(origin);
+            output.list(&assignments, &with_deleted(&["id", "name", "due_at", "points_possible"], deleted))?;
+            return Ok(());
+        }
+        Commands::Modules(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let (modules, origin) = data.load(
+                CacheScope::Modules(course_id),
+                |c| c.get_modules(course_id),
+                |db| db.get_modules(course_id, deleted),
+            )?;
+            show_origin(origin);
+            output.list(&modules, &with_deleted(&["id", "name", "state", "items_count", "completed_at"], deleted))?;
+            return Ok(());
+        }
+        Commands::ModuleItems(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let (items, origin) = data.load(
+                CacheScope::ModuleItems(args.module_id),
+                |c| c.get_module_items(course_id, args.module_id),
+                |db| db.get_module_items(args.module_id, deleted),
+            )?;
+            show_origin(origin);
+            output.list(&items, &with_deleted(&["id", "title", "type"], deleted))?;
+            return Ok(());
+        }
+        Commands::Discussions(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let (discussions, origin) = data.load(
+                CacheScope::Discussions(course_id),
+                |c| c.get_discussions(course_id),
+                |db| db.get_discussions(course_id, deleted),
+            )?;
+            show_origin(origin);
+            // Reply counts aren't cached
+            let columns: &[&str] = if origin.is_cached() {
+                &["id", "title", "posted_at"]
+            } else {
+                &["id", "title", "discussion_subentry_count", "posted_at"]
+            };
+            output.list(&discussions, &with_deleted(columns, deleted))?;
+            return Ok(());
+        }
+        Commands::Pages(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let (pages, origin) = data.load(
+                CacheScope::Pages(course_id),
+                |c| c.get_pages(course_id),
+                |db| db.get_pages(course_id, deleted),
+            )?;
+            show_origin(origin);
+            output.list(&pages, &with_deleted(&["page_id", "url", "title", "updated_at"], deleted))?;
+            return Ok(());
+        }
+        Commands::Page(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let (page, origin) = data.load(
+                CacheScope::Pages(course_id),
+                |c| c.get_page(course_id, &args.page).map(Some),
+                |db| db.get_page(course_id, client::page_key(&args.page), deleted),
+            )?;
+            show_origin(origin);
+            let page = page.ok_or_else(|| format!("no page '{}' has been synced for this course", args.page))?;
+            show_page(&page, &args.course, &config, &output)?;
+            return Ok(());
+        }
+        Commands::Due(args) => {
+            let only = args.course.as_deref().map(|c| courses.resolve(c)).transpose()?;
+            let (assignments, origin) = active_assignments(&data, only)?;
+            show_origin(origin);
+            show_due(&due::due_items(&assignments, &Local::now(), args.days.into()), args.days, &output)?;
+            return Ok(());
+        }
+        Commands::Export(args) => {
+            match &args.command {
+                ExportCommand::Ical(args) => {
+                    let only = args.course.as_deref().map(|c| courses.resolve(c)).transpose()?;
+                    export_ical(args, &data, only, &config, &output)?;
+                }
             }
+            return Ok(());
         }
+        _ if cli.offline => return Err("this command needs a connection to Canvas; run it without --offline".into()),
+        _ => {}
+    }
+    let canvas = canvas.as_ref().expect("connected unless offline");
+
+    match &cli.command {
         Commands::Course(args) => {
-            let course = canvas.get_course(args.course_id)?;
-            println!("Course details for {}:", args.course_id);
-            println!("  Name: {}", course.name.as_deref().unwrap_or("No name"));
-            println!("  Code: {}", course.course_code.as_deref().unwrap_or("No code"));
+            let course_id = courses.resolve(&args.course)?;
+            let course = canvas.get_course(course_id)?;
+            output.item(&course, &["id", "name", "course_code", "start_at", "end_at"])?;
         }
-        Commands::Assignments(args) => {
-            let assignments = canvas.get_assignments(args.course_id)?;
-            println!("Assignments for course {}:", args.course_id);
-            for assignment in assignments {
-                println!("  {}: {}", assignment.id, assignment.name);
+        Commands::Assignment(args) => {
+            let submission_type = args.requested_submission_type()?;
+            let course_id = courses.resolve(&args.course)?;
+            let assignment = canvas.get_assignment(course_id, args.assignment_id)?;
+            output.item(&assignment, &["id", "name", "due_at", "points_possible", "submission_types", "allowed_extensions", "html_url"])?;
+            if output.format == Format::Table {
+                if let Some(description) = assignment.description.as_deref() {
+                    output.status("");
+                    let files = show_html(description, &render_options(&config), &output);
+                    show_linked_files(&args.course, &files, &output);
+                }
+            }
+
+            if let Some(submission_type) = submission_type {
+                let submission = match submission_type {
+                    SubmissionType::Upload => {
+                        for path in &args.file_paths {
+                            output.status(format!("Submitting file: {}", path));
+                        }
+                        canvas.submit_assignment(course_id, &assignment, &args.file_paths)?
+                    }
+                    SubmissionType::TextEntry => {
+                        // Check before prompting for text so an editor isn't opened for nothing
+                        assignment.check_accepts(submission_type)?;
+                        let body = args.text_source().read_html()?;
+                        output.status("Submitting text entry");
+                        canvas.submit_text_entry(course_id, &assignment, &body)?
+                    }
+                    SubmissionType::Url => {
+                        let url = args.url.as_deref().unwrap_or_default();
+                        output.status(format!("Submitting URL: {}", url));
+                        canvas.submit_url(course_id, &assignment, url)?
+                    }
+                };
+                output.status("Submission successful!");
+                if output.format != Format::Table {
+                    output.item(&submission, &[])?;
+                }
             }
         }
-        Commands::Assignment(args) => {
-            let assignment = canvas.get_assignment(args.course_id, args.assignment_id)?;
-            println!("Assignment details:");
-            println!("  Name: {}", assignment.name);
-            println!("  Due: {}", assignment.due_at.map_or("No due date".to_string(), |d| d.to_string()));
-            
-            if let Some(path) = &args.file_path {
-                println!("Submitting file: {}", path);
-                canvas.submit_assignment(args.course_id, args.assignment_id, Path::new(path))?;
-                println!("Submission successful!");
+        Commands::Submission(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let assignment = canvas.get_assignment(course_id, args.assignment_id)?;
+            let submission = canvas.get_submission(course_id, args.assignment_id)?;
+            show_submission(&assignment, &submission, &args.course, &output)?;
+        }
+        Commands::Comment(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            // Check before prompting for text so an editor isn't opened for nothing
+            if let Some(path) = args.attachments.iter().find(|path| !Path::new(path).is_file()) {
+                return Err(format!("cannot attach {}: no such file", path).into());
+            }
+            let text = args.text_source().read()?;
+            if text.trim().is_empty() && args.attachments.is_empty() {
+                return Err("comment is empty, nothing to post".into());
+            }
+            for path in &args.attachments {
+                output.status(format!("Attaching file: {}", path));
+            }
+            let submission = canvas.comment_on_submission(course_id, args.assignment_id, text.trim(), &args.attachments)?;
+            output.status("Posted comment successfully!");
+            match submission.submission_comments.last() {
+                Some(comment) => output.item(comment, &["id", "created_at"])?,
+                None if output.format != Format::Table => output.item(&submission, &[])?,
+                None => {}
             }
         }
         Commands::Announcements(args) => {
-            let announcements = canvas.get_announcements(args.course_id)?;
-            println!("Announcements for course {}:", args.course_id);
-            for announcement in announcements {
-                println!("  {}: {}", announcement.id, announcement.title);
+            let course_id = courses.resolve(&args.course)?;
+            let announcements = canvas.get_announcements(course_id)?;
+            output.list(&announcements, &["id", "title", "posted_at"])?;
+        }
+        Commands::Announcement(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let announcement = canvas.get_announcement(course_id, args.announcement_id)?;
+            if output.format != Format::Table {
+                output.item(&announcement, &[])?;
+                return Ok(());
             }
+            output.status(&announcement.title);
+            output.status(format!("{}\n", announcement.posted_at.with_timezone(&Local).format("%a %b %-d %Y %H:%M")));
+            let files = show_html(&announcement.message, &render_options(&config), &output);
+            show_linked_files(&args.course, &files, &output);
+        }
+        Commands::Folders(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let mut folders = canvas.get_folders(course_id)?;
+            folders.sort_by(|a, b| a.full_name.cmp(&b.full_name));
+            output.list(&folders, &["id", "full_name", "files_count", "folders_count"])?;
+        }
+        Commands::Files(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let files = course_files(canvas, course_id, args.folder.as_deref())?;
+            output.list(&files, &["id", "path", "size", "updated_at"])?;
+        }
+        Commands::Download(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let mut files = match &args.folder {
+                Some(folder) => course_files(canvas, course_id, Some(folder))?,
+                None => Vec::new(),
+            };
+            for file_id in &args.file_ids {
+                files.push(CourseFile::named(canvas.get_file(*file_id)?));
+            }
+            download_files(canvas, &files, Path::new(&args.output), &output)?;
+        }
+        Commands::Mirror(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let files = course_files(canvas, course_id, None)?;
+            let dir = Path::new(&args.dir);
+            if args.prune && dir.exists() {
+                for path in files::prune(&files, dir)? {
+                    output.status(format!("Removed {}", path));
+                }
+            }
+            download_files(canvas, &files, dir, &output)?;
         }
         Commands::Grades(args) => {
-            let grades = canvas.get_grades(args.course_id)?;
-            println!("Grades for course {}:", args.course_id);
-            println!("  Current grade: {}", grades.current_grade.as_deref().unwrap_or("No grade"));
-            println!("  Final grade: {}", grades.final_grade.as_deref().unwrap_or("No grade"));
+            let course_id = courses.resolve(&args.course)?;
+            let grades = canvas.get_grades(course_id)?;
+            output.item(&grades, &["course_id", "current_grade", "current_score", "final_grade", "final_score"])?;
         }
         Commands::Todos => {
             let todos = canvas.get_todos()?;
-            println!("Todo items:");
-            for todo in todos {
-                let title = todo.title.as_deref().unwrap_or("Untitled");
-                let due = todo.due_at.map_or("No due date".to_string(), |d| d.to_string());
-                println!("  {} (Due: {})", title, due);
+            output.list(&todos, &["title", "due_at", "course_id"])?;
+        }
+        Commands::Discussion(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let topic = canvas.get_discussion(course_id, args.topic_id)?;
+            let entries = canvas.get_discussion_entries(course_id, args.topic_id)?;
+            if output.format != Format::Table {
+                output.item(&serde_json::json!({ "topic": topic, "entries": entries }), &[])?;
+                return Ok(());
+            }
+            let options = render_options(&config);
+            output.status(&topic.title);
+            output.status(format!("{}\n", topic.html_url));
+            let mut files = show_html(&topic.message, &options, &output);
+            for entry in &entries {
+                output.status(format!(
+                    "\n--- Entry {} by user {}, {}\n",
+                    entry.id, entry.user_id, entry.created_at.with_timezone(&Local).format("%a %b %-d %Y %H:%M")
+                ));
+                files.extend(show_html(&entry.message, &options, &output));
             }
+            show_linked_files(&args.course, &files, &output);
         }
-        Commands::Modules(args) => {
-            let modules = canvas.get_modules(args.course_id)?;
-            println!("Modules for course {}:", args.course_id);
-            for module in modules {
-                println!("  {}: {} ({})", 
-                    module.id, 
-                    module.name,
-                    if module.completed_at.is_some() { "completed" } else { "in progress" }
-                );
+        Commands::DiscussionEntries(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let mut entries = canvas.get_discussion_entries(course_id, args.topic_id)?;
+            if output.format == Format::Table {
+                for entry in &mut entries {
+                    entry.message = render::plain_text(&entry.message);
+                }
             }
+            output.list(&entries, &["id", "user_id", "created_at", "message"])?;
         }
-        Commands::ModuleItems(args) => {
-            let items = canvas.get_module_items(args.course_id, args.module_id)?;
-            println!("Items for module {}:", args.module_id);
-            for item in items {
-                println!("  {}: {} [{}]", 
-                    item.id, 
-                    item.title,
-                    item.item_type
-                );
+        Commands::PostDiscussion(args) => {
+            let course_id = courses.resolve(&args.course)?;
+            let entry = canvas.post_discussion_entry(course_id, args.topic_id, &args.message)?;
+            output.status("Posted comment successfully!");
+            output.item(&entry, &["id", "created_at"])?;
+        }
+        Commands::Sync(args) => {
+            output.status("Syncing data with local database...");
+            let path = config.database_path();
+            let _lock = DatabaseLock::acquire(&path)?;
+            let db = open_database(&path, true)?;
+            let report = Syncer::new(canvas, &db)
+                .full(args.full)
+                .jobs(args.jobs)
+                .run(|line| output.status(line))?;
+
+            for error in &report.errors {
+                eprintln!("Error: {}", error);
+            }
+            let counts: Vec<_> = report.counts.iter()
+                .map(|(table, counts)| serde_json::json!({
+                    "table": table,
+                    "new": counts.inserted,
+                    "updated": counts.updated,
+                    "unchanged": counts.unchanged,
+                    "deleted": counts.deleted,
+                }))
+                .collect();
+            output.status(format!("\nSynced {} courses:", report.courses));
+            output.list(&counts, &["table", "new", "updated", "unchanged", "deleted"])?;
+            if report.changes > 0 {
+                output.status(format!("Recorded {} changes; run `canvas-cli whatsnew` to see them", report.changes));
             }
+            output.status("Sync complete!");
         }
-        Commands::Discussions(args) => {
-            let discussions = canvas.get_discussions(args.course_id)?;
-            println!("Discussions for course {}:", args.course_id);
-            for discussion in discussions {
-                println!("  {}: {} ({} replies)", 
-                    discussion.id, 
-                    discussion.title,
-                    discussion.discussion_subentry_count
-                );
+        Commands::Courses | Commands::Assignments(_) | Commands::Modules(_)
+        | Commands::ModuleItems(_) | Commands::Discussions(_) | Commands::Pages(_) | Commands::Page(_)
+        | Commands::Due(_) | Commands::Export(_) => unreachable!("listed above"),
+        Commands::Config(_) | Commands::Profile(_) | Commands::Alias(_) | Commands::Db(_) | Commands::Whatsnew(_)
+        | Commands::Search(_) => {
+            unreachable!("handled before connecting")
+        }
+    }
+
+    Ok(())
+}
+
+/// Tell the user when a listing comes from the cache rather than Canvas
+fn show_origin(origin: Origin) {
+    if let Some(notice) = origin.notice(Utc::now()) {
+        eprintln!("{}", notice);
+    }
+}
+
+/// Courses, each with its assignments
+type CourseAssignments = Vec<(Course, Vec<Assignment>)>;
+
+/// Assignments of every active course, or only of `only`, with the user's submissions when online
+fn active_assignments(data: &DataSource, only: Option<u64>) -> Result<(CourseAssignments, Origin), Box<dyn Error>> {
+    let (active, mut origin) = data.load(CacheScope::Courses, |c| c.get_active_courses(), |db| db.get_courses(false))?;
+    let mut fetched = Vec::new();
+    for course in active.into_iter().filter(|c| only.is_none_or(|id| c.id == id)) {
+        let (assignments, loaded) = data.load(
+            CacheScope::Assignments(course.id),
+            |c| c.get_assignments_with_submission(course.id),
+            |db| db.get_assignments(course.id, false),
+        )?;
+        origin = origin.combine(loaded);
+        fetched.push((course, assignments));
+    }
+    if let (Some(id), true) = (only, fetched.is_empty()) {
+        return Err(format!("course {} is not one of your active courses", id).into());
+    }
+    Ok((fetched, origin))
+}
+
+/// Write the iCalendar export once, or over and over with `--every`
+fn export_ical(args: &IcalArgs, data: &DataSource, only: Option<u64>, config: &Config, output: &Output) -> Result<(), Box<dyn Error>> {
+    let every = args.every.as_deref().map(changes::parse_age).transpose()?;
+    if let Some(every) = every.filter(|every| *every < chrono::Duration::minutes(1)) {
+        return Err(format!("--every must be at least a minute, not {}", offline::format_age(every)).into());
+    }
+    // UIDs include the Canvas host so calendars from different instances never collide
+    let domain = config.base_url.as_ref()
+        .and_then(|url| reqwest::Url::parse(&url.value).ok())
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "canvas-cli".to_string());
+
+    loop {
+        let written = active_assignments(data, only).and_then(|(assignments, origin)| {
+            show_origin(origin);
+            let events: Vec<Event> = assignments.iter()
+                .flat_map(|(course, assignments)| assignments.iter().filter_map(|a| Event::for_assignment(&domain, course, a)))
+                .collect();
+            let mut calendar = Vec::new();
+            ical::write_calendar(&mut calendar, "Canvas", &events, Utc::now())?;
+            match &args.output {
+                Some(path) => ical::write_atomically(Path::new(path), &calendar)?,
+                None => io::stdout().write_all(&calendar)?,
             }
+            Ok(events.len())
+        });
+
+        let Some(every) = every else {
+            let count = written?;
+            if let Some(path) = &args.output {
+                output.status(format!("Wrote {} events to {}", count, path));
+            }
+            return Ok(());
+        };
+        // Keep going through failures; the file keeps its last good contents
+        match written {
+            Ok(count) => output.status(format!(
+                "{} Wrote {} events to {}",
+                Local::now().format("%H:%M"), count, args.output.as_deref().unwrap_or_default()
+            )),
+            Err(e) => eprintln!("Error: {}", e),
         }
-        Commands::DiscussionEntries(args) => {
-            let entries = canvas.get_discussion_entries(args.course_id, args.topic_id)?;
-            println!("Entries for discussion {}:", args.topic_id);
-            for entry in entries {
-                println!("  {}: {} (posted: {})", 
-                    entry.id,
-                    entry.message,
-                    entry.created_at
-                );
+        std::thread::sleep(every.to_std().expect("checked to be positive"));
+    }
+}
+
+/// Files of a course, or of one folder and its subfolders, with paths relative to it
+fn course_files(canvas: &CanvasClient, course_id: u64, folder: Option<&str>) -> Result<Vec<CourseFile>, Box<dyn Error>> {
+    let folders = canvas.get_folders(course_id)?;
+    let within = match folder {
+        Some(folder) => Some(files::find_folder(&folders, folder).ok_or_else(|| format!("no folder '{}' in this course", folder))?),
+        None => None,
+    };
+    Ok(files::files_in(&folders, canvas.get_course_files(course_id)?, within))
+}
+
+/// Download files below `dir`, skipping current ones, and report what happened
+fn download_files(canvas: &CanvasClient, files: &[CourseFile], dir: &Path, output: &Output) -> Result<(), Box<dyn Error>> {
+    let report = files::mirror(canvas, files, dir, |path| output.status(format!("Downloading {}", path)));
+    for error in &report.errors {
+        eprintln!("Error: {}", error);
+    }
+    output.status(format!(
+        "Downloaded {} files to {}, {} already up to date",
+        report.downloaded, dir.display(), report.skipped
+    ));
+    if !report.errors.is_empty() {
+        return Err(format!("{} files could not be downloaded", report.errors.len()).into());
+    }
+    Ok(())
+}
+
+/// Print a wiki page; tables get its title and body rendered as text, other formats the raw HTML
+fn show_page(page: &Page, course: &str, config: &Config, output: &Output) -> Result<(), Box<dyn Error>> {
+    if output.format != Format::Table {
+        output.item(page, &[])?;
+        return Ok(());
+    }
+    output.status(&page.title);
+    output.status(format!("{}\n", page.html_url));
+    let files = show_html(page.body.as_deref().unwrap_or_default(), &render_options(config), output);
+    show_linked_files(course, &files, output);
+    Ok(())
+}
+
+/// Print a submission; tables get its attempts, rubric assessment and comments in turn
+fn show_submission(assignment: &Assignment, submission: &Submission, course: &str, output: &Output) -> Result<(), Box<dyn Error>> {
+    if output.format != Format::Table {
+        output.item(submission, &[])?;
+        return Ok(());
+    }
+    match assignment.points_possible {
+        Some(points) => output.status(format!("{} ({} points)", assignment.name, points)),
+        None => output.status(&assignment.name),
+    }
+    output.item(submission, &[
+        "workflow_state", "attempt", "submission_type", "submitted_at", "late", "missing", "excused", "score", "grade", "graded_at",
+    ])?;
+    if let Some(seconds) = submission.seconds_late.filter(|s| *s > 0 && submission.late) {
+        output.status(format!("Submitted {} late", offline::format_age(chrono::Duration::seconds(seconds))));
+    }
+
+    let attempts = submissions::attempts(submission);
+    if !attempts.is_empty() {
+        output.status("\nAttempts");
+        output.list(&attempts, &["attempt", "submitted_at", "submission_type", "score", "grade", "late"])?;
+    }
+
+    let rubric = submissions::rubric_rows(assignment.rubric.as_deref().unwrap_or_default(), submission);
+    if !rubric.is_empty() {
+        output.status("\nRubric");
+        output.list(&rubric, &["criterion", "points", "points_possible", "rating", "comments"])?;
+    }
+
+    if submission.submission_comments.is_empty() {
+        output.status("\nNo comments");
+        return Ok(());
+    }
+    output.status("\nComments");
+    let mut files = Vec::new();
+    for comment in &submission.submission_comments {
+        let attempt = comment.attempt.map(|a| format!(" on attempt {}", a)).unwrap_or_default();
+        output.status(format!(
+            "\n{}, {}{}:",
+            comment.author_name, comment.created_at.with_timezone(&Local).format("%a %b %-d %Y %H:%M"), attempt
+        ));
+        output.status(&comment.comment);
+        for attachment in &comment.attachments {
+            output.status(format!("Attached: {} (file {})", attachment.display_name, attachment.id));
+            files.push(FileRef { id: attachment.id, name: attachment.display_name.clone() });
+        }
+    }
+    show_linked_files(course, &files, output);
+    Ok(())
+}
+
+/// Render Canvas HTML for the terminal: wrapped to its width, with links
+/// relative to Canvas made absolute
+fn render_options(config: &Config) -> RenderOptions {
+    RenderOptions {
+        width: render::terminal_width(),
+        base_url: config.base_url.as_ref().map(|url| url.value.clone()),
+    }
+}
+
+/// Print Canvas HTML as text, returning the Canvas files it links to
+fn show_html(html: &str, options: &RenderOptions, output: &Output) -> Vec<FileRef> {
+    let rendered = render::render(html, options);
+    if rendered.text.is_empty() {
+        output.status("(empty)");
+    } else {
+        output.status(rendered.text);
+    }
+    rendered.files
+}
+
+/// Tell the user how to download the files linked from what was just shown
+fn show_linked_files(course: &str, files: &[FileRef], output: &Output) {
+    let mut ids: Vec<String> = Vec::new();
+    for file in files {
+        let id = file.id.to_string();
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if ids.is_empty() {
+        return;
+    }
+    let course = if course.contains(char::is_whitespace) { format!("\"{}\"", course) } else { course.to_string() };
+    output.status(format!("\nDownload linked files with: canvas-cli download {} {}", course, ids.join(" ")));
+}
+
+/// Print the `due` dashboard; tables get a heading per group
+fn show_due(items: &[DueItem], days: u32, output: &Output) -> Result<(), Box<dyn Error>> {
+    if items.is_empty() {
+        output.status(format!("Nothing due in the next {} days", days));
+        return Ok(());
+    }
+    let columns = ["course_code", "name", "due", "points_possible", "status"];
+    if output.format != Format::Table {
+        output.list(items, &columns)?;
+        return Ok(());
+    }
+    for (i, group) in items.chunk_by(|a, b| a.bucket == b.bucket).enumerate() {
+        if i > 0 {
+            output.status("");
+        }
+        output.status(group[0].bucket.heading());
+        output.list(group, &columns)?;
+    }
+    Ok(())
+}
+
+/// Add the tombstone column when `--include-deleted` is given
+fn with_deleted<'a>(columns: &[&'a str], include_deleted: bool) -> Vec<&'a str> {
+    let mut columns = columns.to_vec();
+    if include_deleted {
+        columns.push("deleted_at");
+    }
+    columns
+}
+
+fn connect(config: &Config) -> Result<CanvasClient, Box<dyn Error>> {
+    let (base_url, access_token) = config.credentials()?;
+    Ok(CanvasClient::new(base_url, access_token).with_per_page(100))
+}
+
+/// The local database, if a sync has created one
+fn open_cache(config: &Config) -> Result<Option<Database>, Box<dyn Error>> {
+    let path = config.database_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(open_database(&path, false)?))
+}
+
+/// Open a database, creating its directory if needed, and bring its schema up
+/// to date, mentioning any backup taken. Upgrading takes the database lock
+/// unless the caller (`locked`) already holds it.
+fn open_database(path: &Path, locked: bool) -> Result<Database, Box<dyn Error>> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+    }
+    let db = Database::open_unmigrated(path)?;
+    if db.pending_migrations()?.is_empty() {
+        return Ok(db);
+    }
+    let _lock = if locked { None } else { Some(DatabaseLock::acquire(path)?) };
+    let report = db.migrate()?;
+    if let Some(backup) = &report.backup {
+        eprintln!("Upgraded {} to schema version {} (backup at {})", path.display(), report.to, backup.display());
+    }
+    Ok(db)
+}
+
+fn migrate_database(config: &Config, output: &Output) -> Result<(), Box<dyn Error>> {
+    let path = config.database_path();
+    let _lock = DatabaseLock::acquire(&path)?;
+    let db = Database::open_unmigrated(&path)?;
+    let report = db.migrate()?;
+    if report.from == report.to {
+        output.status(format!("{} is up to date (schema version {})", path.display(), report.to));
+        return Ok(());
+    }
+    output.status(format!("Migrated {} from schema version {} to {}", path.display(), report.from, report.to));
+    if let Some(backup) = report.backup {
+        output.status(format!("Backup of the previous database: {}", backup.display()));
+    }
+    Ok(())
+}
+
+fn database_status(config: &Config, output: &Output) -> Result<(), Box<dyn Error>> {
+    let path = config.database_path();
+    if !path.exists() {
+        output.status(format!("No database at {} yet; run `canvas-cli sync`", path.display()));
+        return Ok(());
+    }
+    let db = Database::open_unmigrated(&path)?;
+    let version = db.schema_version()?;
+    let pending = db.pending_migrations()?;
+    output.status(format!(
+        "{}: schema version {} of {}, {} pending migration{}",
+        path.display(),
+        version,
+        migrations::latest_version(),
+        pending.len(),
+        if pending.len() == 1 { "" } else { "s" }
+    ));
+    if pending.is_empty() {
+        if let Some(run) = db.sync_runs(1)?.pop() {
+            output.status(describe_sync_run(&run));
+        }
+    }
+
+    let rows: Vec<_> = migrations::MIGRATIONS.iter()
+        .map(|m| serde_json::json!({
+            "version": m.version,
+            "description": m.description,
+            "applied": m.version <= version,
+        }))
+        .collect();
+    output.list(&rows, &["version", "description", "applied"])?;
+    Ok(())
+}
+
+fn describe_sync_run(run: &SyncRun) -> String {
+    let started = run.started_at.format("%Y-%m-%d %H:%M UTC");
+    let Some(finished_at) = run.finished_at else {
+        return format!("Last sync started {} and did not finish", started);
+    };
+    format!(
+        "Last sync {}: {} courses in {}s, {} error{}",
+        started,
+        run.courses.unwrap_or(0),
+        (finished_at - run.started_at).num_seconds(),
+        run.errors.len(),
+        if run.errors.len() == 1 { "" } else { "s" }
+    )
+}
+
+fn manage_aliases(args: &AliasArgs, config: &Config, output: &Output) -> Result<(), Box<dyn Error>> {
+    let path = config.config_path.clone().ok_or("cannot locate the config directory; set XDG_CONFIG_HOME or HOME")?;
+    let mut file = ConfigFile::load(&path)?;
+    let profile = &config.profile.value;
+
+    match &args.command {
+        AliasCommand::List => {
+            let aliases: Vec<_> = config.aliases.iter()
+                .map(|(name, course_id)| serde_json::json!({ "name": name, "course_id": course_id }))
+                .collect();
+            output.list(&aliases, &["name", "course_id"])?;
+        }
+        AliasCommand::Add(add) => {
+            config::validate_alias(&add.name)?;
+            let canvas = connect(config)?;
+            let cache = open_cache(config)?;
+            // Resolve without the alias being redefined, so an alias can't point at itself
+            let mut aliases = config.aliases.clone();
+            aliases.remove(&add.name);
+            let course_id = CourseResolver::new(&aliases, cache.as_ref(), Some(&canvas)).resolve(&add.course)?;
+            file.aliases_mut(profile)?.insert(add.name.clone(), course_id);
+            file.save(&path)?;
+            output.status(format!("Alias '{}' now refers to course {}", add.name, course_id));
+        }
+        AliasCommand::Remove(remove) => {
+            if file.aliases_mut(profile)?.remove(&remove.name).is_none() {
+                return Err(format!("no alias named '{}' in profile '{}'", remove.name, profile).into());
             }
+            file.save(&path)?;
+            output.status(format!("Removed alias '{}'", remove.name));
         }
-        Commands::PostDiscussion(args) => {
-            let entry = canvas.post_discussion_entry(args.course_id, args.topic_id, &args.message)?;
-            println!("Posted comment successfully!");
-            println!("Entry ID: {}", entry.id);
-        }
-        Commands::Sync => {
-            println!("Syncing data with local database...");
-            let db_path = PathBuf::from("canvas.db");
-            let db = Database::new(&db_path)?;
-
-            // Get courses that haven't been synced in the last 6 hours
-            let stale_courses = db.get_stale_courses(6)?;
-            let courses = if stale_courses.is_empty() {
-                canvas.get_courses()?
-            } else {
-                println!("Syncing {} stale courses...", stale_courses.len());
-                stale_courses.iter()
-                    .filter_map(|&id| canvas.get_course(id).ok())
-                    .collect()
-            };
+    }
 
-            for course in &courses {
-                println!("Syncing course: {}", course.name.as_deref().unwrap_or("Unnamed"));
-                if let Err(e) = db.sync_course(course) {
-                    eprintln!("Error syncing course {}: {}", course.id, e);
-                    continue;
-                }
-                
-                // Sync assignments with error handling
-                match canvas.get_assignments(course.id) {
-                    Ok(assignments) => {
-                        for assignment in &assignments {
-                            if let Err(e) = db.sync_assignment(course.id, assignment) {
-                                eprintln!("Error syncing assignment {}: {}", assignment.id, e);
-                            }
-                        }
-                    }
-                    Err(e) => eprintln!("Error fetching assignments for course {}: {}", course.id, e),
-                }
+    Ok(())
+}
 
-                // Sync discussions with error handling
-                match canvas.get_discussions(course.id) {
-                    Ok(discussions) => {
-                        for discussion in &discussions {
-                            if let Err(e) = db.sync_discussion(course.id, discussion) {
-                                eprintln!("Error syncing discussion {}: {}", discussion.id, e);
-                            }
-                        }
-                    }
-                    Err(e) => eprintln!("Error fetching discussions for course {}: {}", course.id, e),
-                }
+fn whats_new(args: &WhatsnewArgs, config: &Config, output: &Output) -> Result<(), Box<dyn Error>> {
+    let db = open_cache(config)?.ok_or("no local database yet; run `canvas-cli sync` first")?;
+    let since = match &args.since {
+        Some(since) => Some(changes::parse_since(since, Utc::now())?),
+        None => db.cursor("whatsnew", 0)?,
+    };
+    let course_id = match &args.course {
+        Some(course) => Some(CourseResolver::new(&config.aliases, Some(&db), None).resolve(course)?),
+        None => None,
+    };
 
-                // Sync modules with error handling
-                match canvas.get_modules(course.id) {
-                    Ok(modules) => {
-                        for module in &modules {
-                            if let Err(e) = db.sync_module(course.id, module) {
-                                eprintln!("Error syncing module {}: {}", module.id, e);
-                                continue;
-                            }
-                            
-                            match canvas.get_module_items(course.id, module.id) {
-                                Ok(items) => {
-                                    for item in &items {
-                                        if let Err(e) = db.sync_module_item(module.id, item) {
-                                            eprintln!("Error syncing module item {}: {}", item.id, e);
-                                        }
-                                    }
-                                }
-                                Err(e) => eprintln!("Error fetching items for module {}: {}", module.id, e),
-                            }
-                        }
-                    }
-                    Err(e) => eprintln!("Error fetching modules for course {}: {}", course.id, e),
-                }
+    let changes = db.changes_since(since, course_id)?;
+    if changes.is_empty() {
+        output.status(match since {
+            Some(since) => format!("Nothing new since {}", since.format("%Y-%m-%d %H:%M UTC")),
+            None => "Nothing new yet; changes are recorded from the second sync on".to_string(),
+        });
+        return Ok(());
+    }
+    output.list(&changes, &["recorded_at", "course_code", "kind", "title", "detail"])?;
+
+    // Only a plain `whatsnew` counts as having looked at everything
+    if args.since.is_none() && args.course.is_none() {
+        if let Some(last) = changes.last() {
+            db.set_cursor("whatsnew", 0, last.recorded_at)?;
+        }
+    }
+    Ok(())
+}
+
+fn search(args: &SearchArgs, config: &Config, output: &Output, include_deleted: bool) -> Result<(), Box<dyn Error>> {
+    let db = open_cache(config)?.ok_or("no local database yet; run `canvas-cli sync` first")?;
+    let course_id = match &args.course {
+        Some(course) => Some(CourseResolver::new(&config.aliases, Some(&db), None).resolve(course)?),
+        None => None,
+    };
+
+    let query = args.query.join(" ");
+    let hits = db.search(&query, course_id, include_deleted, args.limit)?.ok_or("nothing to search for")?;
+    if hits.is_empty() {
+        output.status(format!("No synced content matches '{}'", query));
+        return Ok(());
+    }
+    output.list(&hits, &with_deleted(&["course_code", "kind", "title", "snippet"], include_deleted))?;
+    Ok(())
+}
+
+fn show_config(config: &Config, output: &Output) -> Result<(), Box<dyn Error>> {
+    let row = |name: &str, setting: &Option<config::Setting>, secret: bool| match setting {
+        Some(setting) => serde_json::json!({
+            "setting": name,
+            "value": if secret { config::redact(&setting.value) } else { setting.value.clone() },
+            "source": setting.source.to_string(),
+        }),
+        None => serde_json::json!({ "setting": name, "value": null, "source": null }),
+    };
+
+    let mut rows = vec![
+        row("profile", &Some(config.profile.clone()), false),
+        row("base_url", &config.base_url, false),
+        row("access_token", &config.access_token, true),
+        row("database", &Some(config.database.clone()), false),
+    ];
+    if let Some(path) = &config.config_path {
+        rows.push(serde_json::json!({
+            "setting": "config_file",
+            "value": path.display().to_string(),
+            "source": if path.exists() { "found" } else { "not found" },
+        }));
+    }
+    output.list(&rows, &["setting", "value", "source"])?;
+    Ok(())
+}
+
+fn manage_profiles(args: &ProfileArgs, output: &Output) -> Result<(), Box<dyn Error>> {
+    let path = config::config_path().ok_or("cannot locate the config directory; set XDG_CONFIG_HOME or HOME")?;
+    let mut file = ConfigFile::load(&path)?;
+    let default = file.default_profile.clone().unwrap_or_else(|| config::DEFAULT_PROFILE.to_string());
+
+    match &args.command {
+        ProfileCommand::List => {
+            let profiles: Vec<_> = file.profile_names().into_iter()
+                .map(|name| {
+                    let profile = file.profile(&name).unwrap_or_default();
+                    serde_json::json!({
+                        "name": name,
+                        "default": name == default,
+                        "base_url": profile.base_url,
+                    })
+                })
+                .collect();
+            if profiles.is_empty() {
+                output.status(format!("No profiles configured in {}", path.display()));
             }
-            
-            // Display sync status
-            match db.get_sync_status() {
-                Ok(status) => {
-                    println!("\nSync Status:");
-                    for (type_name, last_sync) in status {
-                        println!("  {}: Last synced at {}", type_name, last_sync);
-                    }
-                }
-                Err(e) => eprintln!("Error getting sync status: {}", e),
+            output.list(&profiles, &["name", "default", "base_url"])?;
+        }
+        ProfileCommand::Add(add) => {
+            config::validate_profile_name(&add.name)?;
+            let existing = file.profiles.contains_key(&add.name);
+            let profile = file.profiles.entry(add.name.clone()).or_insert_with(Profile::default);
+            if add.base_url.is_some() {
+                profile.base_url = add.base_url.clone();
+            }
+            if add.access_token.is_some() {
+                profile.access_token = add.access_token.clone();
+            }
+            if add.make_default {
+                file.default_profile = Some(add.name.clone());
             }
-            
-            println!("Sync complete!");
+            file.save(&path)?;
+            output.status(format!("{} profile '{}' in {}", if existing { "Updated" } else { "Added" }, add.name, path.display()));
+        }
+        ProfileCommand::Remove(remove) => {
+            if file.profiles.remove(&remove.name).is_none() {
+                return Err(config::ConfigError::UnknownProfile(remove.name.clone()).into());
+            }
+            if file.default_profile.as_deref() == Some(remove.name.as_str()) {
+                file.default_profile = None;
+            }
+            file.save(&path)?;
+            output.status(format!("Removed profile '{}'", remove.name));
+        }
+        ProfileCommand::Use(using) => {
+            if file.profile(&using.name).is_none() {
+                return Err(config::ConfigError::UnknownProfile(using.name.clone()).into());
+            }
+            file.default_profile = Some(using.name.clone());
+            file.save(&path)?;
+            output.status(format!("Now using profile '{}'", using.name));
         }
     }
 
     Ok(())
 }
-
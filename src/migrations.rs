@@ -0,0 +1,324 @@
+use rusqlite::{Connection, OptionalExtension};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// One step in the evolution of the local database schema
+#[derive(Debug)]
+pub struct Migration {
+    /// The `user_version` the database has after this step
+    pub version: u32,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// Every migration, in the order they apply.
+///
+/// Never edit a migration once released; add a new one instead. Versions must
+/// count up from 1 without gaps.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        // Databases created before versioning already have these tables
+        sql: "
+            CREATE TABLE IF NOT EXISTS courses (
+                id INTEGER PRIMARY KEY,
+                name TEXT,
+                course_code TEXT,
+                enrollment_term_id INTEGER,
+                total_students INTEGER,
+                start_at TEXT,
+                end_at TEXT,
+                last_sync TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS assignments (
+                id INTEGER PRIMARY KEY,
+                course_id INTEGER,
+                name TEXT NOT NULL,
+                description TEXT,
+                due_at TEXT,
+                points_possible REAL,
+                html_url TEXT,
+                last_sync TEXT NOT NULL,
+                FOREIGN KEY(course_id) REFERENCES courses(id)
+            );
+            CREATE TABLE IF NOT EXISTS discussions (
+                id INTEGER PRIMARY KEY,
+                course_id INTEGER,
+                title TEXT NOT NULL,
+                message TEXT,
+                posted_at TEXT,
+                discussion_type TEXT,
+                pinned BOOLEAN,
+                last_sync TEXT NOT NULL,
+                FOREIGN KEY(course_id) REFERENCES courses(id)
+            );
+            CREATE TABLE IF NOT EXISTS modules (
+                id INTEGER PRIMARY KEY,
+                course_id INTEGER,
+                name TEXT NOT NULL,
+                position INTEGER,
+                unlock_at TEXT,
+                state TEXT,
+                completed_at TEXT,
+                last_sync TEXT NOT NULL,
+                FOREIGN KEY(course_id) REFERENCES courses(id)
+            );
+            CREATE TABLE IF NOT EXISTS module_items (
+                id INTEGER PRIMARY KEY,
+                module_id INTEGER,
+                position INTEGER,
+                title TEXT NOT NULL,
+                item_type TEXT,
+                content_id INTEGER,
+                last_sync TEXT NOT NULL,
+                FOREIGN KEY(module_id) REFERENCES modules(id)
+            );
+            CREATE TABLE IF NOT EXISTS sync_status (
+                type_name TEXT PRIMARY KEY,
+                last_sync TEXT NOT NULL
+            );
+        ",
+    },
+    Migration {
+        version: 2,
+        description: "index cached rows by course and module",
+        sql: "
+            CREATE INDEX IF NOT EXISTS assignments_course_id ON assignments(course_id);
+            CREATE INDEX IF NOT EXISTS discussions_course_id ON discussions(course_id);
+            CREATE INDEX IF NOT EXISTS modules_course_id ON modules(course_id);
+            CREATE INDEX IF NOT EXISTS module_items_module_id ON module_items(module_id);
+        ",
+    },
+    Migration {
+        version: 3,
+        description: "sync cursors and content hashes for incremental sync",
+        sql: "
+            CREATE TABLE sync_cursors (
+                resource TEXT NOT NULL,
+                scope_id INTEGER NOT NULL,
+                synced_at TEXT NOT NULL,
+                PRIMARY KEY (resource, scope_id)
+            );
+            ALTER TABLE courses ADD COLUMN content_hash TEXT;
+            ALTER TABLE assignments ADD COLUMN content_hash TEXT;
+            ALTER TABLE discussions ADD COLUMN content_hash TEXT;
+            ALTER TABLE modules ADD COLUMN content_hash TEXT;
+            ALTER TABLE module_items ADD COLUMN content_hash TEXT;
+        ",
+    },
+    Migration {
+        version: 4,
+        description: "tombstones for rows deleted in Canvas",
+        sql: "
+            ALTER TABLE courses ADD COLUMN deleted_at TEXT;
+            ALTER TABLE assignments ADD COLUMN deleted_at TEXT;
+            ALTER TABLE discussions ADD COLUMN deleted_at TEXT;
+            ALTER TABLE modules ADD COLUMN deleted_at TEXT;
+            ALTER TABLE module_items ADD COLUMN deleted_at TEXT;
+        ",
+    },
+    Migration {
+        version: 5,
+        description: "change log and discussion reply tracking",
+        sql: "
+            CREATE TABLE changes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                recorded_at TEXT NOT NULL,
+                course_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                resource_id INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                detail TEXT
+            );
+            CREATE INDEX changes_recorded_at ON changes(recorded_at);
+            ALTER TABLE discussions ADD COLUMN last_reply_at TEXT;
+            ALTER TABLE discussions ADD COLUMN discussion_subentry_count INTEGER;
+            -- Rewrite every topic on the next sync so the new columns get filled in
+            UPDATE discussions SET content_hash = NULL;
+        ",
+    },
+    Migration {
+        version: 6,
+        description: "sync run history",
+        sql: "
+            CREATE TABLE sync_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at TEXT NOT NULL,
+                finished_at TEXT,
+                full BOOLEAN NOT NULL,
+                courses INTEGER,
+                counts TEXT,
+                errors TEXT
+            );
+        ",
+    },
+    Migration {
+        version: 7,
+        description: "full-text search index",
+        sql: "
+            CREATE TABLE search_documents (
+                id INTEGER PRIMARY KEY,
+                kind TEXT NOT NULL,
+                resource_id INTEGER NOT NULL,
+                course_id INTEGER NOT NULL,
+                content_hash TEXT,
+                deleted_at TEXT,
+                UNIQUE (kind, resource_id)
+            );
+            -- Rows match search_documents by rowid
+            CREATE VIRTUAL TABLE search_index USING fts5(title, body, tokenize = 'porter unicode61');
+            -- Rewrite searchable rows on the next sync so they get indexed, and
+            -- read every discussion instead of only the recently active ones
+            UPDATE assignments SET content_hash = NULL;
+            UPDATE discussions SET content_hash = NULL;
+            UPDATE module_items SET content_hash = NULL;
+            DELETE FROM sync_cursors WHERE resource = 'discussions';
+        ",
+    },
+    Migration {
+        version: 8,
+        description: "wiki pages",
+        sql: "
+            CREATE TABLE pages (
+                id INTEGER PRIMARY KEY,
+                course_id INTEGER NOT NULL,
+                url TEXT NOT NULL,
+                title TEXT NOT NULL,
+                body TEXT,
+                published BOOLEAN,
+                front_page BOOLEAN,
+                html_url TEXT,
+                created_at TEXT,
+                updated_at TEXT,
+                last_sync TEXT NOT NULL,
+                content_hash TEXT,
+                deleted_at TEXT,
+                FOREIGN KEY(course_id) REFERENCES courses(id)
+            );
+            CREATE INDEX pages_course_id ON pages(course_id);
+        ",
+    },
+];
+
+/// The schema version this build expects
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// The schema version recorded in the database
+pub fn current_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Migrations that haven't been applied yet
+pub fn pending(conn: &Connection) -> Result<Vec<&'static Migration>, MigrationError> {
+    let current = current_version(conn)?;
+    if current > latest_version() {
+        return Err(MigrationError::TooNew { found: current, supported: latest_version() });
+    }
+    Ok(MIGRATIONS.iter().filter(|m| m.version > current).collect())
+}
+
+/// What [`migrate`] did
+#[derive(Debug, Default, PartialEq)]
+pub struct MigrationReport {
+    pub from: u32,
+    pub to: u32,
+    /// Copy of the database taken before migrating, if there was anything to keep
+    pub backup: Option<PathBuf>,
+}
+
+/// Bring the database up to [`latest_version`].
+///
+/// Each migration runs in its own transaction together with the
+/// `user_version` bump, so a failed step leaves the database at the previous
+/// version. When `path` is given and the database already holds data, a copy
+/// is written next to it first (see [`backup_path`]).
+pub fn migrate(conn: &Connection, path: Option<&Path>) -> Result<MigrationReport, MigrationError> {
+    let from = current_version(conn)?;
+    let steps = pending(conn)?;
+    if steps.is_empty() {
+        return Ok(MigrationReport { from, to: from, backup: None });
+    }
+
+    let backup = match path {
+        Some(path) if has_tables(conn)? => {
+            let backup = backup_path(path, from);
+            if backup.exists() {
+                std::fs::remove_file(&backup).map_err(|e| MigrationError::Backup(backup.clone(), e))?;
+            }
+            conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+            Some(backup)
+        }
+        _ => None,
+    };
+
+    for step in steps {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(step.sql)
+            .and_then(|_| tx.pragma_update(None, "user_version", step.version))
+            .map_err(|source| MigrationError::Failed { version: step.version, source })?;
+        tx.commit()?;
+    }
+
+    Ok(MigrationReport { from, to: latest_version(), backup })
+}
+
+/// Where the copy taken before migrating from `version` goes, e.g. `canvas.db.v1.bak`
+pub fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    path.with_file_name(name)
+}
+
+fn has_tables(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' LIMIT 1", [], |_| Ok(()))
+        .optional()
+        .map(|row| row.is_some())
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sqlite(rusqlite::Error),
+    /// A migration step failed; the database stays at the version before it
+    Failed { version: u32, source: rusqlite::Error },
+    /// The database was migrated by a newer canvas-cli
+    TooNew { found: u32, supported: u32 },
+    Backup(PathBuf, std::io::Error),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Sqlite(e) => write!(f, "local database error: {}", e),
+            MigrationError::Failed { version, source } => {
+                write!(f, "database migration to version {} failed: {}", version, source)
+            }
+            MigrationError::TooNew { found, supported } => write!(
+                f,
+                "local database has schema version {}, but this canvas-cli only knows up to {}; upgrade canvas-cli",
+                found, supported
+            ),
+            MigrationError::Backup(path, e) => write!(f, "could not replace old backup {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl Error for MigrationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MigrationError::Sqlite(e) => Some(e),
+            MigrationError::Failed { source, .. } => Some(source),
+            MigrationError::Backup(_, e) => Some(e),
+            MigrationError::TooNew { .. } => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
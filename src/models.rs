@@ -1,7 +1,11 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use crate::error::CanvasError;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Course {
     pub id: u64,
     #[serde(default)]
@@ -12,9 +16,12 @@ pub struct Course {
     pub total_students: Option<u64>,
     pub start_at: Option<DateTime<Utc>>,
     pub end_at: Option<DateTime<Utc>>,
+    /// Set on rows read from the local database that Canvas no longer returns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Assignment {
     pub id: u64,
@@ -25,9 +32,95 @@ pub struct Assignment {
     pub html_url: String,
     pub submission_types: Vec<String>,
     pub allowed_extensions: Option<Vec<String>>,
+    /// The user's own submission, when requested with `include[]=submission`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submission: Option<Submission>,
+    /// Criteria the assignment is graded by, if it has a rubric
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rubric: Option<Vec<RubricCriterion>>,
+    /// Set on rows read from the local database that Canvas no longer returns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize)]
+impl Assignment {
+    /// Whether the assignment accepts submissions of the given type
+    pub fn accepts(&self, submission_type: SubmissionType) -> bool {
+        self.submission_types.iter().any(|t| t == submission_type.as_str())
+    }
+
+    /// Like `accepts`, but returns an error listing the allowed types
+    pub fn check_accepts(&self, submission_type: SubmissionType) -> Result<(), CanvasError> {
+        if self.accepts(submission_type) {
+            return Ok(());
+        }
+        Err(CanvasError::UnsupportedSubmissionType {
+            requested: submission_type.to_string(),
+            allowed: self.submission_types.clone(),
+        })
+    }
+}
+
+/// The submission types the CLI can create
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmissionType {
+    Upload,
+    TextEntry,
+    Url,
+}
+
+impl SubmissionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubmissionType::Upload => "online_upload",
+            SubmissionType::TextEntry => "online_text_entry",
+            SubmissionType::Url => "online_url",
+        }
+    }
+}
+
+impl fmt::Display for SubmissionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SubmissionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "online_upload" | "upload" | "file" => Ok(SubmissionType::Upload),
+            "online_text_entry" | "text" => Ok(SubmissionType::TextEntry),
+            "online_url" | "url" => Ok(SubmissionType::Url),
+            _ => Err(format!("unknown submission type '{}' (expected online_upload, online_text_entry or online_url)", s)),
+        }
+    }
+}
+
+/// One criterion of an assignment's rubric
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RubricCriterion {
+    pub id: String,
+    pub description: String,
+    #[serde(default)]
+    pub long_description: Option<String>,
+    #[serde(default)]
+    pub points: Option<f64>,
+    #[serde(default)]
+    pub ratings: Vec<RubricLevel>,
+}
+
+/// A rating a rubric criterion can be given, e.g. "Full marks"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RubricLevel {
+    pub id: String,
+    pub description: String,
+    #[serde(default)]
+    pub points: Option<f64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Submission {
     pub id: u64,
@@ -37,22 +130,119 @@ pub struct Submission {
     pub late: bool,
     pub missing: bool,
     pub grade: Option<String>,
+    /// `unsubmitted`, `submitted`, `pending_review` or `graded`
+    #[serde(default)]
+    pub workflow_state: Option<String>,
+    /// Counts from 1; `None` until something has been submitted
+    #[serde(default)]
+    pub attempt: Option<u32>,
+    #[serde(default)]
+    pub submission_type: Option<String>,
+    #[serde(default)]
+    pub graded_at: Option<DateTime<Utc>>,
+    /// How long after the deadline the submission came in
+    #[serde(default)]
+    pub seconds_late: Option<i64>,
+    #[serde(default)]
+    pub excused: Option<bool>,
+    /// Files handed in with an `online_upload` attempt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    /// Every attempt, oldest first, with `include[]=submission_history`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub submission_history: Vec<Submission>,
+    /// With `include[]=submission_comments`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub submission_comments: Vec<SubmissionComment>,
+    /// Ratings by rubric criterion ID, with `include[]=rubric_assessment`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rubric_assessment: Option<HashMap<String, RubricRating>>,
+}
+
+/// A comment left on a submission by a grader, or by the student
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmissionComment {
+    pub id: u64,
+    #[serde(default)]
+    pub author_id: Option<u64>,
+    pub author_name: String,
+    /// Plain text, not HTML
+    pub comment: String,
+    pub created_at: DateTime<Utc>,
+    /// The attempt the comment is about
+    #[serde(default)]
+    pub attempt: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+}
+
+/// A file attached to a submission or a submission comment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: u64,
+    pub display_name: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub size: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
+/// How a submission was rated on one rubric criterion
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RubricRating {
+    #[serde(default)]
+    pub points: Option<f64>,
+    /// The [`RubricLevel`] chosen, if any
+    #[serde(default)]
+    pub rating_id: Option<String>,
+    #[serde(default)]
+    pub comments: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct CanvasFile {
     pub id: u64,
     pub display_name: String,
     pub filename: String,
+    /// The Files API spells this `content-type`
+    #[serde(alias = "content-type")]
     pub content_type: String,
+    /// Download link; empty when the file is locked for the user
+    #[serde(default)]
     pub url: String,
     pub size: u64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub folder_id: Option<u64>,
+    #[serde(default)]
+    pub locked_for_user: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Folder {
+    pub id: u64,
+    pub name: String,
+    /// Path from the course's root folder, which is named `course files`
+    pub full_name: String,
+    pub parent_folder_id: Option<u64>,
+    #[serde(default)]
+    pub files_count: u64,
+    #[serde(default)]
+    pub folders_count: u64,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Upload slot returned by the first step of a Canvas file upload
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadSlot {
+    pub upload_url: String,
+    #[serde(default)]
+    pub upload_params: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Announcement {
     pub id: u64,
@@ -62,7 +252,7 @@ pub struct Announcement {
     pub url: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Todo {
     #[serde(default)]
@@ -79,7 +269,7 @@ pub struct Todo {
     pub course_id: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Grade {
     pub course_id: u64,
@@ -89,7 +279,7 @@ pub struct Grade {
     pub final_score: Option<f64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Module {
     pub id: u64,
@@ -103,9 +293,15 @@ pub struct Module {
     pub completed_at: Option<DateTime<Utc>>,
     pub items_count: i32,
     pub items_url: String,
+    /// Present when requested with `include[]=items` and the module is small enough
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<Vec<ModuleItem>>,
+    /// Set on rows read from the local database that Canvas no longer returns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct ModuleItem {
     pub id: u64,
@@ -119,9 +315,12 @@ pub struct ModuleItem {
     pub content_id: Option<u64>,
     pub external_url: Option<String>,
     pub completion_requirement: Option<CompletionRequirement>,
+    /// Set on rows read from the local database that Canvas no longer returns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct CompletionRequirement {
     #[serde(rename = "type")]
@@ -130,7 +329,7 @@ pub struct CompletionRequirement {
     pub completed: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Discussion {
     pub id: u64,
@@ -139,6 +338,8 @@ pub struct Discussion {
     pub html_url: String,
     #[serde(default)]
     pub posted_at: Option<DateTime<Utc>>,  // Change to Option
+    #[serde(default)]
+    pub last_reply_at: Option<DateTime<Utc>>,
     pub allow_rating: bool,
     pub user_can_see_posts: bool,
     pub discussion_type: String,
@@ -146,9 +347,19 @@ pub struct Discussion {
     pub published: bool,
     pub locked: bool,
     pub pinned: bool,
+    /// Set on rows read from the local database that Canvas no longer returns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl Discussion {
+    /// When the topic last saw activity: its latest reply, or when it was posted
+    pub fn last_activity_at(&self) -> Option<DateTime<Utc>> {
+        self.last_reply_at.or(self.posted_at)
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct DiscussionEntry {
     pub id: u64,
@@ -160,16 +371,25 @@ pub struct DiscussionEntry {
     pub rating_sum: Option<i32>,
 }
 
-#[derive(Debug, Deserialize)]
+/// A wiki page. Pages are addressed by `url`, a slug of the title, as well as by ID.
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Page {
-    pub page_id: String,
+    pub page_id: u64,
     pub url: String,
     pub title: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
     pub editing_roles: String,
     pub published: bool,
+    #[serde(default)]
+    pub front_page: bool,
     pub html_url: String,
+    /// HTML content; list endpoints only include it when asked to
+    #[serde(default)]
     pub body: Option<String>,
+    /// Set on rows read from the local database that Canvas no longer returns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
@@ -0,0 +1,152 @@
+use crate::client::CanvasClient;
+use crate::db::{CacheScope, Database};
+use crate::error::CanvasError;
+use chrono::{DateTime, Utc};
+use std::error::Error;
+use std::fmt;
+
+/// Where a command's data came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    Network,
+    /// The local database; `fallback` is set when Canvas was unreachable
+    /// rather than `--offline` being given
+    Cache { synced_at: DateTime<Utc>, fallback: bool },
+}
+
+impl Origin {
+    pub fn is_cached(&self) -> bool {
+        matches!(self, Origin::Cache { .. })
+    }
+
+    /// Where data gathered from two loads came from, as far as the user needs
+    /// to know: the staler cache if either was cached
+    pub fn combine(self, other: Origin) -> Origin {
+        match (self, other) {
+            (Origin::Cache { synced_at: a, .. }, Origin::Cache { synced_at: b, .. }) if b < a => other,
+            (Origin::Cache { .. }, _) => self,
+            _ => other,
+        }
+    }
+
+    /// A note on how old cached data is, or `None` for fresh data
+    pub fn notice(&self, now: DateTime<Utc>) -> Option<String> {
+        match self {
+            Origin::Network => None,
+            Origin::Cache { synced_at, fallback } => Some(format!(
+                "{}showing data cached {} ago (last sync {})",
+                if *fallback { "Canvas is unreachable; " } else { "Offline: " },
+                format_age(now - *synced_at),
+                synced_at.format("%Y-%m-%d %H:%M UTC"),
+            )),
+        }
+    }
+}
+
+/// Reads data from Canvas or from the local database.
+///
+/// With no client (`--offline`) everything comes from the cache. With a
+/// client, Canvas is asked first and the cache is only used if Canvas can't
+/// be reached.
+pub struct DataSource<'a> {
+    client: Option<&'a CanvasClient>,
+    cache: Option<&'a Database>,
+}
+
+impl<'a> DataSource<'a> {
+    pub fn new(client: Option<&'a CanvasClient>, cache: Option<&'a Database>) -> Self {
+        Self { client, cache }
+    }
+
+    pub fn load<T>(
+        &self,
+        scope: CacheScope,
+        online: impl FnOnce(&CanvasClient) -> Result<T, CanvasError>,
+        cached: impl FnOnce(&Database) -> rusqlite::Result<T>,
+    ) -> Result<(T, Origin), OfflineError> {
+        let Some(client) = self.client else {
+            return self.load_cached(scope, cached, false);
+        };
+        match online(client) {
+            Ok(data) => Ok((data, Origin::Network)),
+            Err(e) if is_unreachable(&e) && self.cache.is_some() => {
+                self.load_cached(scope, cached, true).map_err(|cache_error| match cache_error {
+                    // Nothing to fall back to, so the network error is the real problem
+                    OfflineError::NotCached => OfflineError::Canvas(e),
+                    other => other,
+                })
+            }
+            Err(e) => Err(OfflineError::Canvas(e)),
+        }
+    }
+
+    fn load_cached<T>(
+        &self,
+        scope: CacheScope,
+        cached: impl FnOnce(&Database) -> rusqlite::Result<T>,
+        fallback: bool,
+    ) -> Result<(T, Origin), OfflineError> {
+        let db = self.cache.ok_or(OfflineError::NoDatabase)?;
+        let synced_at = db.synced_at(scope)?.ok_or(OfflineError::NotCached)?;
+        Ok((cached(db)?, Origin::Cache { synced_at, fallback }))
+    }
+}
+
+/// Whether an error means Canvas couldn't be reached at all
+pub fn is_unreachable(error: &CanvasError) -> bool {
+    match error {
+        CanvasError::Transport(e) => e.is_connect() || e.is_timeout(),
+        _ => false,
+    }
+}
+
+/// Round a duration to its largest whole unit, e.g. "3 hours"
+pub fn format_age(age: chrono::Duration) -> String {
+    let (count, unit) = if age.num_days() > 0 {
+        (age.num_days(), "day")
+    } else if age.num_hours() > 0 {
+        (age.num_hours(), "hour")
+    } else if age.num_minutes() > 0 {
+        (age.num_minutes(), "minute")
+    } else {
+        return "less than a minute".to_string();
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+#[derive(Debug)]
+pub enum OfflineError {
+    /// No local database exists for the active profile
+    NoDatabase,
+    /// The database exists but holds nothing for the request
+    NotCached,
+    Canvas(CanvasError),
+    Database(rusqlite::Error),
+}
+
+impl fmt::Display for OfflineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OfflineError::NoDatabase => write!(f, "no local database yet; run `canvas-cli sync` while online"),
+            OfflineError::NotCached => write!(f, "this data isn't cached; run `canvas-cli sync` while online"),
+            OfflineError::Canvas(e) => write!(f, "{}", e),
+            OfflineError::Database(e) => write!(f, "local database error: {}", e),
+        }
+    }
+}
+
+impl Error for OfflineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OfflineError::Canvas(e) => Some(e),
+            OfflineError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for OfflineError {
+    fn from(e: rusqlite::Error) -> Self {
+        OfflineError::Database(e)
+    }
+}
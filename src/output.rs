@@ -0,0 +1,210 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+/// How command results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Format {
+    /// Aligned columns for reading in a terminal
+    #[default]
+    Table,
+    /// A single JSON document
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Tab-separated values with a header row
+    Tsv,
+}
+
+/// Prints command results in the selected format.
+///
+/// Records are serialized with serde, so machine-readable formats use the
+/// model field names and include every field. `columns` only picks what the
+/// table format shows.
+pub struct Output {
+    pub format: Format,
+}
+
+impl Output {
+    pub fn new(format: Format) -> Self {
+        Self { format }
+    }
+
+    /// Print a list of records
+    pub fn list<T: Serialize>(&self, items: &[T], columns: &[&str]) -> io::Result<()> {
+        write_list(&mut io::stdout().lock(), self.format, items, columns)
+    }
+
+    /// Print a single record
+    pub fn item<T: Serialize>(&self, item: &T, columns: &[&str]) -> io::Result<()> {
+        write_item(&mut io::stdout().lock(), self.format, item, columns)
+    }
+
+    /// Print a progress or confirmation message.
+    ///
+    /// Goes to stdout for tables and to stderr otherwise, keeping
+    /// machine-readable output clean for pipes.
+    pub fn status(&self, message: impl Display) {
+        if self.format == Format::Table {
+            println!("{}", message);
+        } else {
+            eprintln!("{}", message);
+        }
+    }
+}
+
+/// Write a list of records in the given format
+pub fn write_list<T: Serialize>(out: &mut impl Write, format: Format, items: &[T], columns: &[&str]) -> io::Result<()> {
+    let records: Vec<Value> = items.iter().map(to_value).collect();
+    match format {
+        Format::Table => write_table(out, &records, columns),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, &records)?;
+            writeln!(out)
+        }
+        Format::Ndjson => {
+            for record in &records {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)?;
+            }
+            Ok(())
+        }
+        Format::Csv => write_delimited(out, &records, ',', csv_field),
+        Format::Tsv => write_delimited(out, &records, '\t', tsv_field),
+    }
+}
+
+/// Write a single record; tables show it as one `field  value` line per column
+pub fn write_item<T: Serialize>(out: &mut impl Write, format: Format, item: &T, columns: &[&str]) -> io::Result<()> {
+    let record = to_value(item);
+    match format {
+        Format::Table => {
+            let fields = selected_fields(&record, columns);
+            let width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+            for (name, value) in fields {
+                writeln!(out, "{:<width$}  {}", name, cell(value), width = width)?;
+            }
+            Ok(())
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, &record)?;
+            writeln!(out)
+        }
+        _ => write_list(out, format, std::slice::from_ref(&record), columns),
+    }
+}
+
+fn to_value<T: Serialize>(item: &T) -> Value {
+    serde_json::to_value(item).unwrap_or(Value::Null)
+}
+
+/// Column names of a set of records, in first-seen order
+fn all_columns(records: &[Value]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for record in records {
+        if let Value::Object(map) = record {
+            for key in map.keys() {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+        }
+    }
+    columns
+}
+
+fn selected_fields<'a>(record: &'a Value, columns: &[&str]) -> Vec<(String, &'a Value)> {
+    static NULL: Value = Value::Null;
+    match record {
+        Value::Object(map) if columns.is_empty() => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        Value::Object(map) => columns.iter()
+            .map(|c| (c.to_string(), map.get(*c).unwrap_or(&NULL)))
+            .collect(),
+        other => vec![("value".to_string(), other)],
+    }
+}
+
+/// Text of a value for a table or delimited cell
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) if items.iter().all(|v| !v.is_object() && !v.is_array()) => {
+            items.iter().map(cell).collect::<Vec<_>>().join(", ")
+        }
+        other => other.to_string(),
+    }
+}
+
+fn write_table(out: &mut impl Write, records: &[Value], columns: &[&str]) -> io::Result<()> {
+    let columns: Vec<String> = if columns.is_empty() {
+        all_columns(records)
+    } else {
+        columns.iter().map(|c| c.to_string()).collect()
+    };
+    let column_refs: Vec<&str> = columns.iter().map(String::as_str).collect();
+    let rows: Vec<Vec<String>> = records.iter()
+        .map(|record| {
+            selected_fields(record, &column_refs).into_iter()
+                .map(|(_, value)| cell(value).replace('\n', " "))
+                .collect()
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = columns.iter().map(|c| c.chars().count()).collect();
+    for row in &rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+
+    let write_row = |out: &mut dyn Write, values: &[String]| -> io::Result<()> {
+        let line: Vec<String> = values.iter().zip(&widths)
+            .map(|(value, width)| format!("{:<width$}", value, width = width))
+            .collect();
+        writeln!(out, "{}", line.join("  ").trim_end())
+    };
+    write_row(out, &columns.iter().map(|c| c.to_uppercase()).collect::<Vec<_>>())?;
+    for row in &rows {
+        write_row(out, row)?;
+    }
+    Ok(())
+}
+
+fn write_delimited(out: &mut impl Write, records: &[Value], delimiter: char, escape: fn(&str) -> String) -> io::Result<()> {
+    let columns = all_columns(records);
+    if columns.is_empty() {
+        return Ok(());
+    }
+    let separator = delimiter.to_string();
+    let header: Vec<String> = columns.iter().map(|c| escape(c)).collect();
+    writeln!(out, "{}", header.join(&separator))?;
+    for record in records {
+        let row: Vec<String> = columns.iter()
+            .map(|c| escape(&record.get(c).map(cell).unwrap_or_default()))
+            .collect();
+        writeln!(out, "{}", row.join(&separator))?;
+    }
+    Ok(())
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or line break (RFC 4180)
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escape characters that would break a TSV row
+pub fn tsv_field(value: &str) -> String {
+    value.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
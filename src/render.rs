@@ -0,0 +1,735 @@
+//! Canvas HTML rendered as text for reading in a terminal.
+//!
+//! The output reads like Markdown: `#` headings, `-` and `1.` list items,
+//! `>` quotes, pipe tables and indented preformatted blocks. Links keep their
+//! text and get a numbered footnote with the target, listed at the end. Links
+//! to Canvas files are noted by file ID, so they can be fetched with
+//! `download`.
+
+use serde::Serialize;
+use std::io::{self, IsTerminal};
+
+/// Narrowest width text is wrapped to, however small the terminal
+const MIN_WIDTH: usize = 20;
+
+/// How [`render`] lays out its text
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Wrap lines to this many columns; `None` leaves paragraphs on one line
+    pub width: Option<usize>,
+    /// Canvas address that links such as `/courses/1/files/2` are relative to
+    pub base_url: Option<String>,
+}
+
+/// Rendered text, and the Canvas files it links to
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Rendered {
+    pub text: String,
+    pub files: Vec<FileRef>,
+}
+
+/// A Canvas file linked from rendered HTML
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileRef {
+    pub id: u64,
+    pub name: String,
+}
+
+/// Render an HTML fragment as text, with link footnotes at the end
+pub fn render(html: &str, options: &RenderOptions) -> Rendered {
+    let mut renderer = Renderer {
+        width: options.width.map(|w| w.max(MIN_WIDTH)),
+        base_url: options.base_url.as_deref().map(|url| url.trim_end_matches('/').to_string()),
+        ..Renderer::default()
+    };
+    feed(&mut renderer, html);
+    let files = std::mem::take(&mut renderer.files);
+    Rendered { text: renderer.finish(), files }
+}
+
+/// Reduce HTML to the words in it, on one line and without any markup, e.g.
+/// for indexing. Tags separate words, as block elements and line breaks do
+/// when rendered.
+pub fn plain_text(html: &str) -> String {
+    let mut renderer = Renderer { plain: true, ..Renderer::default() };
+    feed(&mut renderer, html);
+    renderer.finish().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Columns to wrap rendered text at: `$COLUMNS` if set, otherwise 80 on a
+/// terminal, or `None` when output goes to a file or pipe
+pub fn terminal_width() -> Option<usize> {
+    if let Some(columns) = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).filter(|c| *c > 0) {
+        return Some(columns);
+    }
+    io::stdout().is_terminal().then_some(80)
+}
+
+/// Run the tokens of `html` through a renderer, leaving out scripts and styles
+fn feed(renderer: &mut Renderer, html: &str) {
+    let mut skip_until: Option<String> = None;
+    for token in tokenize(html) {
+        if let Some(name) = &skip_until {
+            if matches!(&token, Token::End(end) if end == name) {
+                skip_until = None;
+            }
+            continue;
+        }
+        match token {
+            Token::Text(text) => renderer.text(&text),
+            Token::Start { name, .. } if matches!(name.as_str(), "script" | "style" | "head" | "title") => {
+                skip_until = Some(name);
+            }
+            Token::Start { name, attrs } => renderer.start(&name, &attrs),
+            Token::End(name) => renderer.end(&name),
+        }
+    }
+}
+
+/// The ID of the Canvas file a link points to, e.g. `/courses/1/files/5678/download`
+fn file_id(url: &str) -> Option<u64> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let mut segments = path.split('/');
+    segments.find(|s| *s == "files")?;
+    segments.next()?.parse().ok()
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Start { name: String, attrs: Vec<(String, String)> },
+    End(String),
+    Text(String),
+}
+
+/// Split HTML into tags and text, lowercasing tag and attribute names and
+/// decoding entities. Comments and doctypes are dropped. Self-closing tags
+/// come out as a start tag only.
+fn tokenize(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(open) = rest.find('<') else {
+            tokens.push(Token::Text(decode_entities(rest)));
+            break;
+        };
+        if open > 0 {
+            tokens.push(Token::Text(decode_entities(&rest[..open])));
+        }
+        rest = &rest[open..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(close) = tag_end(rest).or_else(|| rest.find('>')) else {
+            // A lone `<` is text
+            tokens.push(Token::Text(decode_entities(rest)));
+            break;
+        };
+        let tag = &rest[1..close];
+        rest = &rest[close + 1..];
+        if let Some(name) = tag.strip_prefix('/') {
+            tokens.push(Token::End(name.trim().to_ascii_lowercase()));
+        } else if tag.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let tag = tag.trim_end_matches('/');
+            let name_end = tag.find(|c: char| c.is_whitespace()).unwrap_or(tag.len());
+            tokens.push(Token::Start {
+                name: tag[..name_end].to_ascii_lowercase(),
+                attrs: parse_attrs(&tag[name_end..]),
+            });
+        }
+    }
+    tokens
+}
+
+/// Where the tag `rest` starts with ends: the first `>` outside a quoted
+/// attribute value, as in `<a title="a>b">`
+fn tag_end(rest: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut after_equals = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => continue,
+            None if c == '>' => return Some(i),
+            None if after_equals && (c == '"' || c == '\'') => quote = Some(c),
+            None => {}
+        }
+        if !c.is_whitespace() {
+            after_equals = c == '=';
+        }
+    }
+    None
+}
+
+/// Attributes of a start tag, e.g. `href="/files/1" title=x`
+fn parse_attrs(mut rest: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        let name_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        if name_end == 0 {
+            return attrs;
+        }
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                let (value, after) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let inner = &value[1..];
+                        let end = inner.find(quote).unwrap_or(inner.len());
+                        (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    }
+                };
+                rest = after;
+                decode_entities(value)
+            }
+            None => String::new(),
+        };
+        attrs.push((name, value));
+    }
+}
+
+/// Replace character references such as `&amp;`, `&#39;` and `&#x2014;`.
+/// Unknown ones are left as they are.
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let end = rest[1..].find(|c: char| !c.is_ascii_alphanumeric() && c != '#').map(|i| i + 1);
+        let entity = match end {
+            Some(end) if rest[end..].starts_with(';') => entity_char(&rest[1..end]).map(|c| (c, end + 1)),
+            _ => None,
+        };
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn entity_char(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bull" => '•',
+        "middot" => '·',
+        "copy" => '©',
+        "reg" => '®',
+        "times" => '×',
+        "deg" => '°',
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum List {
+    Unordered,
+    /// The number of the next item
+    Ordered(u32),
+}
+
+/// A table being collected, rendered once it is closed
+#[derive(Debug, Default)]
+struct Table {
+    /// Cells of each row, and whether the row is all header cells
+    rows: Vec<(Vec<String>, bool)>,
+    cell: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Renderer {
+    width: Option<usize>,
+    base_url: Option<String>,
+    /// Words only: no markup, footnotes or layout
+    plain: bool,
+    out: String,
+    /// Text of the line being built
+    line: String,
+    /// Whitespace was seen since the last word on this line
+    space: bool,
+    /// Put a blank line before the next block
+    blank: bool,
+    lists: Vec<List>,
+    /// Marker for the first line of the current list item, e.g. `- ` or `2. `
+    marker: Option<String>,
+    /// Width of the current item's marker, which later lines are indented by
+    marker_width: usize,
+    quote_depth: usize,
+    pre: bool,
+    /// Targets of open links, with where their text starts on the line
+    links: Vec<(Link, usize)>,
+    footnotes: Vec<String>,
+    files: Vec<FileRef>,
+    /// Tables being collected, innermost last
+    tables: Vec<Table>,
+}
+
+/// Where a link or image points
+#[derive(Debug)]
+enum Link {
+    Url(String),
+    /// A Canvas file, with the name Canvas gave the link if any
+    File(u64, Option<String>),
+}
+
+impl Renderer {
+    fn start(&mut self, name: &str, attrs: &[(String, String)]) {
+        let attr = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+        if self.plain {
+            self.space = true;
+        }
+        match name {
+            "p" | "div" | "section" | "article" | "header" | "footer" | "figure" | "dl" => self.block(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block();
+                if !self.plain {
+                    let level = usize::from(name.as_bytes()[1] - b'0');
+                    self.push_word(&format!("{} ", "#".repeat(level)));
+                    self.space = false;
+                }
+            }
+            "br" => {
+                if self.in_cell() {
+                    self.space = true;
+                } else {
+                    self.flush();
+                }
+            }
+            "hr" => {
+                self.block();
+                if !self.plain {
+                    self.push_word("---");
+                }
+                self.block();
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.block();
+                } else {
+                    self.flush();
+                }
+                let start = attr("start").and_then(|s| s.parse().ok()).unwrap_or(1);
+                self.lists.push(if name == "ol" { List::Ordered(start) } else { List::Unordered });
+            }
+            "li" => {
+                self.flush();
+                let marker = match self.lists.last_mut() {
+                    Some(List::Ordered(n)) => {
+                        let marker = format!("{}. ", n);
+                        *n = n.saturating_add(1);
+                        marker
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.marker_width = marker.len();
+                self.marker = Some(marker);
+            }
+            "dt" | "dd" => self.flush(),
+            "blockquote" => {
+                self.block();
+                self.quote_depth += 1;
+            }
+            "pre" => {
+                self.block();
+                self.pre = true;
+            }
+            "strong" | "b" => self.open_mark("**"),
+            "em" | "i" => self.open_mark("_"),
+            "code" if !self.pre => self.open_mark("`"),
+            "a" => {
+                let link = self.link(attr("href").unwrap_or_default(), attr("title"));
+                let start = self.inline_text().len();
+                self.links.push((link, start));
+            }
+            "img" => {
+                let alt = attr("alt").filter(|a| !a.is_empty());
+                if self.plain {
+                    self.text(alt.unwrap_or_default());
+                    return;
+                }
+                let link = self.link(attr("src").unwrap_or_default(), alt);
+                let note = self.footnote(link);
+                self.text(&format!("[{}]{}", alt.unwrap_or("image"), note));
+            }
+            "table" => {
+                if self.tables.is_empty() {
+                    self.block();
+                }
+                self.tables.push(Table::default());
+            }
+            "tr" => {
+                if let Some(table) = self.tables.last_mut() {
+                    table.rows.push((Vec::new(), true));
+                }
+            }
+            "td" | "th" => {
+                if let Some(table) = self.tables.last_mut() {
+                    if table.rows.is_empty() {
+                        table.rows.push((Vec::new(), true));
+                    }
+                    if let Some((_, header)) = table.rows.last_mut() {
+                        *header &= name == "th";
+                    }
+                    table.cell = Some(String::new());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        if self.plain {
+            self.space = true;
+        }
+        match name {
+            "p" | "div" | "section" | "article" | "header" | "footer" | "figure" | "dl"
+            | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => self.block(),
+            "ul" | "ol" => {
+                self.flush();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank = true;
+                }
+            }
+            "li" | "dt" | "dd" => self.flush(),
+            "blockquote" => {
+                self.block();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            "pre" => {
+                self.flush();
+                self.pre = false;
+                self.blank = true;
+            }
+            "strong" | "b" => self.close_mark("**"),
+            "em" | "i" => self.close_mark("_"),
+            "code" if !self.pre => self.close_mark("`"),
+            "a" => {
+                if let Some((link, start)) = self.links.pop() {
+                    let shown = self.inline_text().get(start..).unwrap_or_default().trim().to_string();
+                    let needs_note = match &link {
+                        Link::Url(url) => !url.is_empty() && *url != shown && !url.starts_with('#'),
+                        Link::File(..) => true,
+                    };
+                    if needs_note && !self.plain {
+                        let link = match link {
+                            // Canvas usually names file links after the file
+                            Link::File(id, None) if !shown.is_empty() => Link::File(id, Some(shown)),
+                            link => link,
+                        };
+                        let note = self.footnote(link);
+                        self.push_word(&note);
+                    }
+                }
+            }
+            "td" | "th" => {
+                if let Some(table) = self.tables.last_mut() {
+                    if let (Some(cell), Some((cells, _))) = (table.cell.take(), table.rows.last_mut()) {
+                        cells.push(cell.split_whitespace().collect::<Vec<_>>().join(" "));
+                    }
+                }
+            }
+            "table" => {
+                if let Some(table) = self.tables.pop() {
+                    if !self.tables.is_empty() {
+                        // A table inside another can't keep its layout, so its
+                        // rows go into the enclosing cell
+                        let rows: Vec<String> = table.rows.into_iter()
+                            .map(|(cells, _)| cells.join(" "))
+                            .filter(|row| !row.is_empty())
+                            .collect();
+                        if !rows.is_empty() {
+                            self.space = true;
+                            self.push_word(&rows.join("; "));
+                            self.space = true;
+                        }
+                    } else if self.plain {
+                        let cells: Vec<String> = table.rows.into_iter().flat_map(|(cells, _)| cells).collect();
+                        self.line = cells.join(" ");
+                        self.flush();
+                        self.blank = true;
+                    } else {
+                        // Tables keep their layout, however wide
+                        for line in table_lines(&table.rows) {
+                            let prefix = self.prefix();
+                            self.emit(&format!("{}{}", prefix, line));
+                        }
+                        self.blank = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre {
+            let mut lines = text.split('\n');
+            if let Some(first) = lines.next() {
+                self.line.push_str(first);
+            }
+            for line in lines {
+                self.flush_pre();
+                self.line.push_str(line);
+            }
+            return;
+        }
+        for (i, word) in text.split(|c: char| c.is_whitespace()).enumerate() {
+            if i > 0 {
+                self.space = true;
+            }
+            if !word.is_empty() {
+                self.push_word(word);
+            }
+        }
+    }
+
+    /// Add a word to the current line or table cell, after a space if one was seen
+    fn push_word(&mut self, word: &str) {
+        let space = std::mem::take(&mut self.space);
+        let target = match self.tables.last_mut() {
+            Some(Table { cell: Some(cell), .. }) => cell,
+            _ => &mut self.line,
+        };
+        if space && !target.is_empty() && !target.ends_with(' ') {
+            target.push(' ');
+        }
+        target.push_str(word);
+    }
+
+    /// Emphasis markers attach to the words they surround
+    fn open_mark(&mut self, mark: &str) {
+        if self.plain {
+            return;
+        }
+        self.push_word(mark);
+        self.space = false;
+    }
+
+    fn close_mark(&mut self, mark: &str) {
+        if self.plain {
+            return;
+        }
+        let space = std::mem::take(&mut self.space);
+        let target = match self.tables.last_mut() {
+            Some(Table { cell: Some(cell), .. }) => cell,
+            _ => &mut self.line,
+        };
+        // Nothing was marked
+        if target.ends_with(mark) {
+            target.truncate(target.len() - mark.len());
+        } else {
+            target.push_str(mark);
+        }
+        self.space = space;
+    }
+
+    fn in_cell(&self) -> bool {
+        matches!(self.tables.last(), Some(Table { cell: Some(_), .. }))
+    }
+
+    fn inline_text(&self) -> &str {
+        match self.tables.last() {
+            Some(Table { cell: Some(cell), .. }) => cell,
+            _ => &self.line,
+        }
+    }
+
+    /// What a link target refers to, made absolute if it is relative to Canvas
+    fn link(&self, url: &str, name: Option<&str>) -> Link {
+        if let Some(id) = file_id(url) {
+            return Link::File(id, name.filter(|n| !n.is_empty()).map(str::to_string));
+        }
+        match &self.base_url {
+            Some(base) if url.starts_with('/') && !url.starts_with("//") => Link::Url(format!("{}{}", base, url)),
+            _ => Link::Url(url.to_string()),
+        }
+    }
+
+    /// Register a link target, returning its marker such as `[1]`
+    fn footnote(&mut self, link: Link) -> String {
+        let note = match link {
+            Link::Url(url) => url,
+            Link::File(id, name) => match self.files.iter().find(|f| f.id == id) {
+                Some(file) => format!("{} (file {})", file.name, id),
+                None => {
+                    let name = name.unwrap_or_else(|| format!("file {}", id));
+                    self.files.push(FileRef { id, name: name.clone() });
+                    format!("{} (file {})", name, id)
+                }
+            },
+        };
+        let number = match self.footnotes.iter().position(|f| *f == note) {
+            Some(i) => i + 1,
+            None => {
+                self.footnotes.push(note);
+                self.footnotes.len()
+            }
+        };
+        format!("[{}]", number)
+    }
+
+    /// End the current block; the next one starts after a blank line
+    fn block(&mut self) {
+        self.flush();
+        if self.lists.is_empty() {
+            self.blank = true;
+        }
+    }
+
+    /// Write out the current line, if it has any text, wrapped to the width
+    fn flush(&mut self) {
+        if self.pre {
+            if !self.line.is_empty() {
+                self.flush_pre();
+            }
+            return;
+        }
+        let line = std::mem::take(&mut self.line);
+        self.space = false;
+        let line = line.trim_matches([' ', '\u{a0}']);
+        if line.is_empty() {
+            return;
+        }
+        let first = self.prefix();
+        let rest = self.prefix();
+        let lines = match self.width {
+            Some(width) => wrap(line, width.saturating_sub(rest.chars().count()).max(MIN_WIDTH / 2)),
+            None => vec![line.to_string()],
+        };
+        for (i, line) in lines.iter().enumerate() {
+            let prefix = if i == 0 { &first } else { &rest };
+            self.emit(&format!("{}{}", prefix, line));
+        }
+    }
+
+    fn flush_pre(&mut self) {
+        let line = std::mem::take(&mut self.line);
+        let indent = if self.plain { "" } else { "    " };
+        let prefix = format!("{}{}", self.prefix(), indent);
+        self.emit(format!("{}{}", prefix, line.trim_end()).trim_end());
+    }
+
+    /// Quote markers and list indentation for the next line
+    fn prefix(&mut self) -> String {
+        if self.plain {
+            return String::new();
+        }
+        let mut prefix = "> ".repeat(self.quote_depth);
+        if !self.lists.is_empty() {
+            prefix.push_str(&"  ".repeat(self.lists.len() - 1));
+            match self.marker.take() {
+                Some(marker) => prefix.push_str(&marker),
+                // Later lines of an item line up with its first
+                None => prefix.push_str(&" ".repeat(self.marker_width)),
+            }
+        }
+        prefix
+    }
+
+    fn emit(&mut self, line: &str) {
+        if std::mem::take(&mut self.blank) && !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn finish(mut self) -> String {
+        self.pre = false;
+        self.flush();
+        if !self.footnotes.is_empty() {
+            self.blank = true;
+            for (i, target) in std::mem::take(&mut self.footnotes).iter().enumerate() {
+                self.emit(&format!("[{}] {}", i + 1, target));
+            }
+        }
+        self.out.trim_end().to_string()
+    }
+}
+
+/// Break text into lines of at most `width` characters at spaces. Words
+/// longer than that get a line of their own.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut length = 0;
+    for word in text.split(' ').filter(|w| !w.is_empty()) {
+        let word_length = word.chars().count();
+        if length > 0 && length + 1 + word_length > width {
+            lines.push(std::mem::take(&mut line));
+            length = 0;
+        }
+        if length > 0 {
+            line.push(' ');
+            length += 1;
+        }
+        line.push_str(word);
+        length += word_length;
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// A pipe table with aligned columns, and a rule under the header row if there is one
+fn table_lines(rows: &[(Vec<String>, bool)]) -> Vec<String> {
+    let rows: Vec<&(Vec<String>, bool)> = rows.iter().filter(|(cells, _)| !cells.is_empty()).collect();
+    let columns = rows.iter().map(|(cells, _)| cells.len()).max().unwrap_or(0);
+    let mut widths = vec![3; columns];
+    for (cells, _) in &rows {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: &[String]| {
+        let padded: Vec<String> = widths.iter().enumerate()
+            .map(|(i, width)| format!("{:<width$}", cells.get(i).map_or("", String::as_str), width = width))
+            .collect();
+        format!("| {} |", padded.join(" | "))
+    };
+    let mut lines = Vec::new();
+    for (i, (cells, header)) in rows.iter().enumerate() {
+        lines.push(line(cells));
+        if i == 0 && *header {
+            let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+            lines.push(line(&rule));
+        }
+    }
+    lines
+}
@@ -0,0 +1,144 @@
+use crate::client::CanvasClient;
+use crate::db::Database;
+use crate::error::CanvasError;
+use crate::models::Course;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Turns a course argument into a course ID.
+///
+/// An argument may be a numeric ID, an alias from the active profile, a
+/// `course_code`, or a fragment of the course name. Codes and names are looked
+/// up in the local database first, and in the Canvas API when nothing cached
+/// matches and a client is available.
+pub struct CourseResolver<'a> {
+    aliases: &'a BTreeMap<String, u64>,
+    db: Option<&'a Database>,
+    client: Option<&'a CanvasClient>,
+}
+
+impl<'a> CourseResolver<'a> {
+    pub fn new(aliases: &'a BTreeMap<String, u64>, db: Option<&'a Database>, client: Option<&'a CanvasClient>) -> Self {
+        Self { aliases, db, client }
+    }
+
+    pub fn resolve(&self, query: &str) -> Result<u64, ResolveError> {
+        let query = query.trim();
+        if let Some(id) = self.aliases.iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(query))
+            .map(|(_, id)| *id)
+        {
+            return Ok(id);
+        }
+        if let Ok(id) = query.parse::<u64>() {
+            return Ok(id);
+        }
+
+        if let Some(db) = self.db {
+            let cached = db.get_courses(false)?;
+            match match_course(query, &cached) {
+                Err(ResolveError::NotFound(_)) => {}
+                result => return result.map(|course| course.id),
+            }
+        }
+
+        match self.client {
+            Some(client) => match_course(query, &client.get_courses()?).map(|course| course.id),
+            None => Err(ResolveError::NotFound(query.to_string())),
+        }
+    }
+}
+
+/// Pick the course a query refers to.
+///
+/// Tries, in order, an exact `course_code`, an exact name, and finally every
+/// word of the query appearing in the name or code. All comparisons ignore
+/// case. The first step with any match decides: one match wins, several are
+/// ambiguous.
+pub fn match_course<'c>(query: &str, courses: &'c [Course]) -> Result<&'c Course, ResolveError> {
+    let query = query.trim();
+    let needle = query.to_lowercase();
+    let words: Vec<&str> = needle.split_whitespace().collect();
+    let lower = |value: &Option<String>| value.as_deref().unwrap_or_default().to_lowercase();
+
+    let steps: [&dyn Fn(&Course) -> bool; 3] = [
+        &|c| lower(&c.course_code) == needle,
+        &|c| lower(&c.name) == needle,
+        &|c| {
+            let text = format!("{} {}", lower(&c.name), lower(&c.course_code));
+            !words.is_empty() && words.iter().all(|w| text.contains(w))
+        },
+    ];
+    for step in steps {
+        let matches: Vec<&Course> = courses.iter().filter(|c| step(c)).collect();
+        match matches.as_slice() {
+            [] => continue,
+            [course] => return Ok(course),
+            _ => {
+                return Err(ResolveError::Ambiguous {
+                    query: query.to_string(),
+                    candidates: matches.iter().map(|c| describe(c)).collect(),
+                })
+            }
+        }
+    }
+    Err(ResolveError::NotFound(query.to_string()))
+}
+
+fn describe(course: &Course) -> String {
+    match (&course.course_code, &course.name) {
+        (Some(code), Some(name)) => format!("{} {} ({})", course.id, code, name),
+        (Some(text), None) | (None, Some(text)) => format!("{} {}", course.id, text),
+        (None, None) => course.id.to_string(),
+    }
+}
+
+#[derive(Debug)]
+pub enum ResolveError {
+    /// No course matched the query
+    NotFound(String),
+    /// Several courses matched; each candidate is described for the user
+    Ambiguous { query: String, candidates: Vec<String> },
+    Canvas(CanvasError),
+    Database(rusqlite::Error),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::NotFound(query) => write!(f, "no course matches '{}' (see `canvas-cli courses`)", query),
+            ResolveError::Ambiguous { query, candidates } => {
+                write!(f, "'{}' matches several courses; use an ID, a course code or an alias:", query)?;
+                for candidate in candidates {
+                    write!(f, "\n  {}", candidate)?;
+                }
+                Ok(())
+            }
+            ResolveError::Canvas(e) => write!(f, "{}", e),
+            ResolveError::Database(e) => write!(f, "local database error: {}", e),
+        }
+    }
+}
+
+impl Error for ResolveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ResolveError::Canvas(e) => Some(e),
+            ResolveError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<CanvasError> for ResolveError {
+    fn from(e: CanvasError) -> Self {
+        ResolveError::Canvas(e)
+    }
+}
+
+impl From<rusqlite::Error> for ResolveError {
+    fn from(e: rusqlite::Error) -> Self {
+        ResolveError::Database(e)
+    }
+}
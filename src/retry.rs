@@ -0,0 +1,131 @@
+use crate::error::CanvasError;
+use reqwest::header::HeaderMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Limits controlling how `CanvasClient` paces and retries requests
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    /// Number of times an idempotent request is retried before giving up
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled on every further attempt
+    pub base_delay: Duration,
+    /// Upper bound for a single backoff delay
+    pub max_delay: Duration,
+    /// Start slowing down once `X-Rate-Limit-Remaining` drops below this
+    pub low_water_mark: f64,
+    /// Longest pause inserted before a request when the bucket is nearly empty
+    pub throttle_delay: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            low_water_mark: 200.0,
+            throttle_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Never retry or throttle
+    #[allow(dead_code)]
+    pub fn disabled() -> Self {
+        Self {
+            max_retries: 0,
+            low_water_mark: 0.0,
+            ..Self::default()
+        }
+    }
+
+    /// Jittered exponential backoff for the given (zero-based) retry attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self.base_delay.saturating_mul(2u32.saturating_pow(attempt));
+        let capped = exp.min(self.max_delay);
+        // "Equal jitter": keep half the delay and randomize the other half
+        let half = capped / 2;
+        half + half.mul_f64(jitter())
+    }
+
+    /// Whether a failed request is worth retrying
+    ///
+    /// A host that refuses or can't be reached isn't: reads that can fall
+    /// back to the local database shouldn't stall on backoff first.
+    pub fn is_retryable(error: &CanvasError) -> bool {
+        match error {
+            CanvasError::RateLimited => true,
+            CanvasError::Api { status, .. } => status.is_server_error(),
+            CanvasError::Transport(e) => e.is_timeout(),
+            _ => false,
+        }
+    }
+}
+
+/// Snapshot of Canvas's rate limit bucket as last reported by the API
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimitState {
+    /// Value of the last `X-Rate-Limit-Remaining` header
+    pub remaining: Option<f64>,
+    /// Value of the last `X-Request-Cost` header
+    pub last_cost: Option<f64>,
+}
+
+/// Tracks the rate limit headers returned by Canvas
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    state: Mutex<RateLimitState>,
+}
+
+impl RateLimiter {
+    pub fn state(&self) -> RateLimitState {
+        *self.state.lock().unwrap()
+    }
+
+    /// Record the rate limit headers of a response
+    pub fn update(&self, headers: &HeaderMap) {
+        let header = |name: &str| {
+            headers.get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<f64>().ok())
+        };
+        let mut state = self.state.lock().unwrap();
+        if let Some(remaining) = header("x-rate-limit-remaining") {
+            state.remaining = Some(remaining);
+        }
+        if let Some(cost) = header("x-request-cost") {
+            state.last_cost = Some(cost);
+        }
+    }
+
+    /// How long to wait before the next request so the bucket can refill.
+    ///
+    /// The pause grows linearly as the expected remaining budget (after another
+    /// request of the same cost) falls below the configured low water mark.
+    pub fn delay(&self, config: &RetryConfig) -> Duration {
+        let state = self.state();
+        let Some(remaining) = state.remaining else {
+            return Duration::ZERO;
+        };
+        if config.low_water_mark <= 0.0 {
+            return Duration::ZERO;
+        }
+        let expected = remaining - state.last_cost.unwrap_or(0.0);
+        if expected >= config.low_water_mark {
+            return Duration::ZERO;
+        }
+        let pressure = (1.0 - expected / config.low_water_mark).clamp(0.0, 1.0);
+        config.throttle_delay.mul_f64(pressure)
+    }
+}
+
+/// A random factor in `[0, 1)`
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Markers around matched words in search snippets
+pub const HIGHLIGHT: (&str, &str) = ("**", "**");
+
+/// The kinds of synced content in the search index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    Assignment,
+    Discussion,
+    Announcement,
+    ModuleItem,
+    Page,
+}
+
+impl DocumentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentKind::Assignment => "assignment",
+            DocumentKind::Discussion => "discussion",
+            DocumentKind::Announcement => "announcement",
+            DocumentKind::ModuleItem => "module_item",
+            DocumentKind::Page => "page",
+        }
+    }
+}
+
+impl fmt::Display for DocumentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DocumentKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "assignment" => Ok(DocumentKind::Assignment),
+            "discussion" => Ok(DocumentKind::Discussion),
+            "announcement" => Ok(DocumentKind::Announcement),
+            "module_item" => Ok(DocumentKind::ModuleItem),
+            "page" => Ok(DocumentKind::Page),
+            _ => Err(format!("unknown document kind '{}'", s)),
+        }
+    }
+}
+
+/// One match from [`crate::db::Database::search`], best matches first
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub kind: DocumentKind,
+    pub resource_id: u64,
+    pub course_id: u64,
+    /// Filled in from the cached course
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub course_code: Option<String>,
+    pub title: String,
+    /// The best matching passage, with matched words between [`HIGHLIGHT`] markers
+    pub snippet: String,
+    /// BM25 score; lower is more relevant
+    pub rank: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Turn what the user typed into an FTS5 query matching documents that
+/// contain every word. Words are quoted, so punctuation such as `C++` or
+/// `-` never reads as query syntax. Returns `None` if there are no words.
+pub fn match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
@@ -0,0 +1,94 @@
+use crate::client::{parse_json, CanvasClient};
+use crate::error::Result;
+use crate::models::*;
+use serde::Serialize;
+use std::path::Path;
+
+/// What [`CanvasClient::get_submission`] asks Canvas to include
+const SUBMISSION_INCLUDES: &str = "include[]=submission_comments&include[]=rubric_assessment&include[]=submission_history";
+
+impl CanvasClient {
+    /// The user's own submission for an assignment, with every attempt, the
+    /// comments on it and its rubric assessment
+    pub fn get_submission(&self, course_id: u64, assignment_id: u64) -> Result<Submission> {
+        self.get(&self.api_url(&format!(
+            "courses/{}/assignments/{}/submissions/self?{}",
+            course_id, assignment_id, SUBMISSION_INCLUDES
+        )))
+    }
+
+    /// Comment on the user's own submission for an assignment.
+    ///
+    /// `comment` is plain text. Files are uploaded first and attached to the
+    /// comment. Returns the submission with its comments, the new one last.
+    pub fn comment_on_submission<P: AsRef<Path>>(&self, course_id: u64, assignment_id: u64, comment: &str, file_paths: &[P]) -> Result<Submission> {
+        let submission = format!("courses/{}/assignments/{}/submissions/self", course_id, assignment_id);
+        let endpoint = format!("{}/comments/files", submission);
+        let file_ids = file_paths.iter()
+            .map(|path| self.upload_file(&endpoint, path.as_ref()).map(|file| file.id))
+            .collect::<Result<Vec<_>>>()?;
+
+        let resp = self.send(self.http()
+            .put(self.api_url(&submission))
+            .json(&serde_json::json!({ "comment": { "text_comment": comment, "file_ids": file_ids } })))?;
+        parse_json(resp)
+    }
+}
+
+/// Attempts that were actually handed in, oldest first. Canvas lists an
+/// empty attempt for submissions that haven't been made yet.
+pub fn attempts(submission: &Submission) -> Vec<&Submission> {
+    let mut attempts: Vec<&Submission> = submission.submission_history.iter()
+        .filter(|attempt| attempt.submitted_at.is_some())
+        .collect();
+    attempts.sort_by_key(|attempt| (attempt.attempt, attempt.submitted_at));
+    attempts
+}
+
+/// One criterion of a rubric assessment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RubricRow {
+    pub criterion: String,
+    pub points: Option<f64>,
+    pub points_possible: Option<f64>,
+    /// Description of the rating chosen
+    pub rating: Option<String>,
+    pub comments: Option<String>,
+}
+
+/// A rubric assessment in the order of the rubric's criteria. Criteria the
+/// rubric doesn't list any more come last, named by their ID.
+pub fn rubric_rows(rubric: &[RubricCriterion], submission: &Submission) -> Vec<RubricRow> {
+    let Some(assessment) = &submission.rubric_assessment else {
+        return Vec::new();
+    };
+    let comments = |rating: &RubricRating| rating.comments.clone().filter(|c| !c.trim().is_empty());
+    let mut rows: Vec<RubricRow> = rubric.iter()
+        .map(|criterion| {
+            let rating = assessment.get(&criterion.id);
+            let level = rating
+                .and_then(|r| r.rating_id.as_ref())
+                .and_then(|id| criterion.ratings.iter().find(|level| level.id == *id));
+            RubricRow {
+                criterion: criterion.description.clone(),
+                points: rating.and_then(|r| r.points),
+                points_possible: criterion.points,
+                rating: level.map(|level| level.description.clone()),
+                comments: rating.and_then(comments),
+            }
+        })
+        .collect();
+
+    let mut unknown: Vec<(&String, &RubricRating)> = assessment.iter()
+        .filter(|(id, _)| !rubric.iter().any(|criterion| criterion.id == **id))
+        .collect();
+    unknown.sort_by_key(|(id, _)| *id);
+    rows.extend(unknown.into_iter().map(|(id, rating)| RubricRow {
+        criterion: id.clone(),
+        points: rating.points,
+        points_possible: None,
+        rating: None,
+        comments: comments(rating),
+    }));
+    rows
+}
@@ -0,0 +1,400 @@
+use crate::async_client::AsyncCanvasClient;
+use crate::changes::{self, Change};
+use crate::client::CanvasClient;
+use crate::db::{CacheScope, Database, SyncOutcome};
+use crate::error::CanvasError;
+use crate::models::{Announcement, Assignment, Discussion, Module, Page};
+use chrono::{DateTime, Utc};
+use futures_util::{stream, StreamExt, TryFutureExt};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+
+/// Row counts for one kind of resource
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct Counts {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Rows tombstoned because Canvas no longer returns them
+    pub deleted: usize,
+}
+
+impl Counts {
+    fn record(&mut self, outcome: SyncOutcome) {
+        match outcome {
+            SyncOutcome::Inserted => self.inserted += 1,
+            SyncOutcome::Updated => self.updated += 1,
+            SyncOutcome::Unchanged => self.unchanged += 1,
+        }
+    }
+}
+
+/// What a sync run did
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub courses: usize,
+    /// Counts keyed by table name
+    pub counts: BTreeMap<&'static str, Counts>,
+    /// Failures that were skipped so the rest of the sync could go on
+    pub errors: Vec<String>,
+    /// Entries added to the change log
+    pub changes: usize,
+}
+
+impl SyncReport {
+    fn record(&mut self, table: &'static str, outcome: SyncOutcome) {
+        self.counts.entry(table).or_default().record(outcome);
+    }
+
+    fn record_deleted(&mut self, table: &'static str, count: usize) {
+        self.counts.entry(table).or_default().deleted += count;
+    }
+
+    /// Fold in the report of a course that was committed
+    fn merge(&mut self, other: SyncReport) {
+        for (table, counts) in other.counts {
+            let total = self.counts.entry(table).or_default();
+            total.inserted += counts.inserted;
+            total.updated += counts.updated;
+            total.unchanged += counts.unchanged;
+            total.deleted += counts.deleted;
+        }
+        self.errors.extend(other.errors);
+        self.changes += other.changes;
+    }
+}
+
+/// How many courses are fetched, and requests made, at once unless told otherwise
+pub const DEFAULT_JOBS: usize = 4;
+
+/// Copies Canvas data into the local database.
+///
+/// Each resource keeps a cursor per course recording when it was last synced
+/// completely. Rows whose content hash hasn't changed are not rewritten.
+/// Discussions are listed most recently active first and pages most recently
+/// updated first, so an incremental sync stops at the first one untouched
+/// since the last run. Canvas can't filter assignments or modules by time, and
+/// announcements are read in full so that deleted ones are noticed, so those
+/// are listed completely every time. A full sync ignores cursors and walks
+/// everything.
+///
+/// Courses are fetched concurrently, with a bounded number of requests in
+/// flight, while all writes happen one course at a time on the calling
+/// thread, in course order.
+///
+/// Whenever a complete listing has been fetched, cached rows missing from it
+/// are tombstoned rather than dropped (see [`Database::mark_deleted`]).
+///
+/// Incoming rows are compared with the stored ones and noteworthy differences
+/// go to the change log. The first sync of a resource only sets the baseline,
+/// so it doesn't report everything as new.
+pub struct Syncer<'a> {
+    client: &'a CanvasClient,
+    db: &'a Database,
+    full: bool,
+    jobs: usize,
+}
+
+/// Everything fetched for one course, ready to be written
+struct CourseData {
+    assignments: Vec<Assignment>,
+    announcements: Vec<Announcement>,
+    discussions: Vec<Discussion>,
+    /// Whether `discussions` is the whole listing rather than only recently active topics
+    discussions_complete: bool,
+    modules: Vec<Module>,
+    /// `None` when the course has its pages turned off
+    pages: Option<Vec<Page>>,
+    /// Whether `pages` is the whole listing rather than only recently updated pages
+    pages_complete: bool,
+}
+
+/// Where an incremental fetch of a course picks up; `None` fetches everything
+#[derive(Clone, Copy, Default)]
+struct Since {
+    discussions: Option<DateTime<Utc>>,
+    pages: Option<DateTime<Utc>>,
+}
+
+impl<'a> Syncer<'a> {
+    pub fn new(client: &'a CanvasClient, db: &'a Database) -> Self {
+        Self { client, db, full: false, jobs: DEFAULT_JOBS }
+    }
+
+    /// Ignore cursors and refetch everything
+    pub fn full(mut self, full: bool) -> Self {
+        self.full = full;
+        self
+    }
+
+    /// Fetch up to `jobs` courses, with up to `jobs` requests in flight, at a time
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Sync every course, calling `progress` with a line per course.
+    ///
+    /// Each course is synced in its own transaction: if any part of it fails,
+    /// none of its changes are kept and the failure is reported instead. Every
+    /// run is recorded in the `sync_runs` table.
+    pub fn run(&self, progress: impl Fn(String)) -> Result<SyncReport, SyncError> {
+        let started = Utc::now();
+        let run_id = self.db.start_sync_run(started, self.full)?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to start async runtime");
+        let result = runtime.block_on(self.sync_all(started, progress));
+        match &result {
+            Ok(report) => self.db.finish_sync_run(run_id, Utc::now(), report.courses, &report.counts, &report.errors)?,
+            Err(e) => self.db.finish_sync_run(run_id, Utc::now(), 0, &BTreeMap::<&str, Counts>::new(), &[e.to_string()])?,
+        }
+        result
+    }
+
+    async fn sync_all(&self, started: DateTime<Utc>, progress: impl Fn(String)) -> Result<SyncReport, SyncError> {
+        let client = self.client.to_async().with_max_concurrency(self.jobs);
+        let mut report = SyncReport::default();
+
+        let courses = client.get_courses().await?;
+        let tx = self.db.transaction()?;
+        for course in &courses {
+            report.record("courses", self.db.sync_course(course)?);
+        }
+        let ids: Vec<u64> = courses.iter().map(|c| c.id).collect();
+        report.record_deleted("courses", self.db.mark_deleted(CacheScope::Courses, &ids)?.len());
+        self.db.set_cursor("courses", 0, started)?;
+        tx.commit()?;
+        report.courses = courses.len();
+
+        // Read the cursors up front so the fetches never touch the database
+        let mut plans = Vec::with_capacity(courses.len());
+        for course in &courses {
+            let since = if self.full {
+                Since::default()
+            } else {
+                Since { discussions: self.db.cursor("discussions", course.id)?, pages: self.db.cursor("pages", course.id)? }
+            };
+            plans.push((course, since));
+        }
+        let client = &client;
+        let mut fetched = stream::iter(plans)
+            .map(|(course, since)| async move { (course, fetch_course(client, course.id, since).await) })
+            .buffered(self.jobs);
+
+        while let Some((course, data)) = fetched.next().await {
+            progress(format!("Syncing course: {}", course.name.as_deref().unwrap_or("Unnamed")));
+            let mut synced = SyncReport::default();
+            let tx = self.db.transaction()?;
+            match data.and_then(|data| self.write_course(course.id, started, data, &mut synced)) {
+                Ok(()) => {
+                    tx.commit()?;
+                    report.merge(synced);
+                }
+                // Dropping the transaction rolls the course back
+                Err((resource, e)) => report.errors.push(format!(
+                    "could not sync {} for course {}, so none of its changes were kept: {}",
+                    resource, course.id, e
+                )),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Write everything fetched for a course, stopping at the first failure
+    fn write_course(&self, course_id: u64, started: DateTime<Utc>, data: CourseData, report: &mut SyncReport) -> Result<(), (&'static str, SyncError)> {
+        self.write_assignments(course_id, started, &data.assignments, report).map_err(|e| ("assignments", e))?;
+        self.write_announcements(course_id, started, &data.announcements, report).map_err(|e| ("announcements", e))?;
+        self.write_discussions(course_id, started, &data.discussions, data.discussions_complete, report)
+            .map_err(|e| ("discussions", e))?;
+        self.write_modules(course_id, started, &data.modules, report).map_err(|e| ("modules", e))?;
+        if let Some(pages) = &data.pages {
+            self.write_pages(course_id, started, pages, data.pages_complete, report).map_err(|e| ("pages", e))?;
+        }
+        Ok(())
+    }
+
+    fn write_assignments(&self, course_id: u64, started: DateTime<Utc>, assignments: &[Assignment], report: &mut SyncReport) -> Result<(), SyncError> {
+        // Canvas can't filter assignments by update time; the content hashes keep unchanged rows untouched
+        let known = self.known("assignments", course_id, |db| db.get_assignments(course_id, true), |a| a.id)?;
+        for assignment in assignments {
+            if let Some(known) = &known {
+                self.log(report, changes::assignment_changes(started, course_id, known.get(&assignment.id), assignment))?;
+            }
+            report.record("assignments", self.db.sync_assignment(course_id, assignment)?);
+        }
+        let ids: Vec<u64> = assignments.iter().map(|a| a.id).collect();
+        report.record_deleted("assignments", self.db.mark_deleted(CacheScope::Assignments(course_id), &ids)?.len());
+        self.db.set_cursor("assignments", course_id, started)?;
+        Ok(())
+    }
+
+    fn write_discussions(
+        &self,
+        course_id: u64,
+        started: DateTime<Utc>,
+        discussions: &[Discussion],
+        complete: bool,
+        report: &mut SyncReport,
+    ) -> Result<(), SyncError> {
+        let known = self.known("discussions", course_id, |db| db.get_discussions(course_id, true), |d| d.id)?;
+        for discussion in discussions {
+            if let Some(known) = &known {
+                self.log(report, changes::discussion_changes(started, course_id, known.get(&discussion.id), discussion))?;
+            }
+            report.record("discussions", self.db.sync_discussion(course_id, discussion)?);
+        }
+        // Deletions can only be told apart from quiet topics after seeing the whole listing
+        if complete {
+            let ids: Vec<u64> = discussions.iter().map(|d| d.id).collect();
+            report.record_deleted("discussions", self.db.mark_deleted(CacheScope::Discussions(course_id), &ids)?.len());
+        }
+        self.db.set_cursor("discussions", course_id, started)?;
+        Ok(())
+    }
+
+    fn write_modules(&self, course_id: u64, started: DateTime<Utc>, modules: &[Module], report: &mut SyncReport) -> Result<(), SyncError> {
+        let known = self.known("modules", course_id, |db| db.get_modules(course_id, true), |m| m.id)?;
+        for module in modules {
+            if let Some(known) = &known {
+                self.log(report, changes::module_changes(started, course_id, known.get(&module.id), module))?;
+            }
+            report.record("modules", self.db.sync_module(course_id, module)?);
+            let items = module.items.as_deref().unwrap_or_default();
+            for item in items {
+                report.record("module_items", self.db.sync_module_item(module.id, item)?);
+            }
+            let ids: Vec<u64> = items.iter().map(|i| i.id).collect();
+            report.record_deleted("module_items", self.db.mark_deleted(CacheScope::ModuleItems(module.id), &ids)?.len());
+        }
+        let ids: Vec<u64> = modules.iter().map(|m| m.id).collect();
+        for module_id in self.db.mark_deleted(CacheScope::Modules(course_id), &ids)? {
+            report.record_deleted("modules", 1);
+            // Items of a deleted module went with it
+            report.record_deleted("module_items", self.db.mark_deleted(CacheScope::ModuleItems(module_id), &[])?.len());
+        }
+        self.db.set_cursor("modules", course_id, started)?;
+        Ok(())
+    }
+
+    fn write_pages(&self, course_id: u64, started: DateTime<Utc>, pages: &[Page], complete: bool, report: &mut SyncReport) -> Result<(), SyncError> {
+        for page in pages {
+            report.record("pages", self.db.sync_page(course_id, page)?);
+        }
+        if complete {
+            let ids: Vec<u64> = pages.iter().map(|p| p.page_id).collect();
+            report.record_deleted("pages", self.db.mark_deleted(CacheScope::Pages(course_id), &ids)?.len());
+        }
+        self.db.set_cursor("pages", course_id, started)?;
+        Ok(())
+    }
+
+    fn write_announcements(&self, course_id: u64, started: DateTime<Utc>, announcements: &[Announcement], report: &mut SyncReport) -> Result<(), SyncError> {
+        // Announcements are only kept in the search index; the cursor is the newest posting seen so far
+        for announcement in announcements {
+            report.record("announcements", self.db.index_announcement(course_id, announcement)?);
+        }
+        let ids: Vec<u64> = announcements.iter().map(|a| a.id).collect();
+        report.record_deleted("announcements", self.db.mark_announcements_deleted(course_id, &ids)?.len());
+        let seen = self.db.cursor("announcements", course_id)?;
+        if let Some(seen) = seen {
+            let new = announcements.iter().filter(|a| a.posted_at > seen);
+            self.log(report, new.map(|a| changes::announcement_change(started, course_id, a)).collect())?;
+        }
+        let newest = announcements.iter().map(|a| a.posted_at).chain(seen).max().unwrap_or(started);
+        self.db.set_cursor("announcements", course_id, newest)?;
+        Ok(())
+    }
+
+    /// Stored rows of a resource by ID, or `None` if it was never synced for the
+    /// course and there is nothing to compare against yet
+    fn known<T>(
+        &self,
+        resource: &str,
+        course_id: u64,
+        load: impl FnOnce(&Database) -> rusqlite::Result<Vec<T>>,
+        id: impl Fn(&T) -> u64,
+    ) -> Result<Option<HashMap<u64, T>>, SyncError> {
+        if self.db.cursor(resource, course_id)?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(load(self.db)?.into_iter().map(|row| (id(&row), row)).collect()))
+    }
+
+    fn log(&self, report: &mut SyncReport, changes: Vec<Change>) -> Result<(), SyncError> {
+        for change in &changes {
+            self.db.record_change(change)?;
+        }
+        report.changes += changes.len();
+        Ok(())
+    }
+}
+
+/// Fetch all of a course's resources concurrently.
+///
+/// Discussions stop at the first topic with no activity, and pages at the
+/// first one not updated, since their cursor in `since`.
+async fn fetch_course(client: &AsyncCanvasClient, course_id: u64, since: Since) -> Result<CourseData, (&'static str, SyncError)> {
+    let failed = |resource: &'static str| move |e: CanvasError| (resource, SyncError::from(e));
+    let active = |discussion: &Discussion| match (since.discussions, discussion.last_activity_at()) {
+        (Some(since), Some(active)) => active >= since,
+        _ => true,
+    };
+    let updated = |page: &Page| since.pages.is_none_or(|since| page.updated_at >= since);
+    // Courses can hide their pages, which Canvas reports as an authorization error
+    let pages = async {
+        match client.pages_by_update(course_id, updated).await {
+            Ok((pages, complete)) => Ok((Some(pages), complete)),
+            Err(CanvasError::Unauthorized | CanvasError::Forbidden(_) | CanvasError::NotFound(_)) => Ok((None, false)),
+            Err(e) => Err(e),
+        }
+    };
+    let (assignments, announcements, (discussions, discussions_complete), modules, (pages, pages_complete)) = tokio::try_join!(
+        client.get_assignments(course_id).map_err(failed("assignments")),
+        client.get_announcements(course_id).map_err(failed("announcements")),
+        client.discussions_by_activity(course_id, active).map_err(failed("discussions")),
+        client.get_modules_with_items(course_id).map_err(failed("modules")),
+        pages.map_err(failed("pages")),
+    )?;
+    Ok(CourseData { assignments, announcements, discussions, discussions_complete, modules, pages, pages_complete })
+}
+
+#[derive(Debug)]
+pub enum SyncError {
+    Canvas(CanvasError),
+    Database(rusqlite::Error),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::Canvas(e) => write!(f, "{}", e),
+            SyncError::Database(e) => write!(f, "local database error: {}", e),
+        }
+    }
+}
+
+impl Error for SyncError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SyncError::Canvas(e) => Some(e),
+            SyncError::Database(e) => Some(e),
+        }
+    }
+}
+
+impl From<CanvasError> for SyncError {
+    fn from(e: CanvasError) -> Self {
+        SyncError::Canvas(e)
+    }
+}
+
+impl From<rusqlite::Error> for SyncError {
+    fn from(e: rusqlite::Error) -> Self {
+        SyncError::Database(e)
+    }
+}
@@ -0,0 +1,106 @@
+use crate::client::{parse_json, CanvasClient};
+use crate::error::{CanvasError, Result};
+use crate::models::*;
+use reqwest::blocking::multipart;
+use reqwest::header::LOCATION;
+use std::fs;
+use std::path::Path;
+
+impl CanvasClient {
+    /// Upload a local file using Canvas's three-step file upload protocol.
+    ///
+    /// `endpoint` is the API path that hands out upload slots, relative to
+    /// `/api/v1/` (e.g. `courses/1/assignments/2/submissions/self/files`).
+    /// The file is announced there, posted to the returned `upload_url` with
+    /// its `upload_params`, and the upload is then confirmed with Canvas.
+    pub fn upload_file(&self, endpoint: &str, file_path: &Path) -> Result<CanvasFile> {
+        let name = file_path.file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| CanvasError::InvalidFile(format!("{} has no file name", file_path.display())))?;
+        let size = fs::metadata(file_path)?.len();
+
+        // Step 1: tell Canvas about the file and receive an upload slot
+        let resp = self.send(self.http()
+            .post(self.api_url(endpoint))
+            .json(&serde_json::json!({ "name": name, "size": size })))?;
+        let slot: UploadSlot = parse_json(resp)?;
+
+        // Step 2: post the file to the upload URL; parameters must precede the file
+        let mut form = multipart::Form::new();
+        for (key, value) in slot.upload_params {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            form = form.text(key, value);
+        }
+        form = form.file("file", file_path)?;
+
+        let resp = self.upload_http()
+            .post(&slot.upload_url)
+            .multipart(form)
+            .send()?;
+        let status = resp.status();
+
+        // Step 3: confirm the upload, either by following the redirect or from the response itself
+        if status.is_redirection() {
+            let location = resp.headers()
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| CanvasError::Api {
+                    status,
+                    message: "upload redirect without a Location header".to_string(),
+                })?
+                .to_string();
+            let resp = self.send_get(&location)?;
+            return parse_json(resp);
+        }
+        if !status.is_success() {
+            let url = resp.url().to_string();
+            let body = resp.text().unwrap_or_default();
+            return Err(CanvasError::from_response(status, &url, &body));
+        }
+        parse_json(resp)
+    }
+
+    /// Upload one or more files and submit them to an assignment.
+    ///
+    /// The assignment must accept `online_upload` submissions, and every file is
+    /// checked against its `allowed_extensions` before anything is uploaded.
+    pub fn submit_assignment<P: AsRef<Path>>(&self, course_id: u64, assignment: &Assignment, file_paths: &[P]) -> Result<Submission> {
+        assignment.check_accepts(SubmissionType::Upload)?;
+        for path in file_paths {
+            check_extension(path.as_ref(), assignment.allowed_extensions.as_deref())?;
+        }
+
+        let endpoint = format!("courses/{}/assignments/{}/submissions/self/files", course_id, assignment.id);
+        let file_ids = file_paths.iter()
+            .map(|path| self.upload_file(&endpoint, path.as_ref()).map(|file| file.id))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.create_submission(course_id, assignment.id, serde_json::json!({
+            "submission_type": SubmissionType::Upload.as_str(),
+            "file_ids": file_ids,
+        }))
+    }
+}
+
+/// Check a file against an assignment's allowed extensions (case-insensitive).
+///
+/// `None` or an empty list means any extension is accepted.
+pub fn check_extension(path: &Path, allowed: Option<&[String]>) -> Result<()> {
+    let Some(allowed) = allowed.filter(|a| !a.is_empty()) else {
+        return Ok(());
+    };
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    match extension {
+        Some(ext) if allowed.iter().any(|a| a.trim_start_matches('.').eq_ignore_ascii_case(&ext)) => Ok(()),
+        _ => Err(CanvasError::InvalidFile(format!(
+            "{} is not an allowed file type (allowed: {})",
+            path.display(),
+            allowed.join(", ")
+        ))),
+    }
+}
@@ -1,7 +1,7 @@
 use canvas_cli::client::CanvasClient;
+use canvas_cli::error::CanvasError;
 use dotenv::dotenv;
 use std::env;
-use reqwest::StatusCode;
 
 fn setup_client() -> CanvasClient {
     dotenv().ok();
@@ -10,13 +10,8 @@ fn setup_client() -> CanvasClient {
     CanvasClient::new(&base_url, &access_token)
 }
 
-fn is_forbidden(err: &Box<dyn std::error::Error>) -> bool {
-    if let Some(req_err) = err.downcast_ref::<reqwest::Error>() {
-        if let Some(status) = req_err.status() {
-            return status == StatusCode::FORBIDDEN;
-        }
-    }
-    false
+fn is_forbidden(err: &CanvasError) -> bool {
+    matches!(err, CanvasError::Forbidden(_))
 }
 
 #[test]
@@ -1,5 +1,7 @@
-use canvas_cli::db::Database;
+use canvas_cli::db::{content_hash, CacheScope, Database, SyncOutcome};
+use canvas_cli::lock::{lock_path, DatabaseLock, LockError};
 use canvas_cli::models::*;
+use canvas_cli::search::DocumentKind;
 use chrono::{Duration, Utc};
 use rusqlite::params;
 use tempfile::tempdir;
@@ -7,7 +9,8 @@ use tempfile::tempdir;
 fn create_test_db() -> (Database, tempfile::TempDir) {
     let temp_dir = tempdir().unwrap();
     let db_path = temp_dir.path().join("test.db");
-    let db = Database::new(&db_path).unwrap();
+    let db = Database::open_unmigrated(&db_path).unwrap();
+    db.migrate().unwrap();
     (db, temp_dir)
 }
 
@@ -20,6 +23,7 @@ fn create_test_course() -> Course {
         total_students: Some(30),
         start_at: Some(Utc::now()),
         end_at: Some(Utc::now() + Duration::days(90)),
+        deleted_at: None,
     }
 }
 
@@ -33,6 +37,9 @@ fn create_test_assignment() -> Assignment {
         html_url: "http://test.com/assignment".to_string(),
         submission_types: vec!["online_upload".to_string()],
         allowed_extensions: Some(vec!["pdf".to_string()]),
+        submission: None,
+        rubric: None,
+        deleted_at: None,
     }
 }
 
@@ -43,6 +50,8 @@ fn create_test_discussion() -> Discussion {
         message: "Test Message".to_string(),
         html_url: "http://test.com/discussion".to_string(),
         posted_at: Some(Utc::now()),
+        last_reply_at: None,
+        deleted_at: None,
         allow_rating: true,
         user_can_see_posts: true,
         discussion_type: "threaded".to_string(),
@@ -66,6 +75,8 @@ fn create_test_module() -> Module {
         completed_at: None,
         items_count: 0,
         items_url: "http://test.com/items".to_string(),
+        items: None,
+        deleted_at: None,
     }
 }
 
@@ -81,6 +92,7 @@ fn create_test_module_item(module_id: u64) -> ModuleItem {
         content_id: Some(1),
         external_url: None,
         completion_requirement: None,
+        deleted_at: None,
     }
 }
 
@@ -96,6 +108,7 @@ fn test_database_initialization() {
     assert!(tables.contains(&"discussions".to_string()));
     assert!(tables.contains(&"modules".to_string()));
     assert!(tables.contains(&"module_items".to_string()));
+    assert!(tables.contains(&"pages".to_string()));
 }
 
 #[test]
@@ -114,6 +127,18 @@ fn test_sync_course() {
     assert_eq!(saved_name, course.name.unwrap());
 }
 
+#[test]
+fn test_get_courses() {
+    let (db, _temp) = create_test_db();
+    let course = create_test_course();
+    db.sync_course(&course).unwrap();
+
+    let courses = db.get_courses(false).unwrap();
+    assert_eq!(courses.len(), 1);
+    assert_eq!(courses[0].course_code, course.course_code);
+    assert_eq!(courses[0].end_at.map(|dt| dt.timestamp()), course.end_at.map(|dt| dt.timestamp()));
+}
+
 #[test]
 fn test_sync_assignment() {
     let (db, _temp) = create_test_db();
@@ -150,26 +175,6 @@ fn test_sync_discussion() {
     assert_eq!(saved_title, discussion.title);
 }
 
-#[test]
-fn test_get_stale_courses() {
-    let (db, _temp) = create_test_db();
-    let course = create_test_course();
-    
-    db.sync_course(&course).unwrap();
-    
-    db.get_connection().execute(
-        "UPDATE courses SET last_sync = datetime('now', '-7 hours') WHERE id = ?1",
-        params![course.id]
-    ).unwrap();
-    
-    let stale_courses = db.get_stale_courses(6).unwrap();
-    assert!(stale_courses.contains(&course.id));
-    
-    db.sync_course(&course).unwrap();
-    let fresh_courses = db.get_stale_courses(6).unwrap();
-    assert!(!fresh_courses.contains(&course.id));
-}
-
 #[test]
 fn test_sync_module() {
     let (db, _temp) = create_test_db();
@@ -208,15 +213,255 @@ fn test_sync_module_item() {
     assert_eq!(saved_title, item.title);
 }
 
+fn create_test_page() -> Page {
+    Page {
+        page_id: 42,
+        url: "office-hours".to_string(),
+        title: "Office Hours".to_string(),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        editing_roles: "teachers".to_string(),
+        published: true,
+        front_page: false,
+        html_url: "https://canvas.test/courses/1/pages/office-hours".to_string(),
+        body: Some("<p>Mondays at <strong>10:00</strong></p>".to_string()),
+        deleted_at: None,
+    }
+}
+
 #[test]
-fn test_sync_status() {
+fn test_sync_page() {
     let (db, _temp) = create_test_db();
-    
-    assert!(db.update_sync_status("courses").is_ok());
-    
-    let status = db.get_sync_status().unwrap();
-    assert!(!status.is_empty());
-    assert_eq!(status[0].0, "courses");
+    let course = create_test_course();
+    db.sync_course(&course).unwrap();
+
+    let page = create_test_page();
+    assert_eq!(db.sync_page(course.id, &page).unwrap(), SyncOutcome::Inserted);
+    assert_eq!(db.sync_page(course.id, &page).unwrap(), SyncOutcome::Unchanged);
+
+    // Found by slug or ID, with the body kept for reading offline
+    let cached = db.get_page(course.id, "office-hours", false).unwrap().unwrap();
+    assert_eq!(cached.body, page.body);
+    assert_eq!(db.get_page(course.id, "42", false).unwrap().unwrap().title, "Office Hours");
+    assert!(db.get_page(course.id, "missing", false).unwrap().is_none());
+    assert!(db.get_page(999, "office-hours", false).unwrap().is_none());
+    assert_eq!(db.search("mondays", None, false, 10).unwrap().unwrap()[0].kind, DocumentKind::Page);
+
+    assert_eq!(db.mark_deleted(CacheScope::Pages(course.id), &[]).unwrap(), vec![42]);
+    assert!(db.get_pages(course.id, false).unwrap().is_empty());
+    assert!(db.get_page(course.id, "office-hours", true).unwrap().unwrap().deleted_at.is_some());
+    assert!(db.search("mondays", None, false, 10).unwrap().unwrap().is_empty());
+}
+
+#[test]
+fn test_read_cached_course_content() {
+    let (db, _temp) = create_test_db();
+    let course = create_test_course();
+    let module = create_test_module();
+
+    db.sync_course(&course).unwrap();
+    db.sync_assignment(course.id, &create_test_assignment()).unwrap();
+    db.sync_discussion(course.id, &create_test_discussion()).unwrap();
+    db.sync_module(course.id, &module).unwrap();
+    db.sync_module_item(module.id, &create_test_module_item(module.id)).unwrap();
+
+    let assignments = db.get_assignments(course.id, false).unwrap();
+    assert_eq!(assignments[0].name, "Test Assignment");
+    assert_eq!(assignments[0].points_possible, Some(100.0));
+    assert!(db.get_assignments(999, false).unwrap().is_empty());
+
+    let discussions = db.get_discussions(course.id, false).unwrap();
+    assert!(discussions[0].pinned);
+
+    // The item count comes from the cached items, not the synced module
+    let modules = db.get_modules(course.id, false).unwrap();
+    assert_eq!(modules[0].items_count, 1);
+
+    let items = db.get_module_items(module.id, false).unwrap();
+    assert_eq!(items[0].item_type, "Assignment");
+}
+
+#[test]
+fn test_synced_at() {
+    let (db, _temp) = create_test_db();
+    let course = create_test_course();
+
+    assert_eq!(db.synced_at(CacheScope::Courses).unwrap(), None);
+    db.sync_course(&course).unwrap();
+    db.sync_assignment(course.id, &create_test_assignment()).unwrap();
+    db.get_connection().execute(
+        "UPDATE assignments SET last_sync = ?1",
+        params!["2024-01-01T00:00:00+00:00"]
+    ).unwrap();
+
+    let synced = db.synced_at(CacheScope::Assignments(course.id)).unwrap().unwrap();
+    assert_eq!(synced.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+    assert!(db.synced_at(CacheScope::Courses).unwrap().unwrap() > synced);
+    assert_eq!(db.synced_at(CacheScope::Modules(course.id)).unwrap(), None);
+}
+
+#[test]
+fn test_unchanged_rows_are_not_rewritten() {
+    let (db, _temp) = create_test_db();
+    let mut course = create_test_course();
+
+    assert_eq!(db.sync_course(&course).unwrap(), SyncOutcome::Inserted);
+    assert_eq!(db.sync_course(&course).unwrap(), SyncOutcome::Unchanged);
+    course.name = Some("Renamed Course".to_string());
+    assert_eq!(db.sync_course(&course).unwrap(), SyncOutcome::Updated);
+    assert_eq!(db.get_courses(false).unwrap()[0].name.as_deref(), Some("Renamed Course"));
+
+    // The same assignment in another course is different content
+    db.sync_course(&Course { id: 2, ..create_test_course() }).unwrap();
+    let assignment = create_test_assignment();
+    assert_eq!(db.sync_assignment(1, &assignment).unwrap(), SyncOutcome::Inserted);
+    assert_eq!(db.sync_assignment(2, &assignment).unwrap(), SyncOutcome::Updated);
+}
+
+#[test]
+fn test_content_hash_is_stable() {
+    // Stored hashes must keep matching across releases
+    assert_eq!(content_hash("a"), "d4272417d7c77eea");
+    assert_ne!(content_hash(&create_test_assignment()), content_hash(&create_test_discussion()));
+}
+
+#[test]
+fn test_sync_cursors() {
+    let (db, _temp) = create_test_db();
+    let course = create_test_course();
+    db.sync_course(&course).unwrap();
+    db.sync_module(course.id, &create_test_module()).unwrap();
+
+    assert_eq!(db.cursor("modules", course.id).unwrap(), None);
+    let at = Utc::now() - Duration::days(3);
+    db.set_cursor("modules", course.id, at).unwrap();
+    assert_eq!(db.cursor("modules", course.id).unwrap().map(|dt| dt.timestamp()), Some(at.timestamp()));
+    assert_eq!(db.cursor("modules", 999).unwrap(), None);
+
+    // Freshness comes from the cursor, for modules and their items alike
+    let synced = db.synced_at(CacheScope::ModuleItems(create_test_module().id)).unwrap().unwrap();
+    assert_eq!(synced.timestamp(), at.timestamp());
+}
+
+#[test]
+fn test_mark_deleted() {
+    let (db, _temp) = create_test_db();
+    let course = create_test_course();
+    db.sync_course(&course).unwrap();
+    let assignment = create_test_assignment();
+    let other = Assignment { id: assignment.id + 1, ..create_test_assignment() };
+    db.sync_assignment(course.id, &assignment).unwrap();
+    db.sync_assignment(course.id, &other).unwrap();
+
+    let gone = db.mark_deleted(CacheScope::Assignments(course.id), &[other.id]).unwrap();
+    assert_eq!(gone, vec![assignment.id]);
+    // Already tombstoned rows aren't reported twice
+    assert!(db.mark_deleted(CacheScope::Assignments(course.id), &[other.id]).unwrap().is_empty());
+
+    let live = db.get_assignments(course.id, false).unwrap();
+    assert_eq!(live.iter().map(|a| a.id).collect::<Vec<_>>(), vec![other.id]);
+    assert_eq!(db.get_assignments(course.id, true).unwrap().len(), 2);
+
+    // Syncing the same content again restores the row
+    assert_eq!(db.sync_assignment(course.id, &assignment).unwrap(), SyncOutcome::Updated);
+    assert_eq!(db.get_assignments(course.id, false).unwrap().len(), 2);
+}
+
+#[test]
+fn test_transaction_rolls_back_when_dropped() {
+    let (db, _temp) = create_test_db();
+    let course = create_test_course();
+
+    {
+        let _tx = db.transaction().unwrap();
+        db.sync_course(&course).unwrap();
+        db.sync_assignment(course.id, &create_test_assignment()).unwrap();
+    }
+    assert!(db.get_courses(true).unwrap().is_empty());
+
+    let tx = db.transaction().unwrap();
+    db.sync_course(&course).unwrap();
+    tx.commit().unwrap();
+    assert_eq!(db.get_courses(false).unwrap().len(), 1);
+}
+
+#[test]
+fn test_search() {
+    let (db, _temp) = create_test_db();
+    let course = create_test_course();
+    db.sync_course(&course).unwrap();
+    let lists = Assignment {
+        id: 10,
+        name: "Project 2".to_string(),
+        description: Some("<p>Implement <strong>linked lists</strong> &amp; iterators.</p>".to_string()),
+        ..create_test_assignment()
+    };
+    db.sync_assignment(course.id, &lists).unwrap();
+    db.sync_assignment(course.id, &create_test_assignment()).unwrap();
+    let discussion = Discussion { title: "Linked list questions".to_string(), ..create_test_discussion() };
+    db.sync_discussion(course.id, &discussion).unwrap();
+    let module = create_test_module();
+    db.sync_module(course.id, &module).unwrap();
+    db.sync_module_item(module.id, &create_test_module_item(module.id)).unwrap();
+    db.index_announcement(course.id, &Announcement {
+        id: 5,
+        title: "Office hours".to_string(),
+        message: "<p>Bring your iterator questions</p>".to_string(),
+        posted_at: Utc::now(),
+        url: "http://test.com/announcement".to_string(),
+    }).unwrap();
+
+    let hits = db.search("linked lists", None, false, 10).unwrap().unwrap();
+    let found: Vec<_> = hits.iter().map(|h| (h.kind, h.resource_id)).collect();
+    // Stemming matches "list" too, and a title match ranks first
+    assert_eq!(found, vec![(DocumentKind::Discussion, discussion.id), (DocumentKind::Assignment, lists.id)]);
+    assert_eq!(hits[1].course_code.as_deref(), Some("TEST101"));
+    assert_eq!(hits[1].snippet, "Implement **linked** **lists** & iterators.");
+
+    let hits = db.search("iterators", None, false, 10).unwrap().unwrap();
+    assert_eq!(hits.iter().map(|h| h.kind).collect::<Vec<_>>(), vec![DocumentKind::Assignment, DocumentKind::Announcement]);
+    let hits = db.search("test item", None, false, 10).unwrap().unwrap();
+    assert_eq!(hits.iter().map(|h| h.kind).collect::<Vec<_>>(), vec![DocumentKind::ModuleItem]);
+    assert!(db.search("iterators", Some(course.id + 1), false, 10).unwrap().unwrap().is_empty());
+    assert!(db.search("  ", None, false, 10).unwrap().is_none());
+
+    // Edits replace the indexed text, and tombstoned rows drop out unless asked for
+    db.sync_assignment(course.id, &Assignment { id: lists.id, description: Some("Hash maps".to_string()), ..create_test_assignment() }).unwrap();
+    assert_eq!(db.search("hash", None, false, 10).unwrap().unwrap().len(), 1);
+    assert!(db.search("lists", None, false, 10).unwrap().unwrap().iter().all(|h| h.kind != DocumentKind::Assignment));
+    db.mark_deleted(CacheScope::Discussions(course.id), &[]).unwrap();
+    assert!(db.search("linked", None, false, 10).unwrap().unwrap().is_empty());
+    let hits = db.search("linked", None, true, 10).unwrap().unwrap();
+    assert!(hits[0].deleted_at.is_some());
+    db.sync_discussion(course.id, &discussion).unwrap();
+    assert_eq!(db.search("linked", None, false, 10).unwrap().unwrap().len(), 1);
+
+    // Announcements have no rows of their own but are tombstoned in the index
+    assert!(db.mark_announcements_deleted(course.id, &[5]).unwrap().is_empty());
+    assert_eq!(db.mark_announcements_deleted(course.id, &[]).unwrap(), vec![5]);
+    assert!(db.search("iterator questions", None, false, 10).unwrap().unwrap().is_empty());
+    assert!(db.search("iterator questions", None, true, 10).unwrap().unwrap()[0].deleted_at.is_some());
+
+    // Nothing from a deleted course turns up
+    db.mark_deleted(CacheScope::Courses, &[]).unwrap();
+    assert!(db.search("linked", None, false, 10).unwrap().unwrap().is_empty());
+    assert_eq!(db.search("linked", None, true, 10).unwrap().unwrap().len(), 1);
+}
+
+#[test]
+fn test_database_lock() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("data").join("canvas.db");
+
+    let lock = DatabaseLock::acquire(&path).unwrap();
+    assert!(lock_path(&path).exists());
+    match DatabaseLock::acquire(&path) {
+        Err(LockError::Busy(busy)) => assert_eq!(busy, path),
+        other => panic!("Expected the database to be busy, got {:?}", other),
+    }
+
+    drop(lock);
+    DatabaseLock::acquire(&path).unwrap();
 }
 
 #[test]
@@ -25,6 +25,42 @@ pub const ASSIGNMENTS_RESPONSE: &str = r#"[
     }
 ]"#;
 
+pub const ASSIGNMENTS_WITH_SUBMISSION_RESPONSE: &str = r#"[
+    {
+        "id": 4321,
+        "name": "Test Assignment",
+        "description": "Test Description",
+        "due_at": "2024-02-01T23:59:59Z",
+        "points_possible": 100.0,
+        "html_url": "https://canvas.test/assignments/4321",
+        "submission_types": ["online_upload"],
+        "allowed_extensions": ["pdf", "doc"],
+        "submission": {
+            "id": 555,
+            "assignment_id": 4321,
+            "score": null,
+            "submitted_at": "2024-02-01T20:00:00Z",
+            "late": false,
+            "missing": false,
+            "grade": null,
+            "workflow_state": "submitted"
+        }
+    }
+]"#;
+
+pub const ASSIGNMENTS_PAGE_2_RESPONSE: &str = r#"[
+    {
+        "id": 4322,
+        "name": "Second Assignment",
+        "description": null,
+        "due_at": null,
+        "points_possible": 50.0,
+        "html_url": "https://canvas.test/assignments/4322",
+        "submission_types": ["online_text_entry"],
+        "allowed_extensions": null
+    }
+]"#;
+
 pub const FILES_RESPONSE: &str = r#"[
     {
         "id": 5678,
@@ -38,6 +74,198 @@ pub const FILES_RESPONSE: &str = r#"[
     }
 ]"#;
 
+pub const FOLDERS_RESPONSE: &str = r#"[
+    {
+        "id": 10,
+        "name": "course files",
+        "full_name": "course files",
+        "parent_folder_id": null,
+        "files_count": 1,
+        "folders_count": 1,
+        "updated_at": "2024-01-01T00:00:00Z"
+    },
+    {
+        "id": 11,
+        "name": "Week 1",
+        "full_name": "course files/Week 1",
+        "parent_folder_id": 10,
+        "files_count": 2,
+        "folders_count": 0,
+        "updated_at": "2024-01-02T00:00:00Z"
+    }
+]"#;
+
+pub const COURSE_FILES_RESPONSE: &str = r#"[
+    {
+        "id": 5678,
+        "display_name": "syllabus.pdf",
+        "filename": "syllabus.pdf",
+        "content-type": "application/pdf",
+        "url": "https://canvas.test/files/5678/download",
+        "size": 10,
+        "folder_id": 10,
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z"
+    },
+    {
+        "id": 5679,
+        "display_name": "slides.pdf",
+        "filename": "slides.pdf",
+        "content-type": "application/pdf",
+        "url": "https://canvas.test/files/5679/download",
+        "size": 6,
+        "folder_id": 11,
+        "created_at": "2024-01-02T00:00:00Z",
+        "updated_at": "2024-01-02T00:00:00Z"
+    },
+    {
+        "id": 5680,
+        "display_name": "answers.pdf",
+        "filename": "answers.pdf",
+        "content-type": "application/pdf",
+        "url": "",
+        "size": 4,
+        "folder_id": 11,
+        "locked_for_user": true,
+        "created_at": "2024-01-02T00:00:00Z",
+        "updated_at": "2024-01-02T00:00:00Z"
+    }
+]"#;
+
+pub const ASSIGNMENT_RESPONSE: &str = r#"{
+    "id": 4321,
+    "name": "Test Assignment",
+    "description": "Test Description",
+    "due_at": "2024-02-01T23:59:59Z",
+    "points_possible": 100.0,
+    "html_url": "https://canvas.test/assignments/4321",
+    "submission_types": ["online_upload"],
+    "allowed_extensions": ["pdf", "doc"]
+}"#;
+
+pub const TEXT_ASSIGNMENT_RESPONSE: &str = r#"{
+    "id": 4322,
+    "name": "Reflection",
+    "description": null,
+    "due_at": null,
+    "points_possible": 10.0,
+    "html_url": "https://canvas.test/assignments/4322",
+    "submission_types": ["online_text_entry"],
+    "allowed_extensions": null
+}"#;
+
+pub const FILE_RESPONSE: &str = r#"{
+    "id": 5678,
+    "display_name": "test.pdf",
+    "filename": "test.pdf",
+    "content_type": "application/pdf",
+    "url": "https://canvas.test/files/5678/download",
+    "size": 1024,
+    "created_at": "2024-01-01T00:00:00Z",
+    "updated_at": "2024-01-01T00:00:00Z"
+}"#;
+
+pub const SUBMISSION_RESPONSE: &str = r#"{
+    "id": 9876,
+    "assignment_id": 4321,
+    "score": null,
+    "submitted_at": "2024-01-15T12:00:00Z",
+    "late": false,
+    "missing": false,
+    "grade": null
+}"#;
+
+pub const ASSIGNMENT_WITH_RUBRIC_RESPONSE: &str = r#"{
+    "id": 4321,
+    "name": "Test Assignment",
+    "description": "Test Description",
+    "due_at": "2024-02-01T23:59:59Z",
+    "points_possible": 10.0,
+    "html_url": "https://canvas.test/assignments/4321",
+    "submission_types": ["online_upload"],
+    "allowed_extensions": ["pdf"],
+    "rubric": [
+        {
+            "id": "_100",
+            "description": "Argument",
+            "long_description": "",
+            "points": 6.0,
+            "ratings": [
+                {"id": "r1", "description": "Convincing", "points": 6.0},
+                {"id": "r2", "description": "Unclear", "points": 3.0}
+            ]
+        },
+        {
+            "id": "_200",
+            "description": "Sources",
+            "points": 4.0,
+            "ratings": [
+                {"id": "r3", "description": "Cited", "points": 4.0},
+                {"id": "r4", "description": "Missing", "points": 0.0}
+            ]
+        }
+    ]
+}"#;
+
+pub const SUBMISSION_DETAILS_RESPONSE: &str = r#"{
+    "id": 9876,
+    "assignment_id": 4321,
+    "score": 7.5,
+    "grade": "7.5",
+    "submitted_at": "2024-02-02T01:59:59Z",
+    "graded_at": "2024-02-05T09:00:00Z",
+    "workflow_state": "graded",
+    "submission_type": "online_upload",
+    "attempt": 2,
+    "late": true,
+    "missing": false,
+    "seconds_late": 7200,
+    "excused": false,
+    "attachments": [{"id": 600, "display_name": "essay-v2.pdf", "url": "https://canvas.test/files/600/download", "size": 2048}],
+    "submission_history": [
+        {
+            "id": 9876,
+            "assignment_id": 4321,
+            "score": 7.5,
+            "grade": "7.5",
+            "submitted_at": "2024-02-02T01:59:59Z",
+            "workflow_state": "graded",
+            "submission_type": "online_upload",
+            "attempt": 2,
+            "late": true,
+            "missing": false
+        },
+        {
+            "id": 9876,
+            "assignment_id": 4321,
+            "score": null,
+            "grade": null,
+            "submitted_at": "2024-01-30T10:00:00Z",
+            "workflow_state": "submitted",
+            "submission_type": "online_upload",
+            "attempt": 1,
+            "late": false,
+            "missing": false
+        }
+    ],
+    "submission_comments": [
+        {
+            "id": 31,
+            "author_id": 77,
+            "author_name": "Dr. Smith",
+            "comment": "Good argument, but cite your sources.",
+            "created_at": "2024-02-05T09:00:00Z",
+            "attempt": 2,
+            "attachments": [{"id": 701, "display_name": "feedback.pdf", "url": "https://canvas.test/files/701/download"}]
+        }
+    ],
+    "rubric_assessment": {
+        "_200": {"points": 1.5, "rating_id": null, "comments": "Only one source"},
+        "_100": {"points": 6.0, "rating_id": "r1", "comments": ""},
+        "_999": {"points": 0.0, "comments": null}
+    }
+}"#;
+
 pub const ANNOUNCEMENTS_RESPONSE: &str = r#"[
     {
         "id": 8765,
@@ -48,6 +276,16 @@ pub const ANNOUNCEMENTS_RESPONSE: &str = r#"[
     }
 ]"#;
 
+pub const ANNOUNCEMENT_RESPONSE: &str = r#"{
+    "id": 8765,
+    "title": "Test Announcement",
+    "message": "<p>Slides are up: <a class=\"instructure_file_link\" title=\"week1.pdf\" href=\"/courses/1/files/5678/download?wrap=1\">Week 1</a></p>",
+    "posted_at": "2024-01-01T00:00:00Z",
+    "url": "https://canvas.test/courses/1/discussion_topics/8765",
+    "html_url": "https://canvas.test/courses/1/discussion_topics/8765",
+    "is_announcement": true
+}"#;
+
 pub const MODULES_RESPONSE: &str = r#"[
     {
         "id": 1234,
@@ -81,16 +319,134 @@ pub const DISCUSSIONS_RESPONSE: &str = r#"[
     }
 ]"#;
 
+pub const DISCUSSION_RESPONSE: &str = r#"{
+    "id": 4321,
+    "title": "Test Discussion",
+    "message": "<p>Discussion <strong>content</strong></p>",
+    "html_url": "https://canvas.test/courses/1/discussion_topics/4321",
+    "posted_at": "2024-01-01T00:00:00Z",
+    "allow_rating": true,
+    "user_can_see_posts": true,
+    "discussion_type": "threaded",
+    "discussion_subentry_count": 5,
+    "published": true,
+    "locked": false,
+    "pinned": false
+}"#;
+
 pub const PAGES_RESPONSE: &str = r#"[
     {
-        "page_id": "test-page",
+        "page_id": 42,
         "url": "test-page",
         "title": "Test Page",
         "created_at": "2024-01-01T00:00:00Z",
         "updated_at": "2024-01-01T00:00:00Z",
         "editing_roles": "teachers",
         "published": true,
+        "front_page": true,
         "html_url": "https://canvas.test/courses/1/pages/test-page",
-        "body": "Page content"
+        "body": "<h2>Welcome</h2><p>Read the <a href=\"https://canvas.test/courses/1/files/5678\">syllabus</a> first.</p>"
+    },
+    {
+        "page_id": 43,
+        "url": "office-hours",
+        "title": "Office Hours",
+        "created_at": "2024-01-02T00:00:00Z",
+        "updated_at": "2024-01-03T00:00:00Z",
+        "editing_roles": "teachers",
+        "published": true,
+        "html_url": "https://canvas.test/courses/1/pages/office-hours",
+        "body": "<table><tr><th>Day</th><th>Time</th></tr><tr><td>Monday</td><td>10:00</td></tr></table>"
+    }
+]"#;
+
+pub const PAGE_RESPONSE: &str = r#"{
+    "page_id": 42,
+    "url": "test-page",
+    "title": "Test Page",
+    "created_at": "2024-01-01T00:00:00Z",
+    "updated_at": "2024-01-01T00:00:00Z",
+    "editing_roles": "teachers",
+    "published": true,
+    "front_page": true,
+    "html_url": "https://canvas.test/courses/1/pages/test-page",
+    "body": "<h2>Welcome</h2><p>Read the <a href=\"https://canvas.test/courses/1/files/5678\">syllabus</a> first.</p>"
+}"#;
+
+pub const MODULE_ITEMS_RESPONSE: &str = r#"[
+    {
+        "id": 555,
+        "module_id": 1234,
+        "position": 1,
+        "title": "Read Chapter 1",
+        "indent": 0,
+        "type": "Page",
+        "html_url": "https://canvas.test/courses/1/modules/items/555",
+        "content_id": null,
+        "external_url": null,
+        "completion_requirement": null
+    }
+]"#;
+
+pub const MODULES_WITH_ITEMS_RESPONSE: &str = r#"[
+    {
+        "id": 1235,
+        "name": "Week 2",
+        "position": 2,
+        "unlock_at": null,
+        "require_sequential_progress": false,
+        "publish_final_grade": false,
+        "prerequisite_module_ids": null,
+        "state": "active",
+        "completed_at": null,
+        "items_count": 1,
+        "items_url": "https://canvas.test/api/v1/courses/1/modules/1235/items",
+        "items": [
+            {
+                "id": 556,
+                "module_id": 1235,
+                "position": 1,
+                "title": "Quiz 2",
+                "indent": 0,
+                "type": "Quiz",
+                "html_url": "https://canvas.test/courses/1/modules/items/556",
+                "content_id": 77,
+                "external_url": null,
+                "completion_requirement": null
+            }
+        ]
+    }
+]"#;
+
+/// Topics ordered by recent activity: one still active, one quiet since 2024
+pub const DISCUSSIONS_BY_ACTIVITY_RESPONSE: &str = r#"[
+    {
+        "id": 4322,
+        "title": "Ongoing Discussion",
+        "message": "Still going",
+        "html_url": "https://canvas.test/courses/1/discussion_topics/4322",
+        "posted_at": "2024-01-01T00:00:00Z",
+        "last_reply_at": "2999-01-01T00:00:00Z",
+        "allow_rating": false,
+        "user_can_see_posts": true,
+        "discussion_type": "threaded",
+        "discussion_subentry_count": 40,
+        "published": true,
+        "locked": false,
+        "pinned": false
+    },
+    {
+        "id": 4321,
+        "title": "Test Discussion",
+        "message": "Discussion content",
+        "html_url": "https://canvas.test/courses/1/discussion_topics/4321",
+        "posted_at": "2024-01-01T00:00:00Z",
+        "allow_rating": true,
+        "user_can_see_posts": true,
+        "discussion_type": "threaded",
+        "discussion_subentry_count": 5,
+        "published": true,
+        "locked": false,
+        "pinned": false
     }
 ]"#;
@@ -0,0 +1,71 @@
+use canvas_cli::async_client::AsyncCanvasClient;
+use canvas_cli::models::Discussion;
+use canvas_cli::retry::RetryConfig;
+use mockito::Server;
+
+mod mocks;
+use mocks::responses::*;
+
+#[tokio::test]
+async fn test_get_while_stops_early() {
+    let mut server = Server::new_async().await;
+    let next = format!("<{}/api/v1/courses/1234/discussion_topics?order_by=recent_activity&page=2>; rel=\"next\"", server.url());
+    let _first = server.mock("GET", "/api/v1/courses/1234/discussion_topics?order_by=recent_activity")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_header("link", &next)
+        .with_body(DISCUSSIONS_BY_ACTIVITY_RESPONSE)
+        .create_async()
+        .await;
+    let second = server.mock("GET", "/api/v1/courses/1234/discussion_topics?order_by=recent_activity&page=2")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("[]")
+        .expect(0)
+        .create_async()
+        .await;
+
+    let client = AsyncCanvasClient::new(&server.url(), "fake-token");
+    let (topics, complete) = client.discussions_by_activity(1234, |d: &Discussion| d.id != 4321).await.unwrap();
+
+    assert_eq!(topics.iter().map(|d| d.id).collect::<Vec<_>>(), vec![4322]);
+    assert!(!complete);
+    second.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_modules_without_inline_items() {
+    let mut server = Server::new_async().await;
+    let _modules = server.mock("GET", "/api/v1/courses/1234/modules?include[]=items")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(MODULES_RESPONSE)
+        .create_async()
+        .await;
+    let _items = server.mock("GET", "/api/v1/courses/1234/modules/1234/items")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(MODULE_ITEMS_RESPONSE)
+        .create_async()
+        .await;
+
+    let client = AsyncCanvasClient::new(&server.url(), "fake-token").with_max_concurrency(2);
+    let modules = client.get_modules_with_items(1234).await.unwrap();
+
+    assert_eq!(modules.len(), 1);
+    assert_eq!(modules[0].items.as_ref().map(Vec::len), Some(1));
+}
+
+#[tokio::test]
+async fn test_errors_are_not_retried_when_disabled() {
+    let mut server = Server::new_async().await;
+    let mock = server.mock("GET", "/api/v1/courses")
+        .with_status(503)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = AsyncCanvasClient::new(&server.url(), "fake-token").with_retry_config(RetryConfig::disabled());
+    assert!(client.get_courses().await.is_err());
+    mock.assert_async().await;
+}
@@ -0,0 +1,59 @@
+use canvas_cli::changes::{assignment_changes, parse_age, parse_since, ChangeKind};
+use canvas_cli::models::Assignment;
+use chrono::{Duration, TimeZone, Utc};
+
+mod mocks;
+use mocks::responses::*;
+
+fn assignment() -> Assignment {
+    let mut assignments: Vec<Assignment> = serde_json::from_str(ASSIGNMENTS_RESPONSE).unwrap();
+    assignments.remove(0)
+}
+
+#[test]
+fn test_new_and_unchanged_assignments() {
+    let now = Utc::now();
+    let changes = assignment_changes(now, 1234, None, &assignment());
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].kind, ChangeKind::NewAssignment);
+    assert_eq!(changes[0].detail.as_deref(), Some("due 2024-02-01 23:59 UTC"));
+
+    assert!(assignment_changes(now, 1234, Some(&assignment()), &assignment()).is_empty());
+
+    let undated = Assignment { due_at: None, ..assignment() };
+    let changes = assignment_changes(now, 1234, Some(&assignment()), &undated);
+    assert_eq!(changes[0].kind, ChangeKind::DueDateChanged);
+    assert_eq!(changes[0].detail.as_deref(), Some("2024-02-01 23:59 UTC -> no due date"));
+}
+
+#[test]
+fn test_change_kinds_round_trip() {
+    for kind in [ChangeKind::NewAssignment, ChangeKind::NewReply, ChangeKind::ModuleUnlocked] {
+        assert_eq!(kind.as_str().parse::<ChangeKind>(), Ok(kind));
+        assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+    }
+    assert!("renamed".parse::<ChangeKind>().is_err());
+}
+
+#[test]
+fn test_parse_since() {
+    let now = Utc.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap();
+    assert_eq!(parse_since("12h", now), Ok(now - Duration::hours(12)));
+    assert_eq!(parse_since("3d", now), Ok(now - Duration::days(3)));
+    assert_eq!(parse_since("2w", now), Ok(now - Duration::weeks(2)));
+    assert_eq!(parse_since("2024-03-01", now), Ok(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap()));
+    assert_eq!(
+        parse_since("2024-03-01T09:30:00+01:00", now),
+        Ok(Utc.with_ymd_and_hms(2024, 3, 1, 8, 30, 0).unwrap()),
+    );
+    assert!(parse_since("yesterday", now).is_err());
+    assert!(parse_since("3y", now).is_err());
+    assert!(parse_since("h", now).is_err());
+    // Too long ago to represent, rather than a panic
+    assert!(parse_since("99999999999999w", now).is_err());
+    assert!(parse_since("99999999w", now).is_err());
+
+    assert_eq!(parse_age("30m"), Ok(Duration::minutes(30)));
+    assert!(parse_age("2024-03-01").is_err());
+    assert!(parse_age("99999999999999w").is_err());
+}
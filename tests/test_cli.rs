@@ -1,9 +1,11 @@
-use canvas_cli::cli::{Cli, Commands};
-use clap::Parser;
+use canvas_cli::cli::{AssignmentArgs, Cli, Commands, ExportCommand};
+use canvas_cli::compose::TextSource;
+use canvas_cli::models::SubmissionType;
+use clap::{CommandFactory, Parser};
 
 #[test]
 fn test_courses_command() {
-    let cli = Cli::try_parse_from(&["canvas-cli", "courses"]).unwrap();
+    let cli = Cli::try_parse_from(["canvas-cli", "courses"]).unwrap();
     match cli.command {
         Commands::Courses => (),
         _ => panic!("Expected Courses command"),
@@ -12,28 +14,249 @@ fn test_courses_command() {
 
 #[test]
 fn test_course_command() {
-    let cli = Cli::try_parse_from(&["canvas-cli", "course", "1234"]).unwrap();
+    let cli = Cli::try_parse_from(["canvas-cli", "course", "1234"]).unwrap();
     match cli.command {
-        Commands::Course(args) => assert_eq!(args.course_id, 1234),
+        Commands::Course(args) => assert_eq!(args.course, "1234"),
         _ => panic!("Expected Course command"),
     }
 }
 
 #[test]
 fn test_assignment_command() {
-    let cli = Cli::try_parse_from(&[
+    let cli = Cli::try_parse_from([
         "canvas-cli", "assignment",
-        "1234",  // course_id
+        "1234",  // course
         "4321",  // assignment_id
-        "--file-path", "test.pdf"
+        "--file-path", "test.pdf",
+        "--file-path", "notes.txt"
     ]).unwrap();
     
     match cli.command {
         Commands::Assignment(args) => {
-            assert_eq!(args.course_id, 1234);
+            assert_eq!(args.course, "1234");
             assert_eq!(args.assignment_id, 4321);
-            assert_eq!(args.file_path, Some("test.pdf".to_string()));
+            assert_eq!(args.file_paths, vec!["test.pdf".to_string(), "notes.txt".to_string()]);
         },
         _ => panic!("Expected Assignment command"),
     }
 }
+
+fn parse_assignment(extra: &[&str]) -> Result<AssignmentArgs, clap::Error> {
+    let mut argv = vec!["canvas-cli", "assignment", "1234", "4321"];
+    argv.extend_from_slice(extra);
+    match Cli::try_parse_from(argv)?.command {
+        Commands::Assignment(args) => Ok(args),
+        _ => panic!("Expected Assignment command"),
+    }
+}
+
+#[test]
+fn test_submission_type_is_inferred() {
+    let args = parse_assignment(&[]).unwrap();
+    assert_eq!(args.requested_submission_type(), Ok(None));
+
+    let args = parse_assignment(&["--file-path", "a.pdf"]).unwrap();
+    assert_eq!(args.requested_submission_type(), Ok(Some(SubmissionType::Upload)));
+
+    let args = parse_assignment(&["--text", "essay.md"]).unwrap();
+    assert_eq!(args.requested_submission_type(), Ok(Some(SubmissionType::TextEntry)));
+    assert_eq!(args.text_source(), TextSource::File("essay.md".into()));
+
+    let args = parse_assignment(&["--text", "-"]).unwrap();
+    assert_eq!(args.text_source(), TextSource::Stdin);
+
+    let args = parse_assignment(&["--type", "online_text_entry"]).unwrap();
+    assert_eq!(args.requested_submission_type(), Ok(Some(SubmissionType::TextEntry)));
+    assert_eq!(args.text_source(), TextSource::Editor);
+
+    let args = parse_assignment(&["--url", "https://example.com"]).unwrap();
+    assert_eq!(args.requested_submission_type(), Ok(Some(SubmissionType::Url)));
+}
+
+#[test]
+fn test_submission_type_conflicts() {
+    assert!(parse_assignment(&["--file-path", "a.pdf", "--url", "https://example.com"]).is_err());
+    assert!(parse_assignment(&["--text", "a.md", "--editor"]).is_err());
+    assert!(parse_assignment(&["--type", "bogus"]).is_err());
+
+    let args = parse_assignment(&["--type", "online_url", "--file-path", "a.pdf"]).unwrap();
+    assert!(args.requested_submission_type().is_err());
+
+    let args = parse_assignment(&["--type", "online_url"]).unwrap();
+    assert!(args.requested_submission_type().is_err());
+}
+
+#[test]
+fn test_offline_flag_is_global() {
+    let cli = Cli::try_parse_from(["canvas-cli", "assignments", "CS101", "--offline"]).unwrap();
+    assert!(cli.offline);
+    let cli = Cli::try_parse_from(["canvas-cli", "courses"]).unwrap();
+    assert!(!cli.offline);
+    assert!(!cli.include_deleted);
+    let cli = Cli::try_parse_from(["canvas-cli", "--offline", "modules", "CS101", "--include-deleted"]).unwrap();
+    assert!(cli.include_deleted);
+    assert_eq!(cli.database, None);
+    let cli = Cli::try_parse_from(["canvas-cli", "sync", "--db", "/tmp/term.db"]).unwrap();
+    assert_eq!(cli.database.as_deref(), Some("/tmp/term.db"));
+}
+
+#[test]
+fn test_whatsnew_command() {
+    let cli = Cli::try_parse_from(["canvas-cli", "whatsnew", "--since", "3d", "-c", "CS101"]).unwrap();
+    match cli.command {
+        Commands::Whatsnew(args) => {
+            assert_eq!(args.since.as_deref(), Some("3d"));
+            assert_eq!(args.course.as_deref(), Some("CS101"));
+        }
+        _ => panic!("Expected Whatsnew command"),
+    }
+}
+
+#[test]
+fn test_search_command() {
+    let cli = Cli::try_parse_from(["canvas-cli", "search", "linked", "lists", "-c", "CS101"]).unwrap();
+    match cli.command {
+        Commands::Search(args) => {
+            assert_eq!(args.query, vec!["linked", "lists"]);
+            assert_eq!(args.course.as_deref(), Some("CS101"));
+            assert_eq!(args.limit, 20);
+        }
+        _ => panic!("Expected Search command"),
+    }
+    assert!(Cli::try_parse_from(["canvas-cli", "search"]).is_err());
+}
+
+#[test]
+fn test_due_command() {
+    let cli = Cli::try_parse_from(["canvas-cli", "due"]).unwrap();
+    match cli.command {
+        Commands::Due(args) => {
+            assert_eq!(args.days, 14);
+            assert_eq!(args.course, None);
+        }
+        _ => panic!("Expected Due command"),
+    }
+    let cli = Cli::try_parse_from(["canvas-cli", "--offline", "due", "--days", "3", "-c", "CS101"]).unwrap();
+    match cli.command {
+        Commands::Due(args) => {
+            assert_eq!(args.days, 3);
+            assert_eq!(args.course.as_deref(), Some("CS101"));
+        }
+        _ => panic!("Expected Due command"),
+    }
+}
+
+#[test]
+fn test_export_ical_command() {
+    let cli = Cli::try_parse_from(["canvas-cli", "export", "ical", "-o", "canvas.ics", "--every", "30m"]).unwrap();
+    match cli.command {
+        Commands::Export(args) => match args.command {
+            ExportCommand::Ical(args) => {
+                assert_eq!(args.output.as_deref(), Some("canvas.ics"));
+                assert_eq!(args.every.as_deref(), Some("30m"));
+            }
+        },
+        _ => panic!("Expected Export command"),
+    }
+    // Rewriting standard output on a timer makes no sense
+    assert!(Cli::try_parse_from(["canvas-cli", "export", "ical", "--every", "30m"]).is_err());
+}
+
+#[test]
+fn test_download_command() {
+    let cli = Cli::try_parse_from(["canvas-cli", "download", "CS101", "5678", "5679", "-o", "notes"]).unwrap();
+    match cli.command {
+        Commands::Download(args) => {
+            assert_eq!(args.course, "CS101");
+            assert_eq!(args.file_ids, vec![5678, 5679]);
+            assert_eq!(args.folder, None);
+            assert_eq!(args.output, "notes");
+        }
+        _ => panic!("Expected Download command"),
+    }
+    let cli = Cli::try_parse_from(["canvas-cli", "download", "CS101", "--folder", "Week 1"]).unwrap();
+    match cli.command {
+        Commands::Download(args) => {
+            assert!(args.file_ids.is_empty());
+            assert_eq!(args.folder.as_deref(), Some("Week 1"));
+            assert_eq!(args.output, ".");
+        }
+        _ => panic!("Expected Download command"),
+    }
+    // Something has to be downloaded
+    assert!(Cli::try_parse_from(["canvas-cli", "download", "CS101"]).is_err());
+}
+
+#[test]
+fn test_page_command() {
+    let cli = Cli::try_parse_from(["canvas-cli", "--offline", "page", "CS101", "office-hours"]).unwrap();
+    assert!(cli.offline);
+    match cli.command {
+        Commands::Page(args) => {
+            assert_eq!(args.course, "CS101");
+            assert_eq!(args.page, "office-hours");
+        }
+        _ => panic!("Expected Page command"),
+    }
+    assert!(matches!(Cli::try_parse_from(["canvas-cli", "pages", "CS101"]).unwrap().command, Commands::Pages(_)));
+}
+
+#[test]
+fn test_announcement_and_discussion_commands() {
+    let cli = Cli::try_parse_from(["canvas-cli", "announcement", "CS101", "8765"]).unwrap();
+    match cli.command {
+        Commands::Announcement(args) => {
+            assert_eq!(args.course, "CS101");
+            assert_eq!(args.announcement_id, 8765);
+        }
+        _ => panic!("Expected Announcement command"),
+    }
+    let cli = Cli::try_parse_from(["canvas-cli", "discussion", "CS101", "4321"]).unwrap();
+    match cli.command {
+        Commands::Discussion(args) => assert_eq!(args.topic_id, 4321),
+        _ => panic!("Expected Discussion command"),
+    }
+}
+
+#[test]
+fn test_submission_command() {
+    let cli = Cli::try_parse_from(["canvas-cli", "submission", "CS101", "4321"]).unwrap();
+    match cli.command {
+        Commands::Submission(args) => {
+            assert_eq!(args.course, "CS101");
+            assert_eq!(args.assignment_id, 4321);
+        }
+        _ => panic!("Expected Submission command"),
+    }
+}
+
+#[test]
+fn test_comment_command() {
+    let cli = Cli::try_parse_from(["canvas-cli", "comment", "CS101", "4321", "Thanks!", "--attach", "a.pdf", "--attach", "b.png"]).unwrap();
+    match cli.command {
+        Commands::Comment(args) => {
+            assert_eq!(args.assignment_id, 4321);
+            assert_eq!(args.text_source(), TextSource::Inline("Thanks!".to_string()));
+            assert_eq!(args.attachments, vec!["a.pdf", "b.png"]);
+        }
+        _ => panic!("Expected Comment command"),
+    }
+    let cli = Cli::try_parse_from(["canvas-cli", "comment", "CS101", "4321", "-"]).unwrap();
+    match cli.command {
+        Commands::Comment(args) => assert_eq!(args.text_source(), TextSource::Stdin),
+        _ => panic!("Expected Comment command"),
+    }
+    let cli = Cli::try_parse_from(["canvas-cli", "comment", "CS101", "4321"]).unwrap();
+    match cli.command {
+        Commands::Comment(args) => assert_eq!(args.text_source(), TextSource::Editor),
+        _ => panic!("Expected Comment command"),
+    }
+}
+
+#[test]
+fn test_connection_flags_are_global() {
+    Cli::command().debug_assert();
+    let cli = Cli::try_parse_from(["canvas-cli", "courses", "--base-url", "https://canvas.test", "--access-token", "token"]).unwrap();
+    assert_eq!(cli.base_url.as_deref(), Some("https://canvas.test"));
+    assert_eq!(cli.access_token.as_deref(), Some("token"));
+}
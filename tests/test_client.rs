@@ -1,6 +1,12 @@
-use canvas_cli::client::CanvasClient;
+use canvas_cli::client::{next_link, page_key, CanvasClient};
+use canvas_cli::error::CanvasError;
+use canvas_cli::models::Course;
+use canvas_cli::render::{render, RenderOptions};
+use canvas_cli::retry::{RateLimiter, RetryConfig};
 use mockito::Server;
+use reqwest::header::{HeaderMap, HeaderValue, LINK};
 use std::error::Error;
+use std::time::Duration;
 
 mod mocks;
 use mocks::responses::*;
@@ -43,6 +49,83 @@ fn test_get_assignments() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+fn test_get_assignments_with_submission() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let _mock = server.mock("GET", "/api/v1/courses/1234/assignments?include[]=submission")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(ASSIGNMENTS_WITH_SUBMISSION_RESPONSE)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let assignments = client.get_assignments_with_submission(1234)?;
+
+    let submission = assignments[0].submission.as_ref().unwrap();
+    assert_eq!(submission.workflow_state.as_deref(), Some("submitted"));
+    assert!(submission.submitted_at.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_get_pages() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let _list = server.mock("GET", "/api/v1/courses/1234/pages?sort=title")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(PAGES_RESPONSE)
+        .create();
+    let page = server.mock("GET", "/api/v1/courses/1234/pages/test-page")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(PAGE_RESPONSE)
+        .expect(2)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let pages = client.get_pages(1234)?;
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0].page_id, 42);
+    assert!(pages[0].front_page);
+
+    // A link to the page works as well as its slug
+    assert!(client.get_page(1234, "test-page")?.body.unwrap().contains("<h2>Welcome</h2>"));
+    assert_eq!(client.get_page(1234, "https://canvas.test/courses/1234/pages/test-page")?.title, "Test Page");
+    page.assert();
+    assert_eq!(page_key("42"), "42");
+
+    Ok(())
+}
+
+#[test]
+fn test_get_announcement_and_discussion() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let _announcement = server.mock("GET", "/api/v1/courses/1/discussion_topics/8765")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(ANNOUNCEMENT_RESPONSE)
+        .create();
+    let _discussion = server.mock("GET", "/api/v1/courses/1/discussion_topics/4321")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(DISCUSSION_RESPONSE)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let announcement = client.get_announcement(1, 8765)?;
+    assert_eq!(announcement.title, "Test Announcement");
+    let rendered = render(&announcement.message, &RenderOptions::default());
+    assert_eq!(rendered.text, "Slides are up: Week 1[1]\n\n[1] week1.pdf (file 5678)");
+    assert_eq!(rendered.files[0].id, 5678);
+
+    let discussion = client.get_discussion(1, 4321)?;
+    assert_eq!(discussion.discussion_subentry_count, 5);
+    assert_eq!(render(&discussion.message, &RenderOptions::default()).text, "Discussion **content**");
+
+    Ok(())
+}
+
 #[test]
 fn test_error_handling() {
     let mut server = Server::new();
@@ -58,3 +141,238 @@ fn test_error_handling() {
     
     assert!(result.is_err());
 }
+
+#[test]
+fn test_pagination_follows_link_header() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let next = format!("<{}/api/v1/courses/1234/assignments?page=2&per_page=1>; rel=\"next\"", server.url());
+
+    let _first = server.mock("GET", "/api/v1/courses/1234/assignments?per_page=1")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_header("link", &next)
+        .with_body(ASSIGNMENTS_RESPONSE)
+        .create();
+    let _second = server.mock("GET", "/api/v1/courses/1234/assignments?page=2&per_page=1")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(ASSIGNMENTS_PAGE_2_RESPONSE)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token").with_per_page(1);
+    let assignments = client.get_assignments(1234)?;
+
+    assert_eq!(assignments.len(), 2);
+    assert_eq!(assignments[0].id, 4321);
+    assert_eq!(assignments[1].id, 4322);
+
+    Ok(())
+}
+
+#[test]
+fn test_paginate_is_lazy() {
+    let mut server = Server::new();
+    let next = format!("<{}/api/v1/courses?page=2>; rel=\"next\"", server.url());
+
+    let _first = server.mock("GET", "/api/v1/courses")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_header("link", &next)
+        .with_body(COURSES_RESPONSE)
+        .create();
+    let second = server.mock("GET", "/api/v1/courses?page=2")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("[]")
+        .expect(0)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let first = client.paginate::<Course>("courses").next().unwrap().unwrap();
+
+    assert_eq!(first.id, 1234);
+    second.assert();
+}
+
+#[test]
+fn test_next_link_parsing() {
+    let mut headers = HeaderMap::new();
+    headers.insert(LINK, HeaderValue::from_static(
+        "<https://canvas.test/api/v1/courses?page=1>; rel=\"current\",\
+         <https://canvas.test/api/v1/courses?page=2>; rel=\"next\",\
+         <https://canvas.test/api/v1/courses?page=5>; rel=\"last\""
+    ));
+    assert_eq!(next_link(&headers), Some("https://canvas.test/api/v1/courses?page=2".to_string()));
+
+    headers.insert(LINK, HeaderValue::from_static("<https://canvas.test/api/v1/courses?page=5>; rel=\"last\""));
+    assert_eq!(next_link(&headers), None);
+}
+
+#[test]
+fn test_error_variants() {
+    let mut server = Server::new();
+
+    let _unauthorized = server.mock("GET", "/api/v1/courses")
+        .with_status(401)
+        .with_body(r#"{"errors": [{"message": "Invalid access token."}]}"#)
+        .create();
+    let _forbidden = server.mock("GET", "/api/v1/courses/1")
+        .with_status(403)
+        .with_body(r#"{"status": "unauthorized", "errors": [{"message": "user not authorized to perform that action"}]}"#)
+        .create();
+    let _throttled = server.mock("GET", "/api/v1/courses/2")
+        .with_status(403)
+        .with_body("403 Forbidden (Rate Limit Exceeded)")
+        .create();
+    let _not_found = server.mock("GET", "/api/v1/courses/3")
+        .with_status(404)
+        .with_body(r#"{"errors": [{"message": "The specified resource does not exist."}]}"#)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token").with_retry_config(RetryConfig::disabled());
+
+    assert!(matches!(client.get_courses(), Err(CanvasError::Unauthorized)));
+    assert!(matches!(client.get_course(1), Err(CanvasError::Forbidden(_))));
+    assert!(matches!(client.get_course(2), Err(CanvasError::RateLimited)));
+    assert!(matches!(client.get_course(3), Err(CanvasError::NotFound(_))));
+}
+
+#[test]
+fn test_validation_error_messages() {
+    let mut server = Server::new();
+
+    let _mock = server.mock("POST", "/api/v1/courses/1/discussion_topics/2/entries")
+        .with_status(400)
+        .with_body(r#"{"errors": {"message": [{"attribute": "message", "type": "blank", "message": "can't be blank"}]}}"#)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    match client.post_discussion_entry(1, 2, "") {
+        Err(CanvasError::Validation(messages)) => assert_eq!(messages, vec!["message: can't be blank"]),
+        other => panic!("Expected validation error, got {:?}", other.map(|e| e.id)),
+    }
+}
+
+#[test]
+fn test_deserialize_error_reports_path() {
+    let mut server = Server::new();
+
+    let _mock = server.mock("GET", "/api/v1/courses")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"[{"id": 1}, {"id": "not-a-number"}]"#)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    match client.get_courses() {
+        Err(CanvasError::Deserialize { path, .. }) => assert_eq!(path, "[1].id"),
+        other => panic!("Expected deserialize error, got {:?}", other.map(|c| c.len())),
+    }
+}
+
+fn fast_retry() -> RetryConfig {
+    RetryConfig {
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(5),
+        throttle_delay: Duration::from_millis(1),
+        ..RetryConfig::default()
+    }
+}
+
+#[test]
+fn test_retries_server_errors() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+
+    let failing = server.mock("GET", "/api/v1/courses")
+        .with_status(503)
+        .expect(2)
+        .create();
+    let _ok = server.mock("GET", "/api/v1/courses")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_header("x-rate-limit-remaining", "512.5")
+        .with_header("x-request-cost", "1.25")
+        .with_body(COURSES_RESPONSE)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token").with_retry_config(fast_retry());
+    let courses = client.get_courses()?;
+
+    assert_eq!(courses.len(), 1);
+    failing.assert();
+    assert_eq!(client.rate_limit_state().remaining, Some(512.5));
+    assert_eq!(client.rate_limit_state().last_cost, Some(1.25));
+
+    Ok(())
+}
+
+#[test]
+fn test_retry_gives_up_after_max_retries() {
+    let mut server = Server::new();
+
+    let throttled = server.mock("GET", "/api/v1/courses")
+        .with_status(403)
+        .with_body("403 Forbidden (Rate Limit Exceeded)")
+        .expect(3)
+        .create();
+
+    let retry = RetryConfig { max_retries: 2, ..fast_retry() };
+    let client = CanvasClient::new(&server.url(), "fake-token").with_retry_config(retry);
+
+    assert!(matches!(client.get_courses(), Err(CanvasError::RateLimited)));
+    throttled.assert();
+}
+
+#[test]
+fn test_client_errors_are_not_retried() {
+    let mut server = Server::new();
+
+    let not_found = server.mock("GET", "/api/v1/courses/1")
+        .with_status(404)
+        .expect(1)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token").with_retry_config(fast_retry());
+
+    assert!(matches!(client.get_course(1), Err(CanvasError::NotFound(_))));
+    not_found.assert();
+}
+
+#[test]
+fn test_backoff_grows_and_is_capped() {
+    let retry = RetryConfig {
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(1000),
+        ..RetryConfig::default()
+    };
+
+    for attempt in 0..8 {
+        let delay = retry.backoff(attempt);
+        let full = (Duration::from_millis(100) * 2u32.pow(attempt)).min(Duration::from_millis(1000));
+        assert!(delay >= full / 2 && delay <= full, "attempt {}: {:?}", attempt, delay);
+    }
+}
+
+#[test]
+fn test_rate_limiter_slows_down_when_bucket_is_low() {
+    let limiter = RateLimiter::default();
+    let config = RetryConfig {
+        low_water_mark: 100.0,
+        throttle_delay: Duration::from_secs(2),
+        ..RetryConfig::default()
+    };
+    assert_eq!(limiter.delay(&config), Duration::ZERO);
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-rate-limit-remaining", HeaderValue::from_static("600"));
+    limiter.update(&headers);
+    assert_eq!(limiter.delay(&config), Duration::ZERO);
+
+    headers.insert("x-rate-limit-remaining", HeaderValue::from_static("50"));
+    limiter.update(&headers);
+    assert_eq!(limiter.delay(&config), Duration::from_secs(1));
+
+    headers.insert("x-rate-limit-remaining", HeaderValue::from_static("0"));
+    limiter.update(&headers);
+    assert_eq!(limiter.delay(&config), Duration::from_secs(2));
+}
@@ -0,0 +1,256 @@
+use canvas_cli::config::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+fn config_file_path() -> PathBuf {
+    PathBuf::from("/config/canvas-cli/config.toml")
+}
+
+fn file_layer(base_url: Option<&str>, access_token: Option<&str>) -> Option<(PathBuf, ConfigFile)> {
+    Some((config_file_path(), ConfigFile {
+        base_url: base_url.map(str::to_string),
+        access_token: access_token.map(str::to_string),
+        ..ConfigFile::default()
+    }))
+}
+
+fn profiles_file(default_profile: Option<&str>) -> ConfigFile {
+    let mut profiles = BTreeMap::new();
+    profiles.insert("work".to_string(), Profile {
+        base_url: Some("https://work.test".to_string()),
+        access_token: Some("work-token".to_string()),
+        aliases: BTreeMap::from([("os".to_string(), 42)]),
+        database: None,
+    });
+    ConfigFile {
+        default_profile: default_profile.map(str::to_string),
+        base_url: Some("https://school.test".to_string()),
+        access_token: Some("school-token".to_string()),
+        aliases: BTreeMap::from([("rust".to_string(), 7)]),
+        database: None,
+        profiles,
+    }
+}
+
+fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+#[test]
+fn test_precedence() {
+    let layers = Layers {
+        flags: Flags { base_url: Some("https://flag.test".to_string()), ..Flags::default() },
+        env: vars(&[(ACCESS_TOKEN_VAR, "env-token")]),
+        dotenv: Some((PathBuf::from("/project/.env"), vars(&[(BASE_URL_VAR, "https://dotenv.test"), (ACCESS_TOKEN_VAR, "dotenv-token")]))),
+        file: file_layer(Some("https://file.test"), Some("file-token")),
+        data_dir: None,
+    };
+    let config = Config::resolve(layers).unwrap();
+
+    let base_url = config.base_url.unwrap();
+    assert_eq!(base_url.value, "https://flag.test");
+    assert_eq!(base_url.source, Source::Flag("--base-url"));
+
+    let token = config.access_token.unwrap();
+    assert_eq!(token.value, "env-token");
+    assert_eq!(token.source, Source::Env(ACCESS_TOKEN_VAR));
+
+    assert_eq!(config.profile.value, DEFAULT_PROFILE);
+    assert_eq!(config.profile.source, Source::Default);
+}
+
+#[test]
+fn test_lower_layers_fill_gaps() {
+    let layers = Layers {
+        env: vars(&[(BASE_URL_VAR, "")]),
+        dotenv: Some((PathBuf::from("/project/.env"), vars(&[(BASE_URL_VAR, "https://dotenv.test")]))),
+        file: file_layer(None, Some("file-token")),
+        ..Layers::default()
+    };
+    let config = Config::resolve(layers).unwrap();
+
+    assert_eq!(config.base_url.unwrap().source, Source::DotEnv(PathBuf::from("/project/.env")));
+    let token = config.access_token.unwrap();
+    assert_eq!(token.value, "file-token");
+    assert_eq!(token.source, Source::ConfigFile(config_file_path()));
+}
+
+#[test]
+fn test_missing_credentials() {
+    let config = Config::resolve(Layers {
+        file: file_layer(Some("https://file.test"), None),
+        ..Layers::default()
+    }).unwrap();
+
+    match config.credentials() {
+        Err(ConfigError::Missing(profile, keys)) => {
+            assert_eq!(profile, DEFAULT_PROFILE);
+            assert_eq!(keys, vec!["access_token"]);
+        }
+        other => panic!("Expected missing access_token, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_profile_selection() {
+    // The config file's default profile applies when nothing else selects one
+    let config = Config::resolve(Layers {
+        file: Some((config_file_path(), profiles_file(Some("work")))),
+        data_dir: Some(PathBuf::from("/data")),
+        ..Layers::default()
+    }).unwrap();
+    assert_eq!(config.profile.value, "work");
+    assert_eq!(config.database_path(), PathBuf::from("/data/canvas-cli/work.db"));
+    assert_eq!(config.base_url.unwrap().value, "https://work.test");
+
+    // CANVAS_PROFILE beats the config file, and --profile beats both
+    let config = Config::resolve(Layers {
+        flags: Flags { profile: Some("default".to_string()), ..Flags::default() },
+        env: vars(&[(PROFILE_VAR, "work")]),
+        file: Some((config_file_path(), profiles_file(Some("work")))),
+        data_dir: Some(PathBuf::from("/data")),
+        ..Layers::default()
+    }).unwrap();
+    assert_eq!(config.profile.source, Source::Flag("--profile"));
+    assert_eq!(config.database_path(), PathBuf::from("/data/canvas-cli/default.db"));
+    assert_eq!(config.access_token.unwrap().value, "school-token");
+}
+
+#[test]
+fn test_database_location() {
+    // Each profile gets its own file in the data directory by default
+    let config = Config::resolve(Layers {
+        flags: Flags { profile: Some("work".to_string()), ..Flags::default() },
+        file: Some((config_file_path(), profiles_file(None))),
+        ..Layers::default()
+    }).unwrap();
+    assert_eq!(config.database.source, Source::Default);
+    assert_eq!(config.database_path(), PathBuf::from("work.db"));
+
+    // A path in the config file is relative to the config file
+    let mut file = profiles_file(None);
+    file.database = Some("school.db".to_string());
+    let config = Config::resolve(Layers {
+        file: Some((config_file_path(), file.clone())),
+        data_dir: Some(PathBuf::from("/data")),
+        ..Layers::default()
+    }).unwrap();
+    assert_eq!(config.database.source, Source::ConfigFile(config_file_path()));
+    assert_eq!(config.database_path(), PathBuf::from("/config/canvas-cli/school.db"));
+
+    // --db beats the config file
+    let config = Config::resolve(Layers {
+        flags: Flags { database: Some("/tmp/other.db".to_string()), ..Flags::default() },
+        file: Some((config_file_path(), file)),
+        ..Layers::default()
+    }).unwrap();
+    assert_eq!(config.database.source, Source::Flag("--db"));
+    assert_eq!(config.database_path(), PathBuf::from("/tmp/other.db"));
+}
+
+#[test]
+fn test_environment_does_not_leak_into_named_profiles() {
+    let config = Config::resolve(Layers {
+        flags: Flags { profile: Some("work".to_string()), ..Flags::default() },
+        env: vars(&[(BASE_URL_VAR, "https://env.test")]),
+        dotenv: Some((PathBuf::from("/project/.env"), vars(&[(ACCESS_TOKEN_VAR, "dotenv-token")]))),
+        file: Some((config_file_path(), profiles_file(None))),
+        data_dir: None,
+    }).unwrap();
+
+    assert_eq!(config.base_url.unwrap().value, "https://work.test");
+    assert_eq!(config.access_token.unwrap().value, "work-token");
+}
+
+#[test]
+fn test_unknown_and_invalid_profiles() {
+    let result = Config::resolve(Layers {
+        flags: Flags { profile: Some("home".to_string()), ..Flags::default() },
+        file: Some((config_file_path(), profiles_file(None))),
+        ..Layers::default()
+    });
+    assert!(matches!(result, Err(ConfigError::UnknownProfile(name)) if name == "home"));
+
+    let result = Config::resolve(Layers {
+        flags: Flags { profile: Some("../etc".to_string()), ..Flags::default() },
+        ..Layers::default()
+    });
+    assert!(matches!(result, Err(ConfigError::InvalidProfileName(_))));
+}
+
+#[test]
+fn test_aliases_follow_profile() {
+    let config = Config::resolve(Layers {
+        file: Some((config_file_path(), profiles_file(Some("work")))),
+        ..Layers::default()
+    }).unwrap();
+    assert_eq!(config.aliases, BTreeMap::from([("os".to_string(), 42)]));
+
+    // The default profile uses the top-level table, and edits land there too
+    let mut file = profiles_file(None);
+    file.aliases_mut("default").unwrap().insert("db".to_string(), 9);
+    assert_eq!(file.profile("default").unwrap().aliases.get("db"), Some(&9));
+    assert!(!file.profile("work").unwrap().aliases.contains_key("db"));
+    assert!(matches!(file.aliases_mut("home"), Err(ConfigError::UnknownProfile(_))));
+
+    assert!(validate_alias("os").is_ok());
+    assert!(matches!(validate_alias("1234"), Err(ConfigError::InvalidAlias(_))));
+    assert!(matches!(validate_alias("my course"), Err(ConfigError::InvalidAlias(_))));
+}
+
+#[test]
+fn test_profile_names() {
+    let file = profiles_file(None);
+    assert_eq!(file.profile_names(), vec!["default", "work"]);
+    assert_eq!(ConfigFile::default().profile_names(), Vec::<String>::new());
+}
+
+#[test]
+fn test_config_file_location_and_parsing() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("canvas-cli").join("config.toml");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "base_url = \"https://file.test\"\naccess_token = \"file-token\"\n").unwrap();
+
+    temp_env::with_vars([("XDG_CONFIG_HOME", Some(dir.path()))], || {
+        assert_eq!(config_path(), Some(path.clone()));
+        let file = ConfigFile::load(&path).unwrap();
+        assert_eq!(file.base_url.as_deref(), Some("https://file.test"));
+        assert_eq!(file.access_token.as_deref(), Some("file-token"));
+    });
+
+    assert_eq!(ConfigFile::load(&dir.path().join("missing.toml")).unwrap(), ConfigFile::default());
+    fs::write(&path, "base_url = [").unwrap();
+    assert!(matches!(ConfigFile::load(&path), Err(ConfigError::Parse(..))));
+}
+
+#[test]
+fn test_config_file_round_trip() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("canvas-cli").join("config.toml");
+    let file = profiles_file(Some("work"));
+
+    file.save(&path).unwrap();
+    assert_eq!(ConfigFile::load(&path).unwrap(), file);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        // An older, world-readable file is narrowed and overwritten in full.
+        fs::write(&path, "x".repeat(4096)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        file.save(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(ConfigFile::load(&path).unwrap(), file);
+    }
+}
+
+#[test]
+fn test_redact() {
+    assert_eq!(redact("1234~abcdefghijklmnop"), "********mnop");
+    assert_eq!(redact("short"), "*****");
+}
@@ -0,0 +1,104 @@
+use canvas_cli::due::*;
+use canvas_cli::models::{Assignment, Course, Submission};
+use chrono::{DateTime, Duration, FixedOffset, TimeZone, Utc};
+
+mod mocks;
+use mocks::responses::*;
+
+fn course(id: u64, code: &str) -> Course {
+    Course {
+        id,
+        name: None,
+        course_code: Some(code.to_string()),
+        enrollment_term_id: None,
+        total_students: None,
+        start_at: None,
+        end_at: None,
+        deleted_at: None,
+    }
+}
+
+fn assignment(id: u64, due_at: Option<DateTime<Utc>>, submission: Option<Submission>) -> Assignment {
+    let mut assignments: Vec<Assignment> = serde_json::from_str(ASSIGNMENTS_RESPONSE).unwrap();
+    Assignment { id, due_at, submission, ..assignments.remove(0) }
+}
+
+fn submission(submitted: bool, missing: bool) -> Submission {
+    Submission {
+        id: 1,
+        assignment_id: 1,
+        score: None,
+        submitted_at: submitted.then(Utc::now),
+        late: false,
+        missing,
+        grade: None,
+        workflow_state: Some(if submitted { "submitted" } else { "unsubmitted" }.to_string()),
+        ..Submission::default()
+    }
+}
+
+/// Wednesday 2024-03-06 10:00 in UTC-5
+fn now() -> DateTime<FixedOffset> {
+    FixedOffset::west_opt(5 * 3600).unwrap().with_ymd_and_hms(2024, 3, 6, 10, 0, 0).unwrap()
+}
+
+#[test]
+fn test_buckets_use_local_days() {
+    let now = now();
+    let at = |hours: i64| now.with_timezone(&Utc) + Duration::hours(hours);
+    assert_eq!(DueBucket::of(at(-1), &now), DueBucket::Overdue);
+    // 23:00 local is still today, though it is tomorrow in UTC
+    assert_eq!(DueBucket::of(at(13), &now), DueBucket::Today);
+    assert_eq!(DueBucket::of(at(15), &now), DueBucket::ThisWeek);
+    assert_eq!(DueBucket::of(at(6 * 24), &now), DueBucket::ThisWeek);
+    assert_eq!(DueBucket::of(at(7 * 24), &now), DueBucket::Later);
+}
+
+#[test]
+fn test_submission_status() {
+    assert_eq!(SubmissionStatus::of(None), SubmissionStatus::Unknown);
+    assert_eq!(SubmissionStatus::of(Some(&submission(false, false))), SubmissionStatus::NotSubmitted);
+    assert_eq!(SubmissionStatus::of(Some(&submission(false, true))), SubmissionStatus::Missing);
+    assert_eq!(SubmissionStatus::of(Some(&submission(true, false))), SubmissionStatus::Submitted);
+    let late = Submission { late: true, ..submission(true, false) };
+    assert_eq!(SubmissionStatus::of(Some(&late)), SubmissionStatus::Late);
+    let graded = Submission { workflow_state: Some("graded".to_string()), ..submission(true, false) };
+    assert_eq!(SubmissionStatus::of(Some(&graded)), SubmissionStatus::Graded);
+}
+
+#[test]
+fn test_due_items() {
+    let now = now();
+    let at = |days: i64| now.with_timezone(&Utc) + Duration::days(days);
+    let courses = vec![
+        (course(1, "CS101"), vec![
+            assignment(10, Some(at(3)), None),
+            assignment(11, None, None),
+            // Handed in, so no longer worth showing
+            assignment(12, Some(at(-1)), Some(submission(true, false))),
+            assignment(13, Some(at(-2)), Some(submission(false, true))),
+        ]),
+        (course(2, "MATH200"), vec![
+            assignment(20, Some(at(1)), Some(submission(false, false))),
+            // Outside the window either way
+            assignment(21, Some(at(30)), None),
+            assignment(22, Some(at(-30)), Some(submission(false, true))),
+        ]),
+    ];
+
+    let items = due_items(&courses, &now, 14);
+    let rows: Vec<_> = items.iter().map(|i| (i.assignment_id, i.bucket, i.status)).collect();
+    assert_eq!(rows, vec![
+        (13, DueBucket::Overdue, SubmissionStatus::Missing),
+        (20, DueBucket::ThisWeek, SubmissionStatus::NotSubmitted),
+        (10, DueBucket::ThisWeek, SubmissionStatus::Unknown),
+    ]);
+    assert_eq!(items[1].course_code.as_deref(), Some("MATH200"));
+    assert_eq!(items[1].due, "Thu Mar 7 10:00 (in 1 day)");
+    assert_eq!(items[0].due, "Mon Mar 4 10:00 (2 days ago)");
+
+    assert_eq!(due_items(&courses, &now, 60).len(), 5);
+    // Beyond the last representable date, everything is in the window
+    assert_eq!(due_items(&courses, &now, u32::MAX.into()).len(), 5);
+    assert_eq!(due_items(&courses, &now, i64::MAX).len(), 5);
+}
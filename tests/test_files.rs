@@ -0,0 +1,207 @@
+use canvas_cli::client::CanvasClient;
+use canvas_cli::error::CanvasError;
+use canvas_cli::files::{self, CourseFile};
+use canvas_cli::models::{CanvasFile, Folder};
+use mockito::{Matcher, Server, ServerGuard};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+mod mocks;
+use mocks::responses::*;
+
+/// Mock the folder and file listings of course 1234, with download URLs on `server`
+fn mock_listings(server: &mut ServerGuard) -> (Vec<Folder>, Vec<CanvasFile>) {
+    server.mock("GET", "/api/v1/courses/1234/folders")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(FOLDERS_RESPONSE)
+        .create();
+    server.mock("GET", "/api/v1/courses/1234/files")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(COURSE_FILES_RESPONSE.replace("https://canvas.test", &server.url()))
+        .create();
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    (client.get_folders(1234).unwrap(), client.get_course_files(1234).unwrap())
+}
+
+#[test]
+fn test_files_in_folders() {
+    let mut server = Server::new();
+    let (folders, files) = mock_listings(&mut server);
+    assert_eq!(folders.len(), 2);
+    assert_eq!(files[0].content_type, "application/pdf");
+    assert_eq!(files[1].folder_id, Some(11));
+
+    let all = files::files_in(&folders, files, None);
+    let paths: Vec<&str> = all.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["Week 1/answers.pdf", "Week 1/slides.pdf", "syllabus.pdf"]);
+
+    let week = files::find_folder(&folders, "Week 1/").unwrap();
+    assert_eq!(week.id, 11);
+    assert_eq!(files::find_folder(&folders, "11").unwrap().id, 11);
+    assert_eq!(files::find_folder(&folders, "/").unwrap().id, 10);
+    assert!(files::find_folder(&folders, "Week 2").is_none());
+
+    let files = CanvasClient::new(&server.url(), "fake-token").get_course_files(1234).unwrap();
+    let within = files::files_in(&folders, files, Some(week));
+    let paths: Vec<&str> = within.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["answers.pdf", "slides.pdf"]);
+}
+
+#[test]
+fn test_mirror_skips_current_files() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let (folders, files) = mock_listings(&mut server);
+    let syllabus = server.mock("GET", "/files/5678/download")
+        .with_status(200)
+        .with_body("0123456789")
+        .expect(1)
+        .create();
+    let slides = server.mock("GET", "/files/5679/download")
+        .with_status(200)
+        .with_body("slides")
+        .expect(1)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir()?;
+    let course_files = files::files_in(&folders, files, None);
+    let report = files::mirror(&client, &course_files, dir.path(), |_| {});
+    assert_eq!(report.downloaded, 2);
+    assert_eq!(report.skipped, 0);
+    // The locked file can't be downloaded, but doesn't stop the others
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.errors[0].contains("Week 1/answers.pdf"));
+
+    assert_eq!(fs::read(dir.path().join("syllabus.pdf"))?, b"0123456789");
+    assert_eq!(fs::read(dir.path().join("Week 1/slides.pdf"))?, b"slides");
+    assert!(files::is_current(&course_files[2].file, &dir.path().join("syllabus.pdf")));
+
+    // Nothing changed, so nothing is fetched again
+    let report = files::mirror(&client, &course_files, dir.path(), |_| {});
+    assert_eq!(report.downloaded, 0);
+    assert_eq!(report.skipped, 2);
+    syllabus.assert();
+    slides.assert();
+
+    // A local edit makes the file stale
+    fs::write(dir.path().join("syllabus.pdf"), b"edited")?;
+    assert!(!files::is_current(&course_files[2].file, &dir.path().join("syllabus.pdf")));
+    Ok(())
+}
+
+#[test]
+fn test_download_resumes_partial_file() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let resumed = server.mock("GET", "/files/5678/download")
+        .match_header("range", "bytes=4-")
+        .with_status(206)
+        .with_body("456789")
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let mut file: CanvasFile = serde_json::from_str(FILE_RESPONSE)?;
+    file.url = format!("{}/files/5678/download", server.url());
+    file.size = 10;
+
+    let dir = tempdir()?;
+    let dest = dir.path().join("test.pdf");
+    let part = dir.path().join(format!("test.pdf.{}.part", file.updated_at.timestamp()));
+    fs::write(&part, b"0123")?;
+
+    client.download_file(&file, &dest)?;
+    resumed.assert();
+    assert_eq!(fs::read(&dest)?, b"0123456789");
+    assert!(!part.exists());
+    assert!(files::is_current(&file, &dest));
+    Ok(())
+}
+
+#[test]
+fn test_download_ignored_range_and_short_reads() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    // A server that ignores the range sends everything again
+    let full = server.mock("GET", "/files/5678/download")
+        .with_status(200)
+        .with_body("0123456789")
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let mut file: CanvasFile = serde_json::from_str(FILE_RESPONSE)?;
+    file.url = format!("{}/files/5678/download", server.url());
+    file.size = 10;
+
+    let dir = tempdir()?;
+    let dest = dir.path().join("test.pdf");
+    fs::write(dir.path().join(format!("test.pdf.{}.part", file.updated_at.timestamp())), b"xxxx")?;
+    client.download_file(&file, &dest)?;
+    full.assert();
+    assert_eq!(fs::read(&dest)?, b"0123456789");
+
+    // A download that comes up short is kept for resuming, not moved into place
+    file.size = 20;
+    let other = dir.path().join("other.pdf");
+    let result = client.download_file(&file, &other);
+    assert!(matches!(result, Err(CanvasError::Io(_))));
+    assert!(!other.exists());
+    assert!(Path::new(&dir.path().join(format!("other.pdf.{}.part", file.updated_at.timestamp()))).exists());
+    Ok(())
+}
+
+#[test]
+fn test_single_file_name() -> Result<(), Box<dyn Error>> {
+    let mut file: CanvasFile = serde_json::from_str(FILE_RESPONSE)?;
+    file.display_name = "../notes/week 1.pdf".to_string();
+    let named = CourseFile::named(file);
+    assert_eq!(named.path, ".._notes_week 1.pdf");
+    Ok(())
+}
+
+#[test]
+fn test_duplicate_names_get_ids() {
+    let mut server = Server::new();
+    let (folders, mut files) = mock_listings(&mut server);
+    // Canvas allows several files of the same name in one folder
+    files[1].display_name = "answers.pdf".to_string();
+    let copies: Vec<CanvasFile> = serde_json::from_str(COURSE_FILES_RESPONSE).unwrap();
+    for (mut readme, id) in copies.into_iter().zip([5690, 5681]) {
+        readme.id = id;
+        readme.display_name = "README".to_string();
+        readme.folder_id = Some(10);
+        files.push(readme);
+    }
+    let all = files::files_in(&folders, files, None);
+    let paths: Vec<&str> = all.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["README", "README-5690", "Week 1/answers-5680.pdf", "Week 1/answers.pdf", "syllabus.pdf"]);
+    assert_eq!(all[3].file.id, 5679);
+}
+
+#[test]
+fn test_prune_removes_files_gone_from_canvas() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let (folders, files) = mock_listings(&mut server);
+    let course_files = files::files_in(&folders, files, None);
+    let dir = tempdir()?;
+    let answers = &course_files[0].file;
+    for path in ["syllabus.pdf", "old.pdf", "Week 1/slides.pdf", "Week 2/notes.pdf"] {
+        fs::create_dir_all(dir.path().join(path).parent().unwrap())?;
+        fs::write(dir.path().join(path), b"data")?;
+    }
+    let part = format!("Week 1/answers.pdf.{}.part", answers.updated_at.timestamp());
+    fs::write(dir.path().join(&part), b"da")?;
+    fs::write(dir.path().join("Week 1/answers.pdf.1.part"), b"da")?;
+
+    let removed = files::prune(&course_files, dir.path())?;
+    assert_eq!(removed, ["Week 1/answers.pdf.1.part", "Week 2/notes.pdf", "old.pdf"]);
+    assert!(dir.path().join("syllabus.pdf").exists());
+    assert!(dir.path().join("Week 1/slides.pdf").exists());
+    // A partial download of a listed file can still resume
+    assert!(dir.path().join(&part).exists());
+    assert!(!dir.path().join("Week 2").exists());
+    Ok(())
+}
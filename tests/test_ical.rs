@@ -0,0 +1,71 @@
+use canvas_cli::ical::*;
+use canvas_cli::models::{Assignment, Course};
+use chrono::{TimeZone, Utc};
+use tempfile::tempdir;
+
+mod mocks;
+use mocks::responses::*;
+
+fn course() -> Course {
+    let mut courses: Vec<Course> = serde_json::from_str(COURSES_RESPONSE).unwrap();
+    courses.remove(0)
+}
+
+fn assignment() -> Assignment {
+    let mut assignments: Vec<Assignment> = serde_json::from_str(ASSIGNMENTS_RESPONSE).unwrap();
+    assignments.remove(0)
+}
+
+#[test]
+fn test_assignment_events() {
+    let course = course();
+    let event = Event::for_assignment("canvas.test", &course, &assignment()).unwrap();
+    assert_eq!(event.uid, "assignment-4321@canvas.test");
+    assert_eq!(event.start, Utc.with_ymd_and_hms(2024, 2, 1, 23, 59, 59).unwrap());
+    assert_eq!(event.summary, format!("[{}] Test Assignment", course.course_code.as_deref().unwrap()));
+    assert_eq!(event.description.as_deref(), Some("Test Description"));
+
+    let undated = Assignment { due_at: None, ..assignment() };
+    assert!(Event::for_assignment("canvas.test", &course, &undated).is_none());
+}
+
+#[test]
+fn test_write_calendar() {
+    let event = Event {
+        uid: "assignment-1@canvas.test".to_string(),
+        start: Utc.with_ymd_and_hms(2024, 3, 1, 23, 59, 0).unwrap(),
+        end: Utc.with_ymd_and_hms(2024, 3, 1, 23, 59, 0).unwrap(),
+        summary: "Essay; draft, final\\notes".to_string(),
+        description: Some(format!("Line one\nLine two {}", "é".repeat(60))),
+        url: Some("https://canvas.test/assignments/1".to_string()),
+    };
+    let mut out = Vec::new();
+    write_calendar(&mut out, "Canvas", &[event], Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap()).unwrap();
+    let text = String::from_utf8(out).unwrap();
+
+    assert!(text.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(text.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+    assert!(text.contains("\r\nUID:assignment-1@canvas.test\r\n"));
+    assert!(text.contains("\r\nDTSTAMP:20240201T000000Z\r\nDTSTART:20240301T235900Z\r\nDTEND:20240301T235900Z\r\n"));
+    assert!(text.contains("\r\nSUMMARY:Essay\\; draft\\, final\\\\notes\r\n"));
+
+    // Long lines are folded at 75 octets without splitting characters
+    let lines: Vec<&str> = text.split("\r\n").collect();
+    assert!(lines.iter().all(|line| line.len() <= 75));
+    let start = lines.iter().position(|line| line.starts_with("DESCRIPTION:")).unwrap();
+    let mut description = lines[start].to_string();
+    for line in lines[start + 1..].iter().take_while(|line| line.starts_with(' ')) {
+        description.push_str(&line[1..]);
+    }
+    assert_eq!(description, format!("DESCRIPTION:Line one\\nLine two {}", "é".repeat(60)));
+}
+
+#[test]
+fn test_write_atomically() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("canvas.ics");
+    write_atomically(&path, b"first").unwrap();
+    write_atomically(&path, b"second").unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
@@ -0,0 +1,86 @@
+use canvas_cli::db::Database;
+use canvas_cli::migrations::*;
+use rusqlite::Connection;
+use std::path::Path;
+use tempfile::tempdir;
+
+#[test]
+fn test_versions_are_sequential() {
+    for (i, migration) in MIGRATIONS.iter().enumerate() {
+        assert_eq!(migration.version as usize, i + 1, "{}", migration.description);
+    }
+    assert_eq!(latest_version() as usize, MIGRATIONS.len());
+}
+
+#[test]
+fn test_new_database_is_current_without_backup() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("canvas.db");
+    let db = Database::open_unmigrated(&path).unwrap();
+    assert_eq!(db.schema_version().unwrap(), 0);
+    assert_eq!(db.pending_migrations().unwrap().len(), MIGRATIONS.len());
+
+    let report = db.migrate().unwrap();
+    assert_eq!(report, MigrationReport { from: 0, to: latest_version(), backup: None });
+    assert!(db.pending_migrations().unwrap().is_empty());
+
+    // Running again is a no-op
+    let report = db.migrate().unwrap();
+    assert_eq!(report.from, report.to);
+}
+
+#[test]
+fn test_legacy_database_is_backed_up_and_upgraded() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("canvas.db");
+
+    // A database from before versioning: tables exist but user_version is 0
+    {
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        conn.execute(
+            "INSERT INTO courses (id, name, last_sync) VALUES (1, 'Kept', '2024-01-01T00:00:00+00:00')",
+            [],
+        ).unwrap();
+    }
+
+    let db = Database::open_unmigrated(&path).unwrap();
+    db.migrate().unwrap();
+    assert_eq!(db.schema_version().unwrap(), latest_version());
+    assert_eq!(db.get_courses(false).unwrap()[0].name.as_deref(), Some("Kept"));
+
+    let backup = backup_path(&path, 0);
+    assert!(backup.exists());
+    let conn = Connection::open(&backup).unwrap();
+    assert_eq!(current_version(&conn).unwrap(), 0);
+    let name: String = conn.query_row("SELECT name FROM courses", [], |row| row.get(0)).unwrap();
+    assert_eq!(name, "Kept");
+}
+
+#[test]
+fn test_each_step_upgrades_from_the_previous_version() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+    conn.pragma_update(None, "user_version", 1).unwrap();
+
+    let report = migrate(&conn, None).unwrap();
+    assert_eq!((report.from, report.to), (1, latest_version()));
+    let indexes: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = 'assignments_course_id'",
+        [],
+        |row| row.get(0),
+    ).unwrap();
+    assert_eq!(indexes, 1);
+}
+
+#[test]
+fn test_newer_database_is_refused() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.pragma_update(None, "user_version", latest_version() + 1).unwrap();
+    assert!(matches!(migrate(&conn, None), Err(MigrationError::TooNew { .. })));
+}
+
+#[test]
+fn test_backup_path() {
+    assert_eq!(backup_path(Path::new("/data/canvas.db"), 3), Path::new("/data/canvas.db.v3.bak"));
+}
@@ -0,0 +1,155 @@
+use canvas_cli::client::CanvasClient;
+use canvas_cli::db::{CacheScope, Database};
+use canvas_cli::error::CanvasError;
+use canvas_cli::models::Course;
+use canvas_cli::offline::{format_age, DataSource, OfflineError, Origin};
+use chrono::{Duration, TimeZone, Utc};
+use mockito::Server;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+use tempfile::tempdir;
+
+mod mocks;
+use mocks::responses::*;
+
+fn cached_db(dir: &tempfile::TempDir) -> Database {
+    let db = Database::open_unmigrated(&dir.path().join("test.db")).unwrap();
+    db.migrate().unwrap();
+    db.sync_course(&Course {
+        id: 77,
+        name: Some("Cached Course".to_string()),
+        course_code: Some("CACHE1".to_string()),
+        enrollment_term_id: None,
+        total_students: None,
+        start_at: None,
+        end_at: None,
+        deleted_at: None,
+    }).unwrap();
+    db
+}
+
+/// A client for a port nothing listens on
+fn unreachable_client() -> CanvasClient {
+    CanvasClient::new("http://127.0.0.1:1", "fake-token")
+}
+
+/// A host that hangs up on every connection, failing the TLS handshake the
+/// way an unreachable one fails to connect.
+///
+/// Connecting to the returned address and writing `stop` ends the listener,
+/// which then yields how many connections it dropped before that.
+fn hanging_up_host() -> (String, JoinHandle<usize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let handle = thread::spawn(move || {
+        let mut dropped = 0;
+        for stream in listener.incoming() {
+            let mut first = [0; 1];
+            if stream.unwrap().read_exact(&mut first).is_ok() && &first == b"s" {
+                break;
+            }
+            dropped += 1;
+        }
+        dropped
+    });
+    (addr, handle)
+}
+
+#[test]
+fn test_offline_reads_cache() {
+    let dir = tempdir().unwrap();
+    let db = cached_db(&dir);
+    let source = DataSource::new(None, Some(&db));
+
+    let (courses, origin) = source.load(CacheScope::Courses, |c| c.get_courses(), |db| db.get_courses(false)).unwrap();
+    assert_eq!(courses[0].id, 77);
+    assert!(matches!(origin, Origin::Cache { fallback: false, .. }));
+
+    let result = source.load(CacheScope::Assignments(77), |c| c.get_assignments(77), |db| db.get_assignments(77, false));
+    assert!(matches!(result, Err(OfflineError::NotCached)));
+
+    let result = DataSource::new(None, None).load(CacheScope::Courses, |c| c.get_courses(), |db| db.get_courses(false));
+    assert!(matches!(result, Err(OfflineError::NoDatabase)));
+}
+
+#[test]
+fn test_online_prefers_network() {
+    let mut server = Server::new();
+    let _mock = server.mock("GET", "/api/v1/courses")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(COURSES_RESPONSE)
+        .create();
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = cached_db(&dir);
+
+    let (courses, origin) = DataSource::new(Some(&client), Some(&db))
+        .load(CacheScope::Courses, |c| c.get_courses(), |db| db.get_courses(false))
+        .unwrap();
+    assert_eq!(courses[0].id, 1234);
+    assert_eq!(origin, Origin::Network);
+}
+
+#[test]
+fn test_falls_back_when_unreachable() {
+    let client = unreachable_client();
+    let dir = tempdir().unwrap();
+    let db = cached_db(&dir);
+    let source = DataSource::new(Some(&client), Some(&db));
+
+    let (courses, origin) = source.load(CacheScope::Courses, |c| c.get_courses(), |db| db.get_courses(false)).unwrap();
+    assert_eq!(courses[0].id, 77);
+    assert!(matches!(origin, Origin::Cache { fallback: true, .. }));
+
+    // With nothing cached, the network error is reported
+    let result = source.load(CacheScope::Assignments(77), |c| c.get_assignments(77), |db| db.get_assignments(77, false));
+    assert!(matches!(result, Err(OfflineError::Canvas(CanvasError::Transport(_)))));
+}
+
+#[test]
+fn test_connection_failures_are_not_retried() {
+    let (addr, host) = hanging_up_host();
+    let client = CanvasClient::new(&format!("https://{addr}"), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = cached_db(&dir);
+
+    let (_, origin) = DataSource::new(Some(&client), Some(&db))
+        .load(CacheScope::Courses, |c| c.get_courses(), |db| db.get_courses(false))
+        .unwrap();
+    assert!(matches!(origin, Origin::Cache { fallback: true, .. }));
+
+    TcpStream::connect(&addr).unwrap().write_all(b"stop").unwrap();
+    assert_eq!(host.join().unwrap(), 1);
+}
+
+#[test]
+fn test_api_errors_do_not_fall_back() {
+    let mut server = Server::new();
+    let _mock = server.mock("GET", "/api/v1/courses")
+        .with_status(401)
+        .create();
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = cached_db(&dir);
+
+    let result = DataSource::new(Some(&client), Some(&db))
+        .load(CacheScope::Courses, |c| c.get_courses(), |db| db.get_courses(false));
+    assert!(matches!(result, Err(OfflineError::Canvas(CanvasError::Unauthorized))));
+}
+
+#[test]
+fn test_cache_notice() {
+    let synced_at = Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+    let origin = Origin::Cache { synced_at, fallback: false };
+    assert_eq!(
+        origin.notice(synced_at + Duration::hours(3)).unwrap(),
+        "Offline: showing data cached 3 hours ago (last sync 2024-03-01 09:30 UTC)"
+    );
+    assert_eq!(Origin::Network.notice(synced_at), None);
+
+    assert_eq!(format_age(Duration::seconds(20)), "less than a minute");
+    assert_eq!(format_age(Duration::minutes(1)), "1 minute");
+    assert_eq!(format_age(Duration::days(2) + Duration::hours(5)), "2 days");
+}
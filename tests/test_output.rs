@@ -0,0 +1,66 @@
+use canvas_cli::output::{csv_field, tsv_field, write_item, write_list, Format};
+use serde_json::json;
+
+fn render_list(format: Format, items: &[serde_json::Value], columns: &[&str]) -> String {
+    let mut out = Vec::new();
+    write_list(&mut out, format, items, columns).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn sample() -> Vec<serde_json::Value> {
+    vec![
+        json!({ "id": 1, "name": "Intro to Rust", "course_code": "CS101" }),
+        json!({ "id": 22, "name": "Systems, Networks", "course_code": null }),
+    ]
+}
+
+#[test]
+fn test_json_includes_all_fields() {
+    let text = render_list(Format::Json, &sample(), &["id"]);
+    let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(parsed, json!(sample()));
+}
+
+#[test]
+fn test_ndjson_one_object_per_line() {
+    let text = render_list(Format::Ndjson, &sample(), &[]);
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(serde_json::from_str::<serde_json::Value>(lines[1]).unwrap()["id"], 22);
+}
+
+#[test]
+fn test_csv_quotes_fields() {
+    let text = render_list(Format::Csv, &sample(), &[]);
+    assert_eq!(text, "id,name,course_code\n1,Intro to Rust,CS101\n22,\"Systems, Networks\",\n");
+    assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+}
+
+#[test]
+fn test_tsv_escapes_fields() {
+    let items = vec![json!({ "title": "a\tb", "message": "line1\nline2" })];
+    let text = render_list(Format::Tsv, &items, &[]);
+    assert_eq!(text, "title\tmessage\na\\tb\tline1\\nline2\n");
+    assert_eq!(tsv_field("back\\slash"), "back\\\\slash");
+}
+
+#[test]
+fn test_table_aligns_selected_columns() {
+    let text = render_list(Format::Table, &sample(), &["id", "name"]);
+    assert_eq!(text, "ID  NAME\n1   Intro to Rust\n22  Systems, Networks\n");
+}
+
+#[test]
+fn test_table_joins_scalar_arrays() {
+    let items = vec![json!({ "submission_types": ["online_upload", "online_url"] })];
+    let text = render_list(Format::Table, &items, &["submission_types"]);
+    assert_eq!(text.lines().nth(1), Some("online_upload, online_url"));
+}
+
+#[test]
+fn test_item_table_is_field_per_line() {
+    let mut out = Vec::new();
+    write_item(&mut out, Format::Table, &sample()[0], &["id", "course_code"]).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "id           1\ncourse_code  CS101\n");
+}
@@ -0,0 +1,129 @@
+use canvas_cli::render::{decode_entities, plain_text, render, FileRef, RenderOptions};
+
+/// Render without wrapping
+fn render_html(html: &str) -> String {
+    render(html, &RenderOptions::default()).text
+}
+
+#[test]
+fn test_headings_and_paragraphs() {
+    let html = "<h2>Week&nbsp;1</h2>\n<p>Read   <strong>chapter 1</strong>\nand <em>take notes</em>.</p><p>Line<br>break</p>";
+    assert_eq!(render_html(html), "## Week 1\n\nRead **chapter 1** and _take notes_.\n\nLine\nbreak");
+}
+
+#[test]
+fn test_lists() {
+    let html = "<ul><li>Intro</li><li>Sorting<ol><li>Merge sort</li><li>Quicksort</li></ol></li></ul><p>After</p>";
+    assert_eq!(render_html(html), "- Intro\n- Sorting\n  1. Merge sort\n  2. Quicksort\n\nAfter");
+
+    let html = "<ol start=\"3\"><li><p>Third</p><p>More about it</p></li></ol>";
+    assert_eq!(render_html(html), "3. Third\n   More about it");
+
+    let html = "<ol start=\"4294967295\"><li>Last</li><li>Past the end</li></ol>";
+    assert_eq!(render_html(html), "4294967295. Last\n4294967295. Past the end");
+}
+
+#[test]
+fn test_links_become_footnotes() {
+    let html = r#"<p>See the <a href="https://canvas.test/courses/1/pages/syllabus">syllabus</a> and
+        <a href="https://example.com">https://example.com</a>, then the
+        <a href="https://canvas.test/courses/1/pages/syllabus">syllabus</a> again.</p><img src="/img/chart.png" alt="Chart">"#;
+    assert_eq!(
+        render_html(html),
+        "See the syllabus[1] and https://example.com, then the syllabus[1] again.\n\n[Chart][2]\n\n\
+        [1] https://canvas.test/courses/1/pages/syllabus\n[2] /img/chart.png"
+    );
+}
+
+#[test]
+fn test_file_links_and_relative_links() {
+    let html = r#"<p>Slides: <a class="instructure_file_link" title="week1.pdf" href="/courses/1/files/5678/download?wrap=1">Week 1
+        slides</a>, <a href="/courses/1/files/5678?verifier=x">again</a> and <a href="/courses/1/pages/notes">notes</a>.</p>
+        <p><img src="https://canvas.test/courses/1/files/91/preview" alt="diagram.png"><a href="/files/7/download"></a></p>"#;
+    let options = RenderOptions { base_url: Some("https://canvas.test/".to_string()), ..RenderOptions::default() };
+    let rendered = render(html, &options);
+    assert_eq!(
+        rendered.text,
+        "Slides: Week 1 slides[1], again[1] and notes[2].\n\n[diagram.png][3][4]\n\n\
+        [1] week1.pdf (file 5678)\n[2] https://canvas.test/courses/1/pages/notes\n[3] diagram.png (file 91)\n[4] file 7 (file 7)"
+    );
+    assert_eq!(rendered.files, vec![
+        FileRef { id: 5678, name: "week1.pdf".to_string() },
+        FileRef { id: 91, name: "diagram.png".to_string() },
+        FileRef { id: 7, name: "file 7".to_string() },
+    ]);
+}
+
+#[test]
+fn test_wrapping() {
+    let html = "<p>The quick brown fox jumps over the lazy dog near the river bank.</p>\
+        <ul><li>A list item that is long enough to wrap around</li></ul>\
+        <blockquote><p>Quoted text that also needs wrapping here</p></blockquote>\
+        <p>https://canvas.test/a/link/longer/than/the/whole/line</p>";
+    let options = RenderOptions { width: Some(30), ..RenderOptions::default() };
+    assert_eq!(
+        render(html, &options).text,
+        "The quick brown fox jumps over\nthe lazy dog near the river\nbank.\n\n\
+        - A list item that is long\n  enough to wrap around\n\n\
+        > Quoted text that also needs\n> wrapping here\n\n\
+        https://canvas.test/a/link/longer/than/the/whole/line"
+    );
+}
+
+#[test]
+fn test_tables() {
+    let html = "<table><thead><tr><th>Day</th><th>Time</th></tr></thead>\
+        <tbody><tr><td>Monday</td><td>10:00</td></tr><tr><td>Thu</td><td>2<br>pm</td></tr></tbody></table><p>Done</p>";
+    assert_eq!(
+        render_html(html),
+        "| Day    | Time  |\n| ------ | ----- |\n| Monday | 10:00 |\n| Thu    | 2 pm  |\n\nDone"
+    );
+}
+
+#[test]
+fn test_nested_tables() {
+    let html = "<table><tr><th>Week</th><th>Topics</th></tr>\
+        <tr><td>1</td><td>Intro <table><tr><td>Mon</td><td>Lab</td></tr><tr><td>Wed</td><td>Quiz</td></tr></table> due</td></tr>\
+        <tr><td>2</td><td>Sorting</td></tr></table>";
+    assert_eq!(
+        render_html(html),
+        "| Week | Topics                      |\n| ---- | --------------------------- |\n\
+        | 1    | Intro Mon Lab; Wed Quiz due |\n| 2    | Sorting                     |"
+    );
+    assert_eq!(plain_text(html), "Week Topics 1 Intro Mon Lab; Wed Quiz due 2 Sorting");
+}
+
+#[test]
+fn test_quoted_angle_brackets_in_tags() {
+    let html = r#"<p><a title="a>b" href="https://example.com/x">link</a> and <img alt='1 > 0' src="/img/x.png"> after</p>"#;
+    assert_eq!(
+        render_html(html),
+        "link[1] and [1 > 0][2] after\n\n[1] https://example.com/x\n[2] /img/x.png"
+    );
+}
+
+#[test]
+fn test_preformatted_quotes_and_skipped_content() {
+    let html = "<style>p { color: red }</style><!-- note --><blockquote><p>Be kind</p></blockquote>\
+        <pre>fn main() {\n    println!(\"hi\");\n}</pre><script>alert(1)</script><hr><p>x &lt; y</p>";
+    assert_eq!(
+        render_html(html),
+        "> Be kind\n\n    fn main() {\n        println!(\"hi\");\n    }\n\n---\n\nx < y"
+    );
+}
+
+#[test]
+fn test_decode_entities() {
+    assert_eq!(decode_entities("a &amp; b &#39;c&#x27; &mdash; &bogus; & d"), "a & b 'c' — &bogus; & d");
+    assert_eq!(render_html("plain text"), "plain text");
+    assert_eq!(render_html(""), "");
+}
+
+#[test]
+fn test_plain_text() {
+    assert_eq!(plain_text("<p>Read&nbsp;<em>chapter</em>3</p>\n<ul><li>A &amp; B</li></ul>"), "Read chapter 3 A & B");
+    assert_eq!(plain_text("1 &lt; 2"), "1 < 2");
+    // No markup, footnotes or layout
+    let html = "<h1>Title</h1><table><tr><th>a</th><td>b</td></tr></table><a href=\"https://example.com\">link</a><hr>";
+    assert_eq!(plain_text(html), "Title a b link");
+}
@@ -0,0 +1,105 @@
+use canvas_cli::client::CanvasClient;
+use canvas_cli::db::Database;
+use canvas_cli::models::Course;
+use canvas_cli::resolve::{match_course, CourseResolver, ResolveError};
+use mockito::Server;
+use std::collections::BTreeMap;
+use tempfile::tempdir;
+
+mod mocks;
+use mocks::responses::*;
+
+fn course(id: u64, code: &str, name: &str) -> Course {
+    Course {
+        id,
+        name: Some(name.to_string()),
+        course_code: Some(code.to_string()),
+        enrollment_term_id: None,
+        total_students: None,
+        start_at: None,
+        end_at: None,
+        deleted_at: None,
+    }
+}
+
+fn catalog() -> Vec<Course> {
+    vec![
+        course(1, "CS101", "Intro to Programming"),
+        course(2, "CS350", "Operating Systems"),
+        course(3, "CS351", "Operating Systems Lab"),
+        course(4, "MATH221", "Linear Algebra"),
+    ]
+}
+
+#[test]
+fn test_match_by_code_and_name() {
+    let courses = catalog();
+    assert_eq!(match_course("cs350", &courses).unwrap().id, 2);
+    // An exact name wins over the longer name that also contains it
+    assert_eq!(match_course("Operating Systems", &courses).unwrap().id, 2);
+    assert_eq!(match_course("algebra", &courses).unwrap().id, 4);
+    assert_eq!(match_course("systems lab", &courses).unwrap().id, 3);
+}
+
+#[test]
+fn test_match_ambiguous_and_missing() {
+    let courses = catalog();
+    match match_course("operating", &courses) {
+        Err(ResolveError::Ambiguous { candidates, .. }) => {
+            assert_eq!(candidates, vec!["2 CS350 (Operating Systems)", "3 CS351 (Operating Systems Lab)"]);
+        }
+        other => panic!("expected an ambiguous match, got {:?}", other),
+    }
+    assert!(matches!(match_course("biology", &courses), Err(ResolveError::NotFound(q)) if q == "biology"));
+}
+
+#[test]
+fn test_resolve_ids_and_aliases_without_lookup() {
+    // No mocks: any request would fail
+    let server = Server::new();
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let aliases = BTreeMap::from([("os".to_string(), 2)]);
+    let resolver = CourseResolver::new(&aliases, None, Some(&client));
+
+    assert_eq!(resolver.resolve("1234").unwrap(), 1234);
+    assert_eq!(resolver.resolve("OS").unwrap(), 2);
+}
+
+#[test]
+fn test_resolve_prefers_local_database() {
+    let server = Server::new();
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = Database::open_unmigrated(&dir.path().join("test.db")).unwrap();
+    db.migrate().unwrap();
+    for course in catalog() {
+        db.sync_course(&course).unwrap();
+    }
+    let aliases = BTreeMap::new();
+    let resolver = CourseResolver::new(&aliases, Some(&db), Some(&client));
+
+    assert_eq!(resolver.resolve("MATH221").unwrap(), 4);
+    assert!(matches!(resolver.resolve("operating"), Err(ResolveError::Ambiguous { .. })));
+}
+
+#[test]
+fn test_resolve_falls_back_to_api() {
+    let mut server = Server::new();
+    let mock = server.mock("GET", "/api/v1/courses")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(COURSES_RESPONSE)
+        .expect(2)
+        .create();
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = Database::open_unmigrated(&dir.path().join("test.db")).unwrap();
+    db.migrate().unwrap();
+    db.sync_course(&course(1, "CS101", "Intro to Programming")).unwrap();
+    let aliases = BTreeMap::new();
+    let resolver = CourseResolver::new(&aliases, Some(&db), Some(&client));
+
+    assert_eq!(resolver.resolve("test101").unwrap(), 1234);
+    assert!(matches!(resolver.resolve("history"), Err(ResolveError::NotFound(_))));
+    mock.assert();
+}
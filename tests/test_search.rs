@@ -0,0 +1,10 @@
+use canvas_cli::search::*;
+
+#[test]
+fn test_match_expression() {
+    assert_eq!(match_expression("linked  lists").as_deref(), Some(r#""linked" "lists""#));
+    // Query syntax in what the user typed is matched literally
+    assert_eq!(match_expression(r#"C++ "quoted" OR-NOT"#).as_deref(), Some(r#""C++" """quoted""" "OR-NOT""#));
+    assert_eq!(match_expression(" \t"), None);
+}
+
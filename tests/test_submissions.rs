@@ -0,0 +1,115 @@
+use canvas_cli::client::CanvasClient;
+use canvas_cli::models::{Assignment, Submission};
+use canvas_cli::submissions::{self, RubricRow};
+use mockito::{Matcher, Server};
+use std::error::Error;
+use std::fs;
+use tempfile::tempdir;
+
+mod mocks;
+use mocks::responses::*;
+
+#[test]
+fn test_get_submission() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let mock = server.mock(
+        "GET",
+        "/api/v1/courses/1234/assignments/4321/submissions/self\
+        ?include[]=submission_comments&include[]=rubric_assessment&include[]=submission_history",
+    )
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(SUBMISSION_DETAILS_RESPONSE)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let submission = client.get_submission(1234, 4321)?;
+    mock.assert();
+    assert_eq!(submission.attempt, Some(2));
+    assert_eq!(submission.seconds_late, Some(7200));
+    assert_eq!(submission.attachments[0].display_name, "essay-v2.pdf");
+    assert_eq!(submission.submission_history.len(), 2);
+
+    let comment = &submission.submission_comments[0];
+    assert_eq!(comment.author_name, "Dr. Smith");
+    assert_eq!(comment.attempt, Some(2));
+    assert_eq!(comment.attachments[0].id, 701);
+
+    Ok(())
+}
+
+#[test]
+fn test_comment_on_submission_with_attachment() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let dir = tempdir()?;
+    let file_path = dir.path().join("revision.pdf");
+    fs::write(&file_path, b"%PDF-1.4 test")?;
+
+    let slot = server.mock("POST", "/api/v1/courses/1234/assignments/4321/submissions/self/comments/files")
+        .match_body(Matcher::PartialJsonString(r#"{"name": "revision.pdf", "size": 13}"#.to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!(r#"{{"upload_url": "{}/files_api", "upload_params": {{}}}}"#, server.url()))
+        .create();
+    let _upload = server.mock("POST", "/files_api")
+        .with_status(201)
+        .with_header("content-type", "application/json")
+        .with_body(FILE_RESPONSE)
+        .create();
+    let comment = server.mock("PUT", "/api/v1/courses/1234/assignments/4321/submissions/self")
+        .match_body(Matcher::Json(serde_json::json!({
+            "comment": { "text_comment": "Revised as discussed", "file_ids": [5678] }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(SUBMISSION_DETAILS_RESPONSE)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let submission = client.comment_on_submission(1234, 4321, "Revised as discussed", &[&file_path])?;
+    slot.assert();
+    comment.assert();
+    assert_eq!(submission.submission_comments.last().unwrap().id, 31);
+
+    Ok(())
+}
+
+#[test]
+fn test_attempts_oldest_first() {
+    let mut submission: Submission = serde_json::from_str(SUBMISSION_DETAILS_RESPONSE).unwrap();
+    let attempts: Vec<Option<u32>> = submissions::attempts(&submission).iter().map(|a| a.attempt).collect();
+    assert_eq!(attempts, vec![Some(1), Some(2)]);
+
+    // The placeholder Canvas lists before anything is submitted isn't an attempt
+    submission.submission_history = vec![Submission::default()];
+    assert!(submissions::attempts(&submission).is_empty());
+}
+
+#[test]
+fn test_rubric_rows() {
+    let assignment: Assignment = serde_json::from_str(ASSIGNMENT_WITH_RUBRIC_RESPONSE).unwrap();
+    let submission: Submission = serde_json::from_str(SUBMISSION_DETAILS_RESPONSE).unwrap();
+    let rows = submissions::rubric_rows(assignment.rubric.as_deref().unwrap(), &submission);
+    assert_eq!(rows, vec![
+        RubricRow {
+            criterion: "Argument".to_string(),
+            points: Some(6.0),
+            points_possible: Some(6.0),
+            rating: Some("Convincing".to_string()),
+            comments: None,
+        },
+        RubricRow {
+            criterion: "Sources".to_string(),
+            points: Some(1.5),
+            points_possible: Some(4.0),
+            rating: None,
+            comments: Some("Only one source".to_string()),
+        },
+        // No longer in the rubric
+        RubricRow { criterion: "_999".to_string(), points: Some(0.0), points_possible: None, rating: None, comments: None },
+    ]);
+
+    // Nothing to show for ungraded submissions
+    let ungraded = Submission { rubric_assessment: None, ..Submission::default() };
+    assert!(submissions::rubric_rows(assignment.rubric.as_deref().unwrap(), &ungraded).is_empty());
+}
@@ -0,0 +1,372 @@
+use canvas_cli::client::CanvasClient;
+use canvas_cli::db::Database;
+use canvas_cli::search::DocumentKind;
+use canvas_cli::sync::{Counts, Syncer};
+use mockito::{Mock, Server, ServerGuard};
+use tempfile::tempdir;
+
+mod mocks;
+use mocks::responses::*;
+
+/// Announcements are listed as discussion topics, all of them at once
+const ANNOUNCEMENTS_PATH: &str = "/api/v1/courses/1234/discussion_topics?only_announcements=true";
+
+fn json_mock(server: &mut ServerGuard, path: &str, body: &str) -> Mock {
+    server.mock("GET", path)
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(body)
+        .create()
+}
+
+fn counts(inserted: usize, updated: usize, unchanged: usize) -> Counts {
+    Counts { inserted, updated, unchanged, ..Counts::default() }
+}
+
+#[test]
+fn test_incremental_sync() {
+    let mut server = Server::new();
+    let _courses = json_mock(&mut server, "/api/v1/courses", COURSES_RESPONSE);
+    let _assignments = json_mock(&mut server, "/api/v1/courses/1234/assignments", ASSIGNMENTS_RESPONSE);
+    let _announcements = json_mock(&mut server, ANNOUNCEMENTS_PATH, ANNOUNCEMENTS_RESPONSE);
+    let _discussions = json_mock(
+        &mut server,
+        "/api/v1/courses/1234/discussion_topics?order_by=recent_activity",
+        DISCUSSIONS_BY_ACTIVITY_RESPONSE,
+    );
+    let _modules = json_mock(&mut server, "/api/v1/courses/1234/modules?include[]=items", MODULES_RESPONSE);
+    let _pages = json_mock(&mut server, "/api/v1/courses/1234/pages?include[]=body&sort=updated_at&order=desc", PAGES_RESPONSE);
+    // The module came back without inline items, so they're fetched on their own
+    let items = json_mock(&mut server, "/api/v1/courses/1234/modules/1234/items", MODULE_ITEMS_RESPONSE)
+        .expect(2);
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = Database::open_unmigrated(&dir.path().join("test.db")).unwrap();
+    db.migrate().unwrap();
+
+    let report = Syncer::new(&client, &db).run(|_| ()).unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.courses, 1);
+    assert_eq!(report.counts["courses"], counts(1, 0, 0));
+    assert_eq!(report.counts["assignments"], counts(1, 0, 0));
+    assert_eq!(report.counts["discussions"], counts(2, 0, 0));
+    assert_eq!(report.counts["modules"], counts(1, 0, 0));
+    assert_eq!(report.counts["module_items"], counts(1, 0, 0));
+    assert_eq!(report.counts["pages"], counts(2, 0, 0));
+    assert_eq!(db.get_page(1234, "test-page", false).unwrap().unwrap().page_id, 42);
+    let hits = db.search("syllabus", Some(1234), false, 10).unwrap().unwrap();
+    assert_eq!(hits.iter().map(|h| (h.kind, h.resource_id)).collect::<Vec<_>>(), vec![(DocumentKind::Page, 42)]);
+    // Announcements are only kept in the search index
+    assert_eq!(report.counts["announcements"], counts(1, 0, 0));
+    let hits = db.search("test announcement", Some(1234), false, 10).unwrap().unwrap();
+    assert_eq!(hits.iter().map(|h| h.resource_id).collect::<Vec<_>>(), vec![8765]);
+
+    // Nothing changed; only the still-active discussion is revisited, and no
+    // page was updated since
+    let report = Syncer::new(&client, &db).run(|_| ()).unwrap();
+    assert_eq!(report.counts["assignments"], counts(0, 0, 1));
+    assert_eq!(report.counts["discussions"], counts(0, 0, 1));
+    assert_eq!(report.counts["module_items"], counts(0, 0, 1));
+    assert_eq!(report.counts["announcements"], counts(0, 0, 1));
+    assert!(!report.counts.contains_key("pages"));
+    assert_eq!(db.get_pages(1234, false).unwrap().len(), 2);
+    items.assert();
+
+    // A full sync walks every discussion and page again
+    let report = Syncer::new(&client, &db).full(true).run(|_| ()).unwrap();
+    assert_eq!(report.counts["discussions"], counts(0, 0, 2));
+    assert_eq!(report.counts["pages"], counts(0, 0, 2));
+}
+
+#[test]
+fn test_failed_course_is_rolled_back() {
+    let mut server = Server::new();
+    let _courses = json_mock(&mut server, "/api/v1/courses", COURSES_RESPONSE);
+    let _discussions = json_mock(&mut server, "/api/v1/courses/1234/discussion_topics?order_by=recent_activity", "[]");
+    let _pages = json_mock(&mut server, "/api/v1/courses/1234/pages?include[]=body&sort=updated_at&order=desc", "[]");
+    let _modules = json_mock(&mut server, "/api/v1/courses/1234/modules?include[]=items", MODULES_WITH_ITEMS_RESPONSE);
+    let _assignments = json_mock(&mut server, "/api/v1/courses/1234/assignments", ASSIGNMENTS_RESPONSE);
+    // Announcements fail after assignments were already written
+    let announcements = server.mock("GET", ANNOUNCEMENTS_PATH)
+        .with_status(403)
+        .with_body(r#"{"errors":[{"message":"user not authorized to perform that action"}]}"#)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = Database::open_unmigrated(&dir.path().join("test.db")).unwrap();
+    db.migrate().unwrap();
+
+    let report = Syncer::new(&client, &db).run(|_| ()).unwrap();
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.errors[0].contains("announcements for course 1234"));
+    assert!(!report.counts.contains_key("assignments"));
+    // The course list itself was committed, but nothing inside the course
+    assert_eq!(report.counts["courses"], counts(1, 0, 0));
+    assert!(db.get_assignments(1234, true).unwrap().is_empty());
+    assert!(db.cursor("assignments", 1234).unwrap().is_none());
+    assert!(db.cursor("modules", 1234).unwrap().is_none());
+
+    let runs = db.sync_runs(10).unwrap();
+    assert_eq!(runs.len(), 1);
+    assert!(runs[0].finished_at.is_some());
+    assert_eq!(runs[0].errors, report.errors);
+    assert_eq!(runs[0].counts["courses"]["inserted"], 1);
+
+    // Once Canvas cooperates, the whole course goes in, module items inline
+    announcements.remove();
+    let _announcements = json_mock(&mut server, ANNOUNCEMENTS_PATH, "[]");
+    let report = Syncer::new(&client, &db).run(|_| ()).unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.counts["assignments"], counts(1, 0, 0));
+    assert_eq!(report.counts["module_items"], counts(1, 0, 0));
+    assert_eq!(db.get_module_items(1235, false).unwrap()[0].title, "Quiz 2");
+    assert!(db.cursor("modules", 1234).unwrap().is_some());
+    assert_eq!(db.sync_runs(10).unwrap().len(), 2);
+}
+
+#[test]
+fn test_deletions_leave_tombstones() {
+    let mut server = Server::new();
+    let _courses = json_mock(&mut server, "/api/v1/courses", COURSES_RESPONSE);
+    let assignments = json_mock(&mut server, "/api/v1/courses/1234/assignments", ASSIGNMENTS_RESPONSE);
+    let _announcements = json_mock(&mut server, ANNOUNCEMENTS_PATH, "[]");
+    let _discussions = json_mock(
+        &mut server,
+        "/api/v1/courses/1234/discussion_topics?order_by=recent_activity",
+        DISCUSSIONS_BY_ACTIVITY_RESPONSE,
+    );
+    let modules = json_mock(&mut server, "/api/v1/courses/1234/modules?include[]=items", MODULES_WITH_ITEMS_RESPONSE);
+    let _pages = json_mock(&mut server, "/api/v1/courses/1234/pages?include[]=body&sort=updated_at&order=desc", "[]");
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = Database::open_unmigrated(&dir.path().join("test.db")).unwrap();
+    db.migrate().unwrap();
+    Syncer::new(&client, &db).run(|_| ()).unwrap();
+
+    // The assignment and the module with its items disappear from Canvas
+    assignments.remove();
+    modules.remove();
+    let assignments = json_mock(&mut server, "/api/v1/courses/1234/assignments", "[]");
+    let _modules = json_mock(&mut server, "/api/v1/courses/1234/modules?include[]=items", "[]");
+
+    let report = Syncer::new(&client, &db).run(|_| ()).unwrap();
+    assert_eq!(report.counts["assignments"].deleted, 1);
+    assert_eq!(report.counts["modules"].deleted, 1);
+    assert_eq!(report.counts["module_items"].deleted, 1);
+    // The incremental discussion listing stopped early, so nothing is known to be gone
+    assert_eq!(report.counts["discussions"].deleted, 0);
+
+    assert!(db.get_assignments(1234, false).unwrap().is_empty());
+    let tombstones = db.get_assignments(1234, true).unwrap();
+    assert_eq!(tombstones.len(), 1);
+    assert!(tombstones[0].deleted_at.is_some());
+    assert!(db.get_modules(1234, false).unwrap().is_empty());
+    assert!(db.get_module_items(1235, false).unwrap().is_empty());
+    assert_eq!(db.get_module_items(1235, true).unwrap().len(), 1);
+
+    // A row that comes back is restored
+    assignments.remove();
+    let _assignments = json_mock(&mut server, "/api/v1/courses/1234/assignments", ASSIGNMENTS_RESPONSE);
+    let report = Syncer::new(&client, &db).run(|_| ()).unwrap();
+    assert_eq!(report.counts["assignments"], counts(0, 1, 0));
+    assert!(db.get_assignments(1234, false).unwrap()[0].deleted_at.is_none());
+}
+
+#[test]
+fn test_deleted_rows_leave_search_results() {
+    let mut server = Server::new();
+    let _courses = json_mock(&mut server, "/api/v1/courses", COURSES_RESPONSE);
+    let assignments = json_mock(&mut server, "/api/v1/courses/1234/assignments", ASSIGNMENTS_RESPONSE);
+    // The listing is complete however old an announcement is
+    let welcome = serde_json::json!({
+        "id": 8700,
+        "title": "Welcome",
+        "message": "Read the test guidelines",
+        "posted_at": "2019-09-01T00:00:00Z",
+        "url": "https://canvas.test/announcements/8700"
+    });
+    let mut listing: Vec<serde_json::Value> = serde_json::from_str(ANNOUNCEMENTS_RESPONSE).unwrap();
+    listing.push(welcome.clone());
+    let announcements = json_mock(&mut server, ANNOUNCEMENTS_PATH, &serde_json::to_string(&listing).unwrap());
+    let _discussions = json_mock(&mut server, "/api/v1/courses/1234/discussion_topics?order_by=recent_activity", "[]");
+    let _modules = json_mock(&mut server, "/api/v1/courses/1234/modules?include[]=items", "[]");
+    let pages_path = "/api/v1/courses/1234/pages?include[]=body&sort=updated_at&order=desc";
+    let pages = json_mock(&mut server, pages_path, PAGES_RESPONSE);
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = Database::open_unmigrated(&dir.path().join("test.db")).unwrap();
+    db.migrate().unwrap();
+    Syncer::new(&client, &db).run(|_| ()).unwrap();
+    let found = |include_deleted: bool| {
+        let mut hits: Vec<_> = db.search("test", Some(1234), include_deleted, 10).unwrap().unwrap()
+            .into_iter()
+            .map(|h| (h.kind, h.resource_id, h.deleted_at.is_some()))
+            .collect();
+        hits.sort_by_key(|&(kind, id, _)| (kind.as_str(), id));
+        hits
+    };
+    assert_eq!(found(false), vec![
+        (DocumentKind::Announcement, 8700, false),
+        (DocumentKind::Announcement, 8765, false),
+        (DocumentKind::Assignment, 4321, false),
+        (DocumentKind::Page, 42, false),
+    ]);
+
+    // The assignment, the pages and the newer announcement are deleted in Canvas
+    for mock in [assignments, announcements, pages] {
+        mock.remove();
+    }
+    let _assignments = json_mock(&mut server, "/api/v1/courses/1234/assignments", "[]");
+    let _announcements = json_mock(&mut server, ANNOUNCEMENTS_PATH, &serde_json::json!([welcome]).to_string());
+    let _pages = json_mock(&mut server, pages_path, "[]");
+
+    // An incremental sync already notices, and the old announcement stays
+    let report = Syncer::new(&client, &db).run(|_| ()).unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.counts["announcements"].deleted, 1);
+    assert_eq!(found(false), vec![(DocumentKind::Announcement, 8700, false)]);
+
+    let report = Syncer::new(&client, &db).full(true).run(|_| ()).unwrap();
+    assert_eq!(report.counts["announcements"], counts(0, 0, 1));
+    assert_eq!(found(false), vec![(DocumentKind::Announcement, 8700, false)]);
+    assert_eq!(found(true), vec![
+        (DocumentKind::Announcement, 8700, false),
+        (DocumentKind::Announcement, 8765, true),
+        (DocumentKind::Assignment, 4321, true),
+        (DocumentKind::Page, 42, true),
+    ]);
+}
+
+#[test]
+fn test_hidden_pages_are_skipped() {
+    let mut server = Server::new();
+    let _courses = json_mock(&mut server, "/api/v1/courses", COURSES_RESPONSE);
+    let _assignments = json_mock(&mut server, "/api/v1/courses/1234/assignments", "[]");
+    let _announcements = json_mock(&mut server, ANNOUNCEMENTS_PATH, "[]");
+    let _discussions = json_mock(&mut server, "/api/v1/courses/1234/discussion_topics?order_by=recent_activity", "[]");
+    let _modules = json_mock(&mut server, "/api/v1/courses/1234/modules?include[]=items", "[]");
+    let pages_path = "/api/v1/courses/1234/pages?include[]=body&sort=updated_at&order=desc";
+    let pages = json_mock(&mut server, pages_path, PAGES_RESPONSE);
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = Database::open_unmigrated(&dir.path().join("test.db")).unwrap();
+    db.migrate().unwrap();
+    Syncer::new(&client, &db).run(|_| ()).unwrap();
+
+    // Turning pages off for the course doesn't fail its sync or discard the cached pages
+    pages.remove();
+    let _pages = server.mock("GET", pages_path)
+        .with_status(401)
+        .with_body(r#"{"status":"unauthorized","errors":[{"message":"That page has been disabled for this course"}]}"#)
+        .create();
+    let report = Syncer::new(&client, &db).run(|_| ()).unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert!(!report.counts.contains_key("pages"));
+    assert_eq!(db.get_pages(1234, false).unwrap().len(), 2);
+}
+
+#[test]
+fn test_changes_are_logged() {
+    let mut server = Server::new();
+    let _courses = json_mock(&mut server, "/api/v1/courses", COURSES_RESPONSE);
+    let assignments = json_mock(&mut server, "/api/v1/courses/1234/assignments", ASSIGNMENTS_RESPONSE);
+    let announcements = json_mock(&mut server, ANNOUNCEMENTS_PATH, ANNOUNCEMENTS_RESPONSE);
+    let discussions_path = "/api/v1/courses/1234/discussion_topics?order_by=recent_activity";
+    let discussions = json_mock(&mut server, discussions_path, DISCUSSIONS_BY_ACTIVITY_RESPONSE);
+    let modules_path = "/api/v1/courses/1234/modules?include[]=items";
+    let locked = MODULES_WITH_ITEMS_RESPONSE.replace(r#""state": "active""#, r#""state": "locked""#);
+    let modules = json_mock(&mut server, modules_path, &locked);
+    let _pages = json_mock(&mut server, "/api/v1/courses/1234/pages?include[]=body&sort=updated_at&order=desc", "[]");
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = Database::open_unmigrated(&dir.path().join("test.db")).unwrap();
+    db.migrate().unwrap();
+
+    // The first sync only sets the baseline
+    let report = Syncer::new(&client, &db).run(|_| ()).unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.changes, 0);
+
+    for mock in [assignments, announcements, discussions, modules] {
+        mock.remove();
+    }
+    let moved = ASSIGNMENTS_RESPONSE
+        .replace("2024-02-01T23:59:59Z", "2024-02-08T23:59:59Z")
+        .replace("100.0", "120.0");
+    let _assignments = json_mock(&mut server, "/api/v1/courses/1234/assignments", &moved);
+    let posted = ANNOUNCEMENTS_RESPONSE.replacen("[", r#"[{
+        "id": 8766,
+        "title": "Room change",
+        "message": "We moved",
+        "posted_at": "2024-01-05T00:00:00Z",
+        "url": "https://canvas.test/announcements/8766"
+    },"#, 1);
+    let _announcements = json_mock(&mut server, ANNOUNCEMENTS_PATH, &posted);
+    let replied = DISCUSSIONS_BY_ACTIVITY_RESPONSE
+        .replace("2999-01-01T00:00:00Z", "3000-01-01T00:00:00Z")
+        .replace(r#""discussion_subentry_count": 40"#, r#""discussion_subentry_count": 42"#);
+    let _discussions = json_mock(&mut server, discussions_path, &replied);
+    let _modules = json_mock(&mut server, modules_path, MODULES_WITH_ITEMS_RESPONSE);
+
+    let report = Syncer::new(&client, &db).run(|_| ()).unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.changes, 5);
+
+    let changes = db.changes_since(None, None).unwrap();
+    let logged: Vec<_> = changes.iter()
+        .map(|c| (c.kind.as_str(), c.resource_id, c.detail.as_deref()))
+        .collect();
+    assert_eq!(logged, vec![
+        ("due_date_changed", 4321, Some("2024-02-01 23:59 UTC -> 2024-02-08 23:59 UTC")),
+        ("points_changed", 4321, Some("100 pts -> 120 pts")),
+        ("new_announcement", 8766, Some("posted 2024-01-05 00:00 UTC")),
+        ("new_reply", 4322, Some("2 new replies")),
+        ("module_unlocked", 1235, None),
+    ]);
+    assert_eq!(changes[0].course_code.as_deref(), Some("TEST101"));
+
+    // Nothing further happened, so nothing more is logged
+    let report = Syncer::new(&client, &db).run(|_| ()).unwrap();
+    assert_eq!(report.changes, 0);
+    assert!(db.changes_since(Some(changes[0].recorded_at), None).unwrap().is_empty());
+    assert!(db.changes_since(None, Some(999)).unwrap().is_empty());
+}
+
+#[test]
+fn test_courses_are_fetched_concurrently_and_written_in_order() {
+    let mut server = Server::new();
+    let courses: Vec<serde_json::Value> = (1..=3)
+        .map(|id| serde_json::json!({ "id": id, "name": format!("Course {}", id), "course_code": format!("C{}", id) }))
+        .collect();
+    let _courses = json_mock(&mut server, "/api/v1/courses", &serde_json::to_string(&courses).unwrap());
+    let mut mocks = Vec::new();
+    for id in 1..=3 {
+        let assignments = ASSIGNMENTS_RESPONSE.replace("4321", &format!("{}", 4320 + id));
+        mocks.push(json_mock(&mut server, &format!("/api/v1/courses/{}/assignments", id), &assignments));
+        mocks.push(json_mock(&mut server, &format!("/api/v1/courses/{}/discussion_topics?only_announcements=true", id), "[]"));
+        mocks.push(json_mock(&mut server, &format!("/api/v1/courses/{}/discussion_topics?order_by=recent_activity", id), "[]"));
+        mocks.push(json_mock(&mut server, &format!("/api/v1/courses/{}/modules?include[]=items", id), "[]"));
+        mocks.push(json_mock(&mut server, &format!("/api/v1/courses/{}/pages?include[]=body&sort=updated_at&order=desc", id), "[]"));
+    }
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let dir = tempdir().unwrap();
+    let db = Database::open_unmigrated(&dir.path().join("test.db")).unwrap();
+    db.migrate().unwrap();
+
+    let seen = std::cell::RefCell::new(Vec::new());
+    let report = Syncer::new(&client, &db).jobs(3).run(|line| seen.borrow_mut().push(line)).unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.courses, 3);
+    assert_eq!(report.counts["assignments"], counts(3, 0, 0));
+    assert_eq!(seen.into_inner(), vec!["Syncing course: Course 1", "Syncing course: Course 2", "Syncing course: Course 3"]);
+    for id in 1..=3 {
+        assert_eq!(db.get_assignments(id, false).unwrap()[0].id, 4320 + id);
+    }
+}
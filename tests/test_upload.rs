@@ -0,0 +1,230 @@
+use canvas_cli::client::CanvasClient;
+use canvas_cli::compose::TextSource;
+use canvas_cli::error::CanvasError;
+use canvas_cli::upload::check_extension;
+use mockito::{Matcher, Server};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+mod mocks;
+use mocks::responses::*;
+
+#[test]
+fn test_submit_assignment_uploads_files() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let dir = tempdir()?;
+    let file_path = dir.path().join("essay.pdf");
+    fs::write(&file_path, b"%PDF-1.4 test")?;
+
+    let _assignment = server.mock("GET", "/api/v1/courses/1234/assignments/4321")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(ASSIGNMENT_RESPONSE)
+        .create();
+    let slot = server.mock("POST", "/api/v1/courses/1234/assignments/4321/submissions/self/files")
+        .match_body(Matcher::PartialJsonString(r#"{"name": "essay.pdf", "size": 13}"#.to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!(
+            r#"{{"upload_url": "{}/files_api", "upload_params": {{"key": "/users/1/essay.pdf"}}}}"#,
+            server.url()
+        ))
+        .create();
+    let upload = server.mock("POST", "/files_api")
+        .match_header("authorization", Matcher::Missing)
+        .match_body(Matcher::Regex("/users/1/essay.pdf".to_string()))
+        .with_status(302)
+        .with_header("location", &format!("{}/api/v1/files/5678/create_success", server.url()))
+        .create();
+    let confirm = server.mock("GET", "/api/v1/files/5678/create_success")
+        .match_header("authorization", "Bearer fake-token")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(FILE_RESPONSE)
+        .create();
+    let submit = server.mock("POST", "/api/v1/courses/1234/assignments/4321/submissions")
+        .match_body(Matcher::PartialJsonString(
+            r#"{"submission": {"submission_type": "online_upload", "file_ids": [5678]}}"#.to_string()
+        ))
+        .with_status(201)
+        .with_header("content-type", "application/json")
+        .with_body(SUBMISSION_RESPONSE)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let assignment = client.get_assignment(1234, 4321)?;
+    let submission = client.submit_assignment(1234, &assignment, &[&file_path])?;
+
+    assert_eq!(submission.assignment_id, 4321);
+    slot.assert();
+    upload.assert();
+    confirm.assert();
+    submit.assert();
+
+    Ok(())
+}
+
+#[test]
+fn test_upload_without_redirect() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let dir = tempdir()?;
+    let file_path = dir.path().join("test.pdf");
+    fs::write(&file_path, b"data")?;
+
+    let _slot = server.mock("POST", "/api/v1/users/self/files")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!(r#"{{"upload_url": "{}/files_api", "upload_params": {{}}}}"#, server.url()))
+        .create();
+    let _upload = server.mock("POST", "/files_api")
+        .with_status(201)
+        .with_header("content-type", "application/json")
+        .with_body(FILE_RESPONSE)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let file = client.upload_file("users/self/files", &file_path)?;
+
+    assert_eq!(file.id, 5678);
+    assert_eq!(file.display_name, "test.pdf");
+
+    Ok(())
+}
+
+#[test]
+fn test_disallowed_extension_is_rejected_before_upload() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+    let dir = tempdir()?;
+    let file_path = dir.path().join("essay.exe");
+    fs::write(&file_path, b"MZ")?;
+
+    let _assignment = server.mock("GET", "/api/v1/courses/1234/assignments/4321")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(ASSIGNMENT_RESPONSE)
+        .create();
+    let slot = server.mock("POST", "/api/v1/courses/1234/assignments/4321/submissions/self/files")
+        .expect(0)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let assignment = client.get_assignment(1234, 4321)?;
+    let result = client.submit_assignment(1234, &assignment, &[&file_path]);
+
+    assert!(matches!(result, Err(CanvasError::InvalidFile(_))));
+    slot.assert();
+
+    Ok(())
+}
+
+#[test]
+fn test_check_extension() {
+    let allowed = vec!["pdf".to_string(), "DOCX".to_string()];
+
+    assert!(check_extension(Path::new("a.pdf"), Some(&allowed)).is_ok());
+    assert!(check_extension(Path::new("a.PDF"), Some(&allowed)).is_ok());
+    assert!(check_extension(Path::new("a.docx"), Some(&allowed)).is_ok());
+    assert!(check_extension(Path::new("a.txt"), Some(&allowed)).is_err());
+    assert!(check_extension(Path::new("noext"), Some(&allowed)).is_err());
+    assert!(check_extension(Path::new("a.txt"), None).is_ok());
+    assert!(check_extension(Path::new("a.txt"), Some(&[])).is_ok());
+}
+
+#[test]
+fn test_submit_text_entry() -> Result<(), Box<dyn Error>> {
+    let mut server = Server::new();
+
+    // The assignment already fetched is checked, not fetched again
+    let fetch = server.mock("GET", "/api/v1/courses/1234/assignments/4322")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(TEXT_ASSIGNMENT_RESPONSE)
+        .expect(1)
+        .create();
+    let submit = server.mock("POST", "/api/v1/courses/1234/assignments/4322/submissions")
+        .match_body(Matcher::PartialJsonString(
+            r#"{"submission": {"submission_type": "online_text_entry", "body": "<p>Hello</p>"}}"#.to_string()
+        ))
+        .with_status(201)
+        .with_header("content-type", "application/json")
+        .with_body(SUBMISSION_RESPONSE)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let assignment = client.get_assignment(1234, 4322)?;
+    client.submit_text_entry(1234, &assignment, "<p>Hello</p>")?;
+    submit.assert();
+    fetch.assert();
+
+    Ok(())
+}
+
+#[test]
+fn test_unsupported_submission_type_is_rejected() {
+    let mut server = Server::new();
+
+    let _assignment = server.mock("GET", "/api/v1/courses/1234/assignments/4322")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(TEXT_ASSIGNMENT_RESPONSE)
+        .create();
+    let submit = server.mock("POST", "/api/v1/courses/1234/assignments/4322/submissions")
+        .expect(0)
+        .create();
+
+    let client = CanvasClient::new(&server.url(), "fake-token");
+    let assignment = client.get_assignment(1234, 4322).unwrap();
+    match client.submit_url(1234, &assignment, "https://example.com") {
+        Err(CanvasError::UnsupportedSubmissionType { requested, allowed }) => {
+            assert_eq!(requested, "online_url");
+            assert_eq!(allowed, vec!["online_text_entry"]);
+        }
+        other => panic!("Expected unsupported submission type, got {:?}", other.map(|s| s.id)),
+    }
+    submit.assert();
+}
+
+#[test]
+fn test_markdown_is_converted_to_html() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let markdown = dir.path().join("essay.md");
+    fs::write(&markdown, "# Title\n\nSome *emphasis*.")?;
+    let html = dir.path().join("essay.html");
+    fs::write(&html, "<p>Already *HTML*</p>")?;
+
+    assert_eq!(
+        TextSource::File(markdown).read_html()?,
+        "<h1>Title</h1>\n<p>Some <em>emphasis</em>.</p>\n"
+    );
+    assert_eq!(TextSource::File(html).read_html()?, "<p>Already *HTML*</p>");
+    assert!(TextSource::Inline("  \n".to_string()).read_html().is_err());
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_editor_draft_is_private_and_removed() -> Result<(), Box<dyn Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // The "editor" records the draft's path and mode, then appends a line
+    let dir = tempdir()?;
+    let editor = dir.path().join("editor.sh");
+    let log = dir.path().join("draft");
+    fs::write(&editor, format!(
+        "#!/bin/sh\necho \"$1\" > {log}\nls -l \"$1\" | cut -c1-10 >> {log}\necho edited >> \"$1\"\n",
+        log = log.display()
+    ))?;
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o700))?;
+
+    let text = temp_env::with_vars([("VISUAL", Some(editor.as_os_str()))], || canvas_cli::compose::edit_text("draft\n"))?;
+    assert_eq!(text, "draft\nedited\n");
+    let log = fs::read_to_string(&log)?;
+    let mut lines = log.lines();
+    let draft = Path::new(lines.next().unwrap());
+    assert_eq!(lines.next(), Some("-rw-------"));
+    assert!(!draft.exists());
+    Ok(())
+}